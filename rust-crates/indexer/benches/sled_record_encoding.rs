@@ -0,0 +1,156 @@
+// Compares sled write throughput and on-disk size across record encodings, replaying
+// a catch-up: one `commit_block` per block with a busy table.
+//
+//     cargo bench -p indexer --bench sled_record_encoding
+use fuels::types::{
+    Address,
+    Identity,
+};
+use indexer::{
+    app::{
+        sled_storage::{
+            RecordEncoding,
+            SledSnapshotStorage,
+        },
+        snapshot_storage::{
+            BlockChanges,
+            SnapshotStorage,
+        },
+    },
+    events::{
+        Modifier,
+        Strap,
+        StrapKind,
+    },
+    snapshot::{
+        ALL_ROLLS,
+        AccountBetKind,
+        AccountBetPlacement,
+        AccountRollBets,
+        AccountSnapshot,
+        OverviewSnapshot,
+        TableAccountBets,
+    },
+};
+use std::time::Instant;
+use tempdir::TempDir;
+
+const BLOCKS: u32 = 1_000;
+const PLAYERS: u8 = 40;
+// Accounts whose snapshots change in each block
+const ACCOUNTS_PER_BLOCK: u8 = 4;
+
+fn player(index: u8) -> Identity {
+    Identity::Address(Address::from([index; 32]))
+}
+
+fn roll_bets(index: u8) -> Vec<AccountRollBets> {
+    ALL_ROLLS
+        .iter()
+        .enumerate()
+        .map(|(position, roll)| AccountRollBets {
+            roll: *roll,
+            bets: vec![
+                AccountBetPlacement {
+                    bet_roll_index: position as u32,
+                    amount: 1_000 + index as u64,
+                    kind: AccountBetKind::Chip,
+                },
+                AccountBetPlacement {
+                    bet_roll_index: position as u32,
+                    amount: 1,
+                    kind: AccountBetKind::Strap(Strap {
+                        level: 1,
+                        kind: StrapKind::Hat,
+                        modifier: Modifier::Lucky,
+                    }),
+                },
+            ],
+        })
+        .collect()
+}
+
+// Snapshot fields are crate private, so fill them in through serde
+fn busy_overview(height: u32) -> OverviewSnapshot {
+    let table_bets: Vec<TableAccountBets> = (0..PLAYERS)
+        .map(|index| TableAccountBets {
+            identity: player(index),
+            per_roll_bets: roll_bets(index),
+        })
+        .collect();
+    let mut overview = serde_json::to_value(OverviewSnapshot::new()).unwrap();
+    overview["game_id"] = 1.into();
+    overview["current_block_height"] = height.into();
+    overview["pot_size"] = 5_000_000.into();
+    overview["next_roll_height"] = (height + 10).into();
+    overview["table_bets"] = serde_json::to_value(table_bets).unwrap();
+    serde_json::from_value(overview).unwrap()
+}
+
+fn account(index: u8) -> AccountSnapshot {
+    AccountSnapshot {
+        total_chip_bet: 11_000 + index as u64,
+        per_roll_bets: roll_bets(index),
+        ..AccountSnapshot::default()
+    }
+}
+
+fn average_value_size(tree: &sled::Tree) -> usize {
+    let (count, total) = tree.iter().fold((0, 0), |(count, total), entry| {
+        let (_, value) = entry.unwrap();
+        (count + 1, total + value.len())
+    });
+    total / count.max(1)
+}
+
+fn blocks() -> Vec<BlockChanges> {
+    (1..=BLOCKS)
+        .map(|height| {
+            let accounts = (0..ACCOUNTS_PER_BLOCK)
+                .map(|offset| {
+                    let index = (height as u8).wrapping_add(offset) % PLAYERS;
+                    (player(index), 1, account(index), height)
+                })
+                .collect();
+            BlockChanges {
+                overview: Some((busy_overview(height), height)),
+                accounts,
+                ..BlockChanges::default()
+            }
+        })
+        .collect()
+}
+
+fn run(encoding: RecordEncoding, blocks: &[BlockChanges]) {
+    let temp_dir = TempDir::new("sled_record_encoding").unwrap();
+    let db = sled::Config::default()
+        .path(temp_dir.path())
+        .open()
+        .unwrap();
+    let mut storage = SledSnapshotStorage::new(&db)
+        .unwrap()
+        .with_encoding(encoding);
+
+    let start = Instant::now();
+    for changes in blocks {
+        storage.commit_block(changes).unwrap();
+    }
+    db.flush().unwrap();
+    let elapsed = start.elapsed();
+
+    let overview_size = average_value_size(&db.open_tree("snapshot_overview").unwrap());
+    let account_size = average_value_size(&db.open_tree("account_snapshots").unwrap());
+    println!(
+        "{encoding:?}: {BLOCKS} blocks in {elapsed:.2?} ({:.0} blocks/s), \
+         overview {overview_size} B, account {account_size} B, {} KiB on disk",
+        BLOCKS as f64 / elapsed.as_secs_f64(),
+        db.size_on_disk().unwrap() / 1024,
+    );
+}
+
+fn main() {
+    let blocks = blocks();
+    for encoding in [RecordEncoding::Json, RecordEncoding::MessagePack] {
+        run(encoding, &blocks);
+    }
+}
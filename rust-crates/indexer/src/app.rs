@@ -1,25 +1,32 @@
 use crate::{
     Result,
     app::{
-        event_source::EventSource,
+        event_source::{
+            EventBatch,
+            EventSource,
+        },
+        metrics::IndexerMetrics,
         query_api::{
-            AccountSnapshotQuery,
-            HistoricalAccountSnapshotQuery,
-            HistoricalSnapshotQuery,
             Query,
             QueryAPI,
+            answer_query,
         },
         snapshot_storage::{
+            BlockChanges,
             MetadataStorage,
             SnapshotStorage,
         },
+        strap_supply::strap_bet_upgrades,
+        working_set::WorkingSet,
     },
     events::{
+        BlockMetadataEvent,
         ClaimRewardsEvent,
         ContractEvent,
         Event,
         FundPotEvent,
         InitializedEvent,
+        InsufficientHouseWithdrawalEvent,
         Modifier,
         ModifierTriggeredEvent,
         NewGameEvent,
@@ -29,6 +36,7 @@ use crate::{
         Roll,
         RollEvent,
         Strap,
+        WithdrawHousePotEvent,
     },
     snapshot::{
         ALL_ROLLS,
@@ -37,12 +45,17 @@ use crate::{
         AccountRollBets,
         AccountSnapshot,
         ActiveModifier,
+        GameWorkingState,
+        HistoricalAccountSnapshot,
         HistoricalSnapshot,
+        HouseLedgerEntry,
         ModifierShopEntry,
         OverviewSnapshot,
+        PlayerGameStats,
+        StrapSupplyChange,
+        StrapSupplyChangeKind,
     },
 };
-use anyhow::anyhow;
 use fuels::{
     tx::ContractIdExt,
     types::{
@@ -50,6 +63,7 @@ use fuels::{
         Identity,
     },
 };
+use std::time::Instant;
 
 #[cfg(test)]
 mod tests;
@@ -64,21 +78,39 @@ pub mod in_memory_metadata_storage;
 
 pub mod sled_storage;
 
+pub mod sqlite_storage;
+
+pub mod event_archive;
 pub mod event_source;
+pub mod graphql;
+pub mod health;
+pub mod identity;
+pub mod leaderboard;
+pub mod metrics;
 pub mod query_api;
+pub mod roll_stats;
+pub mod shared_event_source;
 pub mod snapshot_storage;
+#[cfg(any(test, feature = "conformance"))]
+pub mod storage_conformance;
+pub mod strap_supply;
+pub mod working_set;
+
+// Fuel networks produce a block roughly every second
+const DEFAULT_BLOCK_TIME_SECS: u64 = 1;
+// Number of blocks to look back over when estimating the average block time
+const BLOCK_TIME_SAMPLE_WINDOW: u32 = 100;
 
 pub struct App<Events, API, Snapshots, Metadata> {
     events: Events,
     api: API,
-    snapshots: Snapshots,
+    snapshots: WorkingSet<Snapshots>,
     metadata: Metadata,
     contract_id: ContractId,
-    historical_modifiers: Vec<ActiveModifier>,
+    working_state: GameWorkingState,
     roll_frequency: Option<u32>,
     first_roll_height: Option<u32>,
-    modifier_triggered: Vec<Modifier>,
-    modifier_purchased: Vec<Modifier>,
+    metrics: IndexerMetrics,
 }
 
 fn roll_to_index(roll: &Roll) -> usize {
@@ -98,6 +130,19 @@ fn roll_to_index(roll: &Roll) -> usize {
     }
 }
 
+fn shop_modifiers(
+    snapshot: &OverviewSnapshot,
+    include: impl Fn(&ModifierShopEntry) -> bool,
+) -> Vec<Modifier> {
+    let mut modifiers = Vec::new();
+    for entry in snapshot.modifier_shop.iter().filter(|entry| include(entry)) {
+        if !modifiers.contains(&entry.modifier) {
+            modifiers.push(entry.modifier);
+        }
+    }
+    modifiers
+}
+
 fn accumulate_strap(bets: &mut Vec<(Strap, u64)>, strap: &Strap, amount: u64) {
     if let Some(idx) = bets.iter().position(|(existing, _)| existing == strap) {
         bets[idx].1 = bets[idx].1.saturating_add(amount);
@@ -117,27 +162,84 @@ where
         metadata: Metadata,
         contract_id: ContractId,
     ) -> Self {
-        let (roll_frequency, first_roll_height) = snapshots
-            .latest_snapshot()
-            .ok()
-            .map(|(snapshot, _)| (snapshot.roll_frequency, snapshot.first_roll_height))
-            .unwrap_or((None, None));
-        Self {
+        let mut app = Self {
             events,
             api,
-            snapshots,
+            snapshots: WorkingSet::new(snapshots),
             metadata,
             contract_id,
-            historical_modifiers: Vec::new(),
-            roll_frequency,
-            first_roll_height,
-            modifier_triggered: Vec::new(),
-            modifier_purchased: Vec::new(),
+            working_state: GameWorkingState::default(),
+            roll_frequency: None,
+            first_roll_height: None,
+            metrics: IndexerMetrics::new(),
+        };
+        app.restore_game_state();
+        app
+    }
+
+    /// Record event processing into `metrics`, e.g. the ones the query server exposes
+    pub fn with_metrics(mut self, metrics: IndexerMetrics) -> Self {
+        if let Ok((snapshot, height)) = self.snapshots.latest_snapshot() {
+            metrics.record_snapshot(&snapshot, height);
         }
+        self.metrics = metrics;
+        self
+    }
+
+    // Reload the game state kept in memory from what has been committed to storage
+    fn restore_game_state(&mut self) {
+        (self.roll_frequency, self.first_roll_height) = self
+            .snapshots
+            .latest_snapshot()
+            .ok()
+            .map(|(snapshot, _)| (snapshot.roll_frequency, snapshot.first_roll_height))
+            .unwrap_or((None, None));
+        self.working_state = self
+            .snapshots
+            .latest_working_state()
+            .ok()
+            .flatten()
+            .map(|(state, _)| state)
+            .unwrap_or_default();
     }
 
     fn refresh_height(&self, snapshot: &mut OverviewSnapshot, height: u32) {
         snapshot.current_block_height = height;
+        snapshot.next_roll_time = snapshot
+            .next_roll_height
+            .and_then(|next_roll_height| self.estimate_block_time(next_roll_height));
+    }
+
+    /// Timestamp of the block at `height`, extrapolated from the latest known block
+    /// timestamp if that block hasn't been seen yet
+    fn estimate_block_time(&self, height: u32) -> Option<u64> {
+        let (known_height, known_time) = self
+            .snapshots
+            .block_timestamp_at_or_before(height)
+            .ok()
+            .flatten()?;
+        if known_height == height {
+            return Some(known_time);
+        }
+        let blocks_ahead = u64::from(height - known_height);
+        let earlier_block = |height: u32| {
+            self.snapshots
+                .block_timestamp_at_or_before(height)
+                .ok()
+                .flatten()
+                .filter(|(sample_height, _)| *sample_height < known_height)
+        };
+        // Prefer averaging over a wide window, but fall back to whatever history we have
+        let sample = earlier_block(known_height.saturating_sub(BLOCK_TIME_SAMPLE_WINDOW))
+            .or_else(|| earlier_block(known_height.saturating_sub(1)));
+        let offset = match sample {
+            Some((sample_height, sample_time)) => {
+                blocks_ahead * known_time.saturating_sub(sample_time)
+                    / u64::from(known_height - sample_height)
+            }
+            None => blocks_ahead * DEFAULT_BLOCK_TIME_SECS,
+        };
+        Some(known_time.saturating_add(offset))
     }
 
     fn ensure_account_roll_template(snapshot: &mut AccountSnapshot) {
@@ -194,6 +296,40 @@ where
         }
     }
 
+    fn update_player_stats(
+        &mut self,
+        player: &Identity,
+        game_id: u32,
+        height: u32,
+        update: impl FnOnce(&mut PlayerGameStats),
+    ) -> Result<()> {
+        let mut stats = self
+            .snapshots
+            .player_stats_at(player, game_id)?
+            .unwrap_or_default();
+        update(&mut stats);
+        self.snapshots
+            .update_player_stats(player, game_id, &stats, height)
+    }
+
+    fn record_strap_supply(
+        &mut self,
+        strap: &Strap,
+        kind: StrapSupplyChangeKind,
+        amount: u64,
+        height: u32,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let change = StrapSupplyChange {
+            strap: strap.clone(),
+            kind,
+            amount,
+        };
+        self.snapshots.record_strap_supply_change(&change, height)
+    }
+
     fn bump_height_if_newer(&mut self, height: u32) -> Result<()> {
         let Ok((mut snapshot, current_height)) = self.snapshots.latest_snapshot() else {
             return Ok(());
@@ -231,11 +367,21 @@ impl<
         tokio::select! {
             batch = self.events.next_event_batch() => {
                 match batch {
-                    Ok(Some((events, height))) => {
-                        for event in events {
-                            self.handle_event(event, height)?;
+                    Ok(Some(EventBatch::Events { events, height })) => {
+                        self.apply_batch(events, height)?;
+                        Ok(RunState::Continue)
+                    }
+                    Ok(Some(EventBatch::Rollback { to_height })) => {
+                        self.handle_rollback(to_height)?;
+                        // Clients need to drop whatever they saw from the orphaned blocks
+                        if let Ok(latest) = self.snapshots.latest_snapshot() {
+                            self.metrics.record_snapshot(&latest.0, latest.1);
+                            let changes = BlockChanges {
+                                overview: Some(latest),
+                                ..BlockChanges::default()
+                            };
+                            self.api.publish_committed(&changes);
                         }
-                        self.bump_height_if_newer(height)?;
                         Ok(RunState::Continue)
                     }
                     Ok(None) => {
@@ -249,7 +395,7 @@ impl<
             query = self.api.query() => {
                 match query {
                     Ok(Some(inner)) => {
-                        self.handle_query(inner)?;
+                        self.handle_query(inner);
                         Ok(RunState::Continue)
                     }
                     Ok(None) => {
@@ -266,16 +412,109 @@ impl<
         }
     }
 
+    /// Apply every event in the batch to the working set, then commit them together so
+    /// storage only ever holds whole blocks
+    fn apply_batch(&mut self, events: Vec<Event>, height: u32) -> Result<()> {
+        // Checkpoints arrive every block, so they must not write an overview each time
+        let checkpoint_only = !events.is_empty()
+            && events
+                .iter()
+                .all(|event| matches!(event, Event::BlockchainEvent(_)));
+        let result = events
+            .into_iter()
+            .try_for_each(|event| {
+                let name = event.name();
+                let started = Instant::now();
+                let result = self.handle_event(event, height);
+                self.metrics.observe_event(name, started.elapsed());
+                result
+            })
+            .and_then(|()| {
+                if checkpoint_only {
+                    Ok(())
+                } else {
+                    self.bump_height_if_newer(height)
+                }
+            })
+            .and_then(|()| {
+                let started = Instant::now();
+                let changes = self.snapshots.commit()?;
+                if !changes.is_empty() {
+                    self.metrics.observe_commit(started.elapsed());
+                }
+                Ok(changes)
+            });
+        match result {
+            Ok(changes) => {
+                if let Some((snapshot, height)) = &changes.overview {
+                    self.metrics.record_snapshot(snapshot, *height);
+                } else if let Some(height) = changes.block_timestamps.keys().next_back() {
+                    self.metrics.record_checkpoint(*height);
+                }
+                if !changes.is_empty() {
+                    self.api.publish_committed(&changes);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                self.snapshots.discard();
+                self.restore_game_state();
+                Err(e)
+            }
+        }
+    }
+
     fn remember_strap(&mut self, strap: &Strap) {
         let sub_id = strap.sub_id();
         let asset_id = self.contract_id.asset_id(&sub_id);
         let _ = self.metadata.record_new_asset_id(&asset_id, strap);
     }
 
+    fn handle_rollback(&mut self, to_height: u32) -> Result<()> {
+        tracing::warn!("Rolling back indexed state to block height {}", to_height);
+        // Also drops the historical snapshots of the game current at `to_height`, which
+        // is unfinished again, and of every later game
+        self.snapshots.roll_back(to_height)?;
+
+        let Ok((snapshot, _)) = self.snapshots.latest_snapshot() else {
+            self.working_state = GameWorkingState::default();
+            self.roll_frequency = None;
+            self.first_roll_height = None;
+            return Ok(());
+        };
+
+        self.working_state = match self.snapshots.latest_working_state()? {
+            Some((state, _)) => state,
+            // Indexed before working state was persisted, so rebuild what we can
+            None => self.rebuild_working_state(&snapshot),
+        };
+        self.roll_frequency = snapshot.roll_frequency;
+        self.first_roll_height = snapshot.first_roll_height;
+        Ok(())
+    }
+
+    fn rebuild_working_state(&mut self, snapshot: &OverviewSnapshot) -> GameWorkingState {
+        // If an orphaned block finished the game, its modifiers were moved into the
+        // historical snapshot, otherwise they are still being tracked in memory.
+        let game_modifiers = match self.snapshots.historical_snapshots(snapshot.game_id) {
+            Ok(historical) => historical.modifiers,
+            Err(_) => std::mem::take(&mut self.working_state.historical_modifiers),
+        };
+        let rolls_so_far = snapshot.rolls.len() as u32;
+        GameWorkingState {
+            historical_modifiers: game_modifiers
+                .into_iter()
+                .filter(|modifier| modifier.roll_index <= rolls_so_far)
+                .collect(),
+            modifier_triggered: shop_modifiers(snapshot, |entry| entry.triggered),
+            modifier_purchased: shop_modifiers(snapshot, |entry| entry.purchased),
+        }
+    }
+
     fn handle_event(&mut self, event: Event, height: u32) -> Result<()> {
         match event {
-            Event::BlockchainEvent => {
-                todo!()
+            Event::BlockchainEvent(event) => {
+                self.handle_block_metadata_event(event, height)
             }
             Event::ContractEvent(contract_event) => match contract_event {
                 ContractEvent::Initialized(event) => {
@@ -305,79 +544,19 @@ impl<
                 ContractEvent::PurchaseModifier(event) => {
                     self.handle_purchase_modifier_event(event, height)
                 }
+                ContractEvent::WithdrawHousePot(event) => {
+                    self.handle_withdraw_house_pot_event(event, height)
+                }
+                ContractEvent::InsufficientHouseWithdrawal(event) => {
+                    self.handle_insufficient_house_withdrawal_event(event, height)
+                }
             },
         }
     }
 
-    fn handle_query(&self, query: Query) -> Result<()> {
+    fn handle_query(&self, query: Query) {
         tracing::info!("Handling query {:?}", query);
-        match query {
-            Query::LatestSnapshot(sender) => {
-                let snapshot = self.snapshots.latest_snapshot()?;
-                sender.send(snapshot).unwrap();
-                Ok(())
-            }
-            Query::LatestAccountSnapshot(inner) => {
-                let AccountSnapshotQuery { identity, sender } = inner;
-                let snapshot = self.snapshots.latest_account_snapshot(&identity)?;
-                sender.send(snapshot)
-                    .map_err(
-                        |maybe_snapshot|
-                            match maybe_snapshot {
-                                Some((snapshot, height)) => {
-                                    anyhow!("Could not send `LatestAccountSnapshot` response for {identity:?}: {snapshot:?} at {height:?}")
-                                }
-                                None => {
-                                    anyhow!("Could not send `LatestAccountSnapshot` response for {identity:?}, also it was `None` btw")
-                                }
-                        }
-                    )?;
-                Ok(())
-            }
-            Query::HistoricalSnapshot(inner) => {
-                let HistoricalSnapshotQuery { game_id, sender } = inner;
-                let snapshot = self.snapshots.historical_snapshots(game_id)?;
-                sender.send(Some(snapshot))
-                    .map_err(
-                        |maybe_snapshot|
-                            match maybe_snapshot {
-                                Some(snapshot) => {
-                                    anyhow!("Could not send `HistoricalSnapshot` response for {game_id:?}: {snapshot:?}")
-                                }
-                                None => {
-                                    anyhow!("Could not send `HistoricalSnapshot` response for {game_id:?}, also it was `None` btw")
-                                }
-                            }
-                    )?;
-                Ok(())
-            }
-            Query::HistoricalAccountSnapshot(inner) => {
-                let HistoricalAccountSnapshotQuery {
-                    identity,
-                    game_id,
-                    sender,
-                } = inner;
-                let snapshot = self.snapshots.account_snapshot_at(&identity, game_id)?;
-                sender
-                    .send(snapshot)
-                    .map_err(|maybe_snapshot| match maybe_snapshot {
-                        Some((snapshot, height)) => anyhow!(
-                            "Could not send `HistoricalAccountSnapshot` response for {identity:?} at {game_id:?}: {snapshot:?} at {height:?}"
-                        ),
-                        None => anyhow!(
-                            "Could not send `HistoricalAccountSnapshot` response for {identity:?} at {game_id:?}, also it was `None` btw"
-                        ),
-                    })?;
-                Ok(())
-            }
-            Query::AllKnownStraps(sender) => {
-                let straps = self.metadata.all_known_straps()?;
-                sender.send(straps).map_err(|straps| {
-                    anyhow!("Could not send `AllKnownStraps` response: {:?}", straps)
-                })?;
-                Ok(())
-            }
-        }
+        answer_query(query, &self.snapshots, &self.metadata);
     }
 
     fn handle_initialized_event(
@@ -394,19 +573,31 @@ impl<
         snapshot.next_roll_height = Some(event.first_height + frequency);
         snapshot.roll_frequency = Some(frequency);
         snapshot.first_roll_height = Some(event.first_height);
-        snapshot.current_block_height = height;
+        self.refresh_height(&mut snapshot, height);
         self.snapshots.update_snapshot(&snapshot, height)?;
         Ok(())
     }
 
+    fn handle_block_metadata_event(
+        &mut self,
+        event: BlockMetadataEvent,
+        height: u32,
+    ) -> Result<()> {
+        tracing::debug!("Handling BlockMetadataEvent at height {}", height);
+        // Only the timestamp is kept, `next_roll_time` is refreshed by the next contract
+        // event that rewrites the overview
+        self.snapshots
+            .record_block_timestamp(event.height, event.timestamp)
+    }
+
     fn handle_roll_event(&mut self, event: RollEvent, height: u32) -> Result<()> {
         tracing::info!("Handling RollEvent at height {}", height);
         let (mut snapshot, _) = self.snapshots.latest_snapshot()?;
         snapshot.rolls.push(event.rolled_value);
         snapshot.chips_owed = event.chips_owed_total;
         snapshot.pot_size = event.house_pot_total;
-        self.refresh_height(&mut snapshot, height);
         snapshot.next_roll_height = Some(event.next_roll_height);
+        self.refresh_height(&mut snapshot, height);
         self.snapshots.update_snapshot(&snapshot, height)
     }
 
@@ -416,8 +607,9 @@ impl<
         height: u32,
     ) -> Result<()> {
         tracing::info!("Handling ModifierTriggeredEvent at height {}", height);
-        if !self.modifier_triggered.contains(&event.modifier) {
-            self.modifier_triggered.push(event.modifier);
+        let triggered = &mut self.working_state.modifier_triggered;
+        if !triggered.contains(&event.modifier) {
+            triggered.push(event.modifier);
         }
         let (mut snapshot, _) = self.snapshots.latest_snapshot()?;
         let idx = roll_to_index(&event.modifier_roll);
@@ -437,7 +629,11 @@ impl<
             modifier: event.modifier,
             modifier_roll: event.modifier_roll,
         };
-        self.historical_modifiers.push(active_modifier);
+        self.working_state
+            .historical_modifiers
+            .push(active_modifier);
+        self.snapshots
+            .update_working_state(&self.working_state, height)?;
         self.refresh_height(&mut snapshot, height);
         self.snapshots.update_snapshot(&snapshot, height)
     }
@@ -456,17 +652,27 @@ impl<
         let mut historical = HistoricalSnapshot::new(
             previous_snapshot.game_id,
             previous_snapshot.rolls.clone(),
-            self.historical_modifiers.clone(),
+            self.working_state.historical_modifiers.clone(),
         );
         historical.strap_rewards = previous_snapshot.rewards.clone();
-        self.historical_modifiers.clear();
-        let _ = self
-            .snapshots
-            .write_historical_snapshot(previous_snapshot.game_id, &historical);
+        for table_bets in &previous_snapshot.table_bets {
+            let identity = table_bets.identity;
+            if let Some((snapshot, _)) = self
+                .snapshots
+                .account_snapshot_at(&identity, previous_snapshot.game_id)?
+            {
+                historical
+                    .accounts
+                    .push(HistoricalAccountSnapshot { identity, snapshot });
+            }
+        }
+        self.snapshots
+            .write_historical_snapshot(previous_snapshot.game_id, &historical)?;
 
         // Reset per-game tracking
-        self.modifier_triggered.clear();
-        self.modifier_purchased.clear();
+        self.working_state = GameWorkingState::default();
+        self.snapshots
+            .update_working_state(&self.working_state, height)?;
 
         let mut snapshot = OverviewSnapshot {
             pot_size,
@@ -545,7 +751,9 @@ impl<
             &account_snapshot,
             height,
         )?;
-        Ok(())
+        self.update_player_stats(&player, game_id, height, |stats| {
+            stats.chips_wagered = stats.chips_wagered.saturating_add(amount);
+        })
     }
 
     fn handle_place_strap_bet_event(
@@ -584,13 +792,21 @@ impl<
         Self::append_bet_to_account(&mut account_snapshot, roll, placement);
         Self::upsert_table_bets(&mut snapshot, &player, &account_snapshot.per_roll_bets);
         self.remember_strap(&strap);
+        self.record_strap_supply(
+            &strap,
+            StrapSupplyChangeKind::Escrowed,
+            amount,
+            height,
+        )?;
         self.snapshots.update_snapshot(&snapshot, height)?;
         self.snapshots.update_account_snapshot(
             &player,
             game_id,
             &account_snapshot,
             height,
-        )
+        )?;
+        // Strap bets wager no chips, but still count as playing the game
+        self.update_player_stats(&player, game_id, height, |_| {})
     }
 
     fn handle_claim_rewards_event(
@@ -602,9 +818,9 @@ impl<
         let ClaimRewardsEvent {
             game_id,
             player,
+            enabled_modifiers,
             total_chips_winnings,
             total_strap_winnings,
-            ..
         } = event;
         let (mut snapshot, _) = self.snapshots.latest_snapshot()?;
         snapshot.pot_size = snapshot.pot_size.saturating_sub(total_chips_winnings);
@@ -614,7 +830,7 @@ impl<
 
         let mut account_snapshot = self
             .snapshots
-            .latest_account_snapshot(&player)?
+            .account_snapshot_at(&player, game_id)?
             .map(|(snap, _)| snap)
             .unwrap_or_default();
         Self::ensure_account_roll_template(&mut account_snapshot);
@@ -622,22 +838,125 @@ impl<
             .total_chip_won
             .saturating_add(total_chips_winnings);
         let strap_rewards: Vec<(Strap, u64)> = total_strap_winnings.clone();
-        for (strap, _) in &strap_rewards {
+        for (strap, amount) in &strap_rewards {
             self.remember_strap(strap);
+            self.record_strap_supply(
+                strap,
+                StrapSupplyChangeKind::Minted,
+                *amount,
+                height,
+            )?;
         }
+        // The contract clears a player's bets on their first claim, so only that one
+        // upgrades strap bets
+        let first_claim = account_snapshot.claimed_rewards.is_none();
+        let historical = self
+            .snapshots
+            .historical_snapshots(game_id)
+            .ok()
+            .filter(|_| first_claim);
+        if let Some(historical) = historical {
+            let upgrades = strap_bet_upgrades(
+                &account_snapshot,
+                &historical.rolls,
+                &historical.modifiers,
+                &enabled_modifiers,
+            );
+            for (consumed, produced, amount) in upgrades {
+                self.record_strap_supply(
+                    &consumed,
+                    StrapSupplyChangeKind::UpgradeConsumed,
+                    amount,
+                    height,
+                )?;
+                self.record_strap_supply(
+                    &produced,
+                    StrapSupplyChangeKind::UpgradeProduced,
+                    amount,
+                    height,
+                )?;
+            }
+        }
+        self.update_player_stats(&player, game_id, height, |stats| {
+            stats.chips_won = stats.chips_won.saturating_add(total_chips_winnings);
+            for (strap, amount) in &strap_rewards {
+                if *amount > 0 {
+                    stats.hit = true;
+                    stats.highest_strap_level =
+                        stats.highest_strap_level.max(strap.level);
+                }
+            }
+            stats.hit |= total_chips_winnings > 0;
+        })?;
         account_snapshot.claimed_rewards = Some((total_chips_winnings, strap_rewards));
         self.snapshots.update_account_snapshot(
             &player,
             game_id,
             &account_snapshot,
             height,
-        )
+        )?;
+
+        // Claims for a finished game also update that game's results table, keyed by
+        // height so a rollback reverts them
+        if self.snapshots.historical_snapshots(game_id).is_ok() {
+            self.snapshots.update_historical_account(
+                game_id,
+                &player,
+                &account_snapshot,
+                height,
+            )?;
+        }
+        Ok(())
     }
 
     fn handle_fund_pot_event(&mut self, event: FundPotEvent, height: u32) -> Result<()> {
         tracing::info!("Handling FundPotEvent at height {}", height);
         let (mut snapshot, _) = self.snapshots.latest_snapshot()?;
         snapshot.pot_size = snapshot.pot_size.saturating_add(event.chips_amount);
+        let entry = HouseLedgerEntry::Fund {
+            amount: event.chips_amount,
+            funder: event.funder,
+        };
+        self.snapshots.record_house_ledger_entry(&entry, height)?;
+        self.refresh_height(&mut snapshot, height);
+        self.snapshots.update_snapshot(&snapshot, height)
+    }
+
+    fn handle_withdraw_house_pot_event(
+        &mut self,
+        event: WithdrawHousePotEvent,
+        height: u32,
+    ) -> Result<()> {
+        tracing::info!("Handling WithdrawHousePotEvent at height {}", height);
+        let (mut snapshot, _) = self.snapshots.latest_snapshot()?;
+        snapshot.pot_size = snapshot.pot_size.saturating_sub(event.amount);
+        let entry = HouseLedgerEntry::Withdrawal {
+            amount: event.amount,
+            to: event.to,
+        };
+        self.snapshots.record_house_ledger_entry(&entry, height)?;
+        self.refresh_height(&mut snapshot, height);
+        self.snapshots.update_snapshot(&snapshot, height)
+    }
+
+    fn handle_insufficient_house_withdrawal_event(
+        &mut self,
+        event: InsufficientHouseWithdrawalEvent,
+        height: u32,
+    ) -> Result<()> {
+        tracing::info!(
+            "Handling InsufficientHouseWithdrawalEvent at height {}",
+            height
+        );
+        let (mut snapshot, _) = self.snapshots.latest_snapshot()?;
+        // Nothing was transferred, but the contract reports the pot it checked against
+        snapshot.pot_size = event.available_amount;
+        let entry = HouseLedgerEntry::FailedWithdrawal {
+            requested_amount: event.requested_amount,
+            available_amount: event.available_amount,
+            to: event.to,
+        };
+        self.snapshots.record_house_ledger_entry(&entry, height)?;
         self.refresh_height(&mut snapshot, height);
         self.snapshots.update_snapshot(&snapshot, height)
     }
@@ -648,8 +967,11 @@ impl<
         height: u32,
     ) -> Result<()> {
         tracing::info!("Handling PurchaseModifierEvent at height {}", height);
-        if !self.modifier_purchased.contains(&event.expected_modifier) {
-            self.modifier_purchased.push(event.expected_modifier);
+        let purchased = &mut self.working_state.modifier_purchased;
+        if !purchased.contains(&event.expected_modifier) {
+            purchased.push(event.expected_modifier);
+            self.snapshots
+                .update_working_state(&self.working_state, height)?;
         }
         let (mut snapshot, _) = self.snapshots.latest_snapshot()?;
         let modifier = event.expected_modifier;
@@ -1,34 +1,73 @@
 use crate::{
     Result,
-    app::query_api::{
-        Query,
-        QueryAPI,
+    app::{
+        graphql::{
+            self,
+            IndexerSchema,
+        },
+        health::{
+            IndexingStatus,
+            Readiness,
+        },
+        identity::{
+            IdentityKind,
+            parse_identity,
+        },
+        leaderboard::{
+            LeaderboardEntry,
+            LeaderboardMetric,
+            LeaderboardWindow,
+        },
+        metrics::{
+            IndexerMetrics,
+            METRICS_CONTENT_TYPE,
+        },
+        query_api::{
+            Query,
+            QueryAPI,
+            QueryDispatcher,
+            QueryError,
+            StorageReader,
+        },
+        roll_stats::RollStats,
+        snapshot_storage::{
+            BlockChanges,
+            HistoryOrder,
+        },
+        strap_supply::StrapSupplyReport,
     },
     events::Strap,
     snapshot::{
         ALL_ROLLS,
         AccountRollBets,
         AccountSnapshot,
+        HistoricalGameSummary,
         HistoricalSnapshot,
+        HouseLedgerEntry,
         OverviewSnapshot,
     },
 };
 use actix_cors::Cors;
 use actix_web::{
     App,
+    HttpResponse,
     HttpServer,
-    dev::ServerHandle,
+    ResponseError,
+    dev::{
+        ServerHandle,
+        Service,
+    },
     error::{
+        ErrorBadRequest,
         ErrorInternalServerError,
-        PayloadError,
-        UrlencodedError,
     },
+    http::StatusCode,
     web,
 };
 use anyhow::Context;
 use fuels::types::{
-    Address,
     AssetId,
+    ContractId,
     Identity,
 };
 use serde::{
@@ -38,13 +77,28 @@ use serde::{
 use std::{
     mem,
     net::TcpListener,
-    str::FromStr,
+    sync::{
+        Arc,
+        Mutex,
+    },
     thread::JoinHandle,
+    time::Instant,
 };
 use tokio::sync::{
     mpsc,
     oneshot,
 };
+use tokio_stream::{
+    StreamExt,
+    wrappers::ReceiverStream,
+};
+
+// Messages buffered per stream client before further updates to it are skipped
+const STREAM_CLIENT_BUFFER: usize = 16;
+const DEFAULT_HISTORY_PAGE_SIZE: usize = 10;
+const MAX_HISTORY_PAGE_SIZE: usize = 100;
+const DEFAULT_LEADERBOARD_SIZE: usize = 20;
+const MAX_LEADERBOARD_SIZE: usize = 500;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 struct LatestSnapshotDto {
@@ -63,12 +117,78 @@ struct HistoricalSnapshotDto {
     snapshot: HistoricalSnapshot,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct HistoricalGamesDto {
+    games: Vec<HistoricalGameSummary>,
+    /// `from` for the next page, unset once the listing is exhausted
+    next_from: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoricalGamesParams {
+    from: Option<u32>,
+    limit: Option<usize>,
+    #[serde(default)]
+    order: HistoryOrder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct LeaderboardDto {
+    metric: LeaderboardMetric,
+    window: String,
+    entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LeaderboardParams {
+    #[serde(default)]
+    metric: LeaderboardMetric,
+    window: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RollStatsParams {
+    from: Option<u32>,
+    to: Option<u32>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 struct StrapMetadataDto {
     asset_id: AssetId,
     strap: Strap,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct BlockTimestampDto {
+    block_height: u32,
+    timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct AccountUpdateDto {
+    game_id: u32,
+    snapshot: AccountSnapshot,
+    block_height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UpdateStreamParams {
+    identity: Option<String>,
+    kind: Option<IdentityKind>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentityParams {
+    kind: Option<IdentityKind>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct HouseLedgerEntryDto {
+    entry: HouseLedgerEntry,
+    block_height: u32,
+}
+
 fn normalize_account_snapshot(snapshot: &mut AccountSnapshot) {
     if snapshot.per_roll_bets.len() == ALL_ROLLS.len() {
         return;
@@ -89,101 +209,355 @@ fn normalize_account_snapshot(snapshot: &mut AccountSnapshot) {
     snapshot.per_roll_bets = rebuilt;
 }
 
+struct StreamClient {
+    identity: Option<Identity>,
+    sender: mpsc::Sender<web::Bytes>,
+}
+
+/// Clients connected to `/snapshot/stream`
+#[derive(Clone, Default)]
+struct StreamClients {
+    clients: Arc<Mutex<Vec<StreamClient>>>,
+}
+
+impl StreamClients {
+    fn subscribe(&self, identity: Option<Identity>) -> mpsc::Receiver<web::Bytes> {
+        let (sender, receiver) = mpsc::channel(STREAM_CLIENT_BUFFER);
+        self.clients
+            .lock()
+            .unwrap()
+            .push(StreamClient { identity, sender });
+        receiver
+    }
+
+    fn publish(&self, changes: &BlockChanges) {
+        let snapshot_event = changes.overview.as_ref().and_then(|(snapshot, height)| {
+            let mut snapshot = snapshot.clone();
+            snapshot.current_block_height = *height;
+            let dto = LatestSnapshotDto {
+                snapshot,
+                block_height: *height,
+            };
+            sse_event("snapshot", &dto)
+        });
+
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|client| {
+            let account_events = changes
+                .accounts
+                .iter()
+                .filter(|(identity, ..)| client.identity.as_ref() == Some(identity))
+                .filter_map(|(_, game_id, snapshot, height)| {
+                    let mut snapshot = snapshot.clone();
+                    normalize_account_snapshot(&mut snapshot);
+                    let dto = AccountUpdateDto {
+                        game_id: *game_id,
+                        snapshot,
+                        block_height: *height,
+                    };
+                    sse_event("account", &dto)
+                });
+            for event in snapshot_event.clone().into_iter().chain(account_events) {
+                match client.sender.try_send(event) {
+                    Ok(()) => {}
+                    // Every message carries full state, so a slow client only misses
+                    // intermediate updates
+                    Err(mpsc::error::TrySendError::Full(_)) => {}
+                    Err(mpsc::error::TrySendError::Closed(_)) => return false,
+                }
+            }
+            true
+        });
+    }
+
+    fn close(&self) {
+        self.clients.lock().unwrap().clear();
+    }
+}
+
+fn sse_event<T: Serialize>(event: &str, payload: &T) -> Option<web::Bytes> {
+    match serde_json::to_string(payload) {
+        Ok(data) => Some(web::Bytes::from(format!(
+            "event: {event}\ndata: {data}\n\n"
+        ))),
+        Err(e) => {
+            tracing::warn!("failed to serialize {event} stream update: {e}");
+            None
+        }
+    }
+}
+
 pub struct ActixQueryApi {
     receiver: mpsc::Receiver<Query>,
+    stream_clients: StreamClients,
+    metrics: IndexerMetrics,
+    readiness: Readiness,
     base_url: String,
-    server_handle: ServerHandle,
-    server_thread: Option<JoinHandle<()>>,
+    _server: Arc<QueryServer>,
+}
+
+/// The HTTP server every deployment's routes are served from, stopped once the last
+/// `ActixQueryApi` sharing it is dropped
+struct QueryServer {
+    handle: ServerHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for QueryServer {
+    fn drop(&mut self) {
+        drop(self.handle.stop(true));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Routes and state of one deployment, served under `prefix`
+#[derive(Clone)]
+struct DeploymentService {
+    prefix: String,
+    sender: mpsc::Sender<Query>,
+    reader: Option<StorageReader>,
+    stream_clients: StreamClients,
+    metrics: IndexerMetrics,
 }
 
 impl ActixQueryApi {
+    /// Serve every query through the event loop
     pub async fn new(port: Option<u16>) -> Result<Self> {
-        let (sender, receiver) = mpsc::channel(16);
+        Self::start_one(port, None)
+    }
 
+    /// Serve queries straight from `reader`, so they never wait behind event processing
+    pub async fn with_storage_reader(
+        port: Option<u16>,
+        reader: StorageReader,
+    ) -> Result<Self> {
+        Self::start_one(port, Some(reader))
+    }
+
+    /// Serve several deployments from one port, each under `/{contract_id:#x}` and
+    /// answered from its own reader. The first is also served at the root, for clients
+    /// that only know about one deployment. One API per deployment, in the given order.
+    pub async fn with_deployments(
+        port: Option<u16>,
+        deployments: Vec<(ContractId, StorageReader)>,
+    ) -> Result<Vec<Self>> {
+        let deployments = deployments
+            .into_iter()
+            .map(|(contract_id, reader)| (format!("/{contract_id:#x}"), Some(reader)))
+            .collect();
+        Self::start(port, deployments)
+    }
+
+    fn start_one(port: Option<u16>, reader: Option<StorageReader>) -> Result<Self> {
+        let mut apis = Self::start(port, vec![(String::new(), reader)])?;
+        Ok(apis.remove(0))
+    }
+
+    fn start(
+        port: Option<u16>,
+        deployments: Vec<(String, Option<StorageReader>)>,
+    ) -> Result<Vec<Self>> {
         let listener = TcpListener::bind(("0.0.0.0", port.unwrap_or(0)))
             .context("failed to bind HTTP listener for query API")?;
         let address = listener
             .local_addr()
             .context("failed to read listener address")?;
-        let base_url = format!("http://{}", address);
+        let server_url = format!("http://{}", address);
+
+        tracing::info!("query API listening on {}", server_url);
 
-        tracing::info!("query API listening on {}", base_url);
+        let readiness = Readiness::default();
+        let mut services = Vec::with_capacity(deployments.len() + 1);
+        let mut receivers = Vec::with_capacity(deployments.len());
+        for (prefix, reader) in deployments {
+            let (sender, receiver) = mpsc::channel(16);
+            services.push(DeploymentService {
+                prefix,
+                sender,
+                reader,
+                stream_clients: StreamClients::default(),
+                metrics: IndexerMetrics::new(),
+            });
+            receivers.push(receiver);
+        }
+        // Scopes match in registration order and an empty prefix matches every path, so
+        // the root alias has to come last
+        if let Some(first) = services.first().filter(|first| !first.prefix.is_empty()) {
+            let root = DeploymentService {
+                prefix: String::new(),
+                ..first.clone()
+            };
+            services.push(root);
+        }
 
-        let server_sender = sender.clone();
+        let server_services = services.clone();
+        let server_readiness = readiness.clone();
         let server = HttpServer::new(move || {
-            let sender = server_sender.clone();
-            // server_routes(sender)
-
-            App::new()
-                .app_data(web::Data::new(sender))
-                .wrap(Cors::permissive())
-                .route("/snapshot/latest", web::get().to(handle_latest_snapshot))
-                .route(
-                    "/account/{identity}/{game_id}",
-                    web::get().to(handle_historical_account_snapshot),
-                )
-                .route(
-                    "/account/{identity}",
-                    web::get().to(handle_account_snapshot),
-                )
-                .route(
-                    "/historical/{game_id}",
-                    web::get().to(handle_historical_snapshot),
-                )
-                .route("/straps", web::get().to(handle_all_known_straps))
+            server_services.iter().fold(
+                App::new().wrap(Cors::permissive()),
+                |app, service| {
+                    // The server keeps its sender alive either way, so `query` only
+                    // returns `None` once the server has shut down
+                    let dispatcher = match &service.reader {
+                        Some(reader) => QueryDispatcher::Storage(reader.clone()),
+                        None => QueryDispatcher::EventLoop(service.sender.clone()),
+                    };
+                    let schema = graphql::schema(dispatcher.clone());
+                    let route_metrics = service.metrics.clone();
+                    let prefix = service.prefix.clone();
+
+                    app.service(
+                        web::scope(&service.prefix)
+                            .app_data(web::Data::new(dispatcher))
+                            .app_data(web::Data::new(schema))
+                            .app_data(web::Data::new(service.stream_clients.clone()))
+                            .app_data(web::Data::new(service.metrics.clone()))
+                            .app_data(web::Data::new(server_readiness.clone()))
+                            .configure(deployment_routes)
+                            .wrap_fn(move |request, service| {
+                                // Label by route pattern so path parameters don't each
+                                // get a series
+                                let route = request
+                                    .match_pattern()
+                                    .map(|pattern| {
+                                        pattern
+                                            .strip_prefix(prefix.as_str())
+                                            .map(str::to_string)
+                                            .unwrap_or(pattern)
+                                    })
+                                    .unwrap_or_else(|| "unmatched".to_string());
+                                let metrics = route_metrics.clone();
+                                let started = Instant::now();
+                                let response = service.call(request);
+                                async move {
+                                    let response = response.await;
+                                    metrics.observe_query(&route, started.elapsed());
+                                    response
+                                }
+                            }),
+                    )
+                },
+            )
         })
         .listen(listener)
         .context("failed to start Actix server")?
         .run();
 
-        let server_handle = server.handle();
-        let server_thread = std::thread::spawn(move || {
+        let handle = server.handle();
+        let thread = std::thread::spawn(move || {
             let sys = actix_web::rt::System::new();
             let _ = sys.block_on(server);
         });
+        let server = Arc::new(QueryServer {
+            handle,
+            thread: Some(thread),
+        });
 
-        Ok(Self {
-            receiver,
-            base_url,
-            server_handle,
-            server_thread: Some(server_thread),
-        })
+        let apis = services
+            .into_iter()
+            .zip(receivers)
+            .map(|(service, receiver)| Self {
+                receiver,
+                stream_clients: service.stream_clients,
+                metrics: service.metrics,
+                readiness: readiness.clone(),
+                base_url: format!("{server_url}{}", service.prefix),
+                _server: server.clone(),
+            })
+            .collect();
+        Ok(apis)
     }
 
     pub fn base_url(&self) -> &str {
         &self.base_url
     }
+
+    /// Metrics served at `/metrics`, for the event loop to record into
+    pub fn metrics(&self) -> IndexerMetrics {
+        self.metrics.clone()
+    }
+
+    /// Node head tracking behind `/ready`, for whoever polls the node to update
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.clone()
+    }
 }
 
 impl QueryAPI for ActixQueryApi {
     async fn query(&mut self) -> Result<Option<Query>> {
         Ok(self.receiver.recv().await)
     }
+
+    fn publish_committed(&mut self, changes: &BlockChanges) {
+        self.stream_clients.publish(changes);
+    }
 }
 
 impl Drop for ActixQueryApi {
     fn drop(&mut self) {
-        drop(self.server_handle.stop(true));
-        if let Some(thread) = self.server_thread.take() {
-            let _ = thread.join();
+        // Open streams would otherwise hold up the server's graceful shutdown, which
+        // follows once every deployment sharing it is gone
+        self.stream_clients.close();
+    }
+}
+
+fn deployment_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/snapshot/latest", web::get().to(handle_latest_snapshot))
+        .route("/snapshot/stream", web::get().to(handle_update_stream))
+        .route(
+            "/account/{identity}/{game_id}",
+            web::get().to(handle_historical_account_snapshot),
+        )
+        .route(
+            "/account/{identity}",
+            web::get().to(handle_account_snapshot),
+        )
+        .route("/historical", web::get().to(handle_historical_games))
+        .route(
+            "/historical/{game_id}",
+            web::get().to(handle_historical_snapshot),
+        )
+        .route("/leaderboard", web::get().to(handle_leaderboard))
+        .route("/stats/rolls", web::get().to(handle_roll_stats))
+        .route("/straps/supply", web::get().to(handle_strap_supply))
+        .route("/straps", web::get().to(handle_all_known_straps))
+        .route("/house/ledger", web::get().to(handle_house_ledger))
+        .route(
+            "/block/{height}/timestamp",
+            web::get().to(handle_block_timestamp),
+        )
+        .route("/graphql", web::post().to(handle_graphql))
+        .route("/metrics", web::get().to(handle_metrics))
+        .route("/health", web::get().to(handle_health))
+        .route("/ready", web::get().to(handle_ready));
+}
+
+impl ResponseError for QueryError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            QueryError::NotFound(_) => StatusCode::NOT_FOUND,
+            QueryError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            QueryError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
 async fn handle_latest_snapshot(
-    sender: web::Data<mpsc::Sender<Query>>,
+    dispatcher: web::Data<QueryDispatcher>,
 ) -> actix_web::Result<web::Json<LatestSnapshotDto>> {
     tracing::info!("received latest snapshot request");
     let (response_sender, response_receiver) = oneshot::channel();
     let query = Query::LatestSnapshot(response_sender);
 
-    sender.get_ref().clone().send(query).await.map_err(|_| {
+    dispatcher.dispatch(query).await.map_err(|_| {
         ErrorInternalServerError("unable to forward latest snapshot query")
     })?;
 
     let (mut snapshot, block_height) = response_receiver
         .await
-        .map_err(|_| ErrorInternalServerError("latest snapshot responder dropped"))?;
+        .map_err(|_| ErrorInternalServerError("latest snapshot responder dropped"))??;
 
     snapshot.current_block_height = block_height;
 
@@ -193,24 +567,45 @@ async fn handle_latest_snapshot(
     }))
 }
 
+async fn handle_update_stream(
+    stream_clients: web::Data<StreamClients>,
+    params: web::Query<UpdateStreamParams>,
+) -> actix_web::Result<HttpResponse> {
+    tracing::info!("received update stream request");
+    let identity = params
+        .identity
+        .as_deref()
+        .map(|identity| parse_identity(identity, params.kind))
+        .transpose()
+        .map_err(|e| ErrorBadRequest(e.to_string()))?;
+    let updates = ReceiverStream::new(stream_clients.subscribe(identity))
+        .map(Ok::<_, actix_web::Error>);
+    // The connection is dedicated to the stream, so don't keep it alive once it ends
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .force_close()
+        .streaming(updates))
+}
+
 async fn handle_account_snapshot(
-    sender: web::Data<mpsc::Sender<Query>>,
+    dispatcher: web::Data<QueryDispatcher>,
     account_identity: web::Path<String>,
+    params: web::Query<IdentityParams>,
 ) -> actix_web::Result<web::Json<Option<LatestAccountSnapshotDto>>> {
     tracing::info!("received account snapshot request");
+    let identity = parse_identity(&account_identity, params.kind)
+        .map_err(|e| ErrorBadRequest(e.to_string()))?;
     let (response_sender, response_receiver) = oneshot::channel();
-    let inner = Address::from_str(&account_identity)
-        .map_err(|_| UrlencodedError::Payload(PayloadError::EncodingCorrupted))?;
-    let identity = Identity::Address(inner);
     let query = Query::latest_account_summary(identity, response_sender);
 
-    sender.get_ref().clone().send(query).await.map_err(|_| {
+    dispatcher.dispatch(query).await.map_err(|_| {
         ErrorInternalServerError("unable to forward latest snapshot query")
     })?;
 
     if let Some((mut snapshot, block_height)) = response_receiver
         .await
-        .map_err(|_| ErrorInternalServerError("latest snapshot responder dropped"))?
+        .map_err(|_| ErrorInternalServerError("latest snapshot responder dropped"))??
     {
         normalize_account_snapshot(&mut snapshot);
         Ok(web::Json(Some(LatestAccountSnapshotDto {
@@ -223,24 +618,26 @@ async fn handle_account_snapshot(
 }
 
 async fn handle_historical_account_snapshot(
-    sender: web::Data<mpsc::Sender<Query>>,
+    dispatcher: web::Data<QueryDispatcher>,
     path: web::Path<(String, u32)>,
+    params: web::Query<IdentityParams>,
 ) -> actix_web::Result<web::Json<Option<LatestAccountSnapshotDto>>> {
     tracing::info!("received historical account snapshot request");
     let (identity_str, game_id) = path.into_inner();
+    let identity = parse_identity(&identity_str, params.kind)
+        .map_err(|e| ErrorBadRequest(e.to_string()))?;
     let (response_sender, response_receiver) = oneshot::channel();
-    let inner = Address::from_str(&identity_str)
-        .map_err(|_| UrlencodedError::Payload(PayloadError::EncodingCorrupted))?;
-    let identity = Identity::Address(inner);
     let query = Query::historical_account_summary(identity, game_id, response_sender);
 
-    sender.get_ref().clone().send(query).await.map_err(|_| {
+    dispatcher.dispatch(query).await.map_err(|_| {
         ErrorInternalServerError("unable to forward historical account snapshot query")
     })?;
 
-    if let Some((mut snapshot, block_height)) = response_receiver.await.map_err(|_| {
-        ErrorInternalServerError("historical account snapshot responder dropped")
-    })? {
+    if let Some((mut snapshot, block_height)) =
+        response_receiver.await.map_err(|_| {
+            ErrorInternalServerError("historical account snapshot responder dropped")
+        })??
+    {
         normalize_account_snapshot(&mut snapshot);
         Ok(web::Json(Some(LatestAccountSnapshotDto {
             snapshot,
@@ -252,41 +649,128 @@ async fn handle_historical_account_snapshot(
 }
 
 async fn handle_historical_snapshot(
-    sender: web::Data<mpsc::Sender<Query>>,
+    dispatcher: web::Data<QueryDispatcher>,
     game_id: web::Path<u32>,
-) -> actix_web::Result<web::Json<Option<HistoricalSnapshotDto>>> {
+) -> actix_web::Result<web::Json<HistoricalSnapshotDto>> {
     tracing::info!("received historical snapshot request for {}", game_id);
     let (response_sender, response_receiver) = oneshot::channel();
     let query = Query::historical_snapshot(*game_id, response_sender);
 
-    sender.get_ref().clone().send(query).await.map_err(|_| {
+    dispatcher.dispatch(query).await.map_err(|_| {
         ErrorInternalServerError("unable to forward historical snapshot query")
     })?;
 
-    if let Some(snapshot) = response_receiver
+    let snapshot = response_receiver.await.map_err(|_| {
+        ErrorInternalServerError("historical snapshot responder dropped")
+    })??;
+
+    Ok(web::Json(HistoricalSnapshotDto { snapshot }))
+}
+
+async fn handle_historical_games(
+    dispatcher: web::Data<QueryDispatcher>,
+    params: web::Query<HistoricalGamesParams>,
+) -> actix_web::Result<web::Json<HistoricalGamesDto>> {
+    tracing::info!("received historical games request");
+    let HistoricalGamesParams { from, limit, order } = params.into_inner();
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_PAGE_SIZE)
+        .clamp(1, MAX_HISTORY_PAGE_SIZE);
+    let (response_sender, response_receiver) = oneshot::channel();
+    let query = Query::historical_games(from, limit, order, response_sender);
+
+    dispatcher.dispatch(query).await.map_err(|_| {
+        ErrorInternalServerError("unable to forward historical games query")
+    })?;
+
+    let games = response_receiver
         .await
-        .map_err(|_| ErrorInternalServerError("historical snapshot responder dropped"))?
-    {
-        Ok(web::Json(Some(HistoricalSnapshotDto { snapshot })))
-    } else {
-        Ok(web::Json(None))
-    }
+        .map_err(|_| ErrorInternalServerError("historical games responder dropped"))??;
+
+    let next_from = match games.last() {
+        Some(last) if games.len() == limit => match order {
+            HistoryOrder::Ascending => last.game_id.checked_add(1),
+            HistoryOrder::Descending => last.game_id.checked_sub(1),
+        },
+        _ => None,
+    };
+
+    Ok(web::Json(HistoricalGamesDto { games, next_from }))
+}
+
+async fn handle_leaderboard(
+    dispatcher: web::Data<QueryDispatcher>,
+    params: web::Query<LeaderboardParams>,
+) -> actix_web::Result<web::Json<LeaderboardDto>> {
+    tracing::info!("received leaderboard request");
+    let LeaderboardParams {
+        metric,
+        window,
+        limit,
+    } = params.into_inner();
+    let window = match window {
+        Some(window) => window
+            .parse::<LeaderboardWindow>()
+            .map_err(|e| ErrorBadRequest(format!("invalid leaderboard window: {e}")))?,
+        None => LeaderboardWindow::default(),
+    };
+    let limit = limit
+        .unwrap_or(DEFAULT_LEADERBOARD_SIZE)
+        .clamp(1, MAX_LEADERBOARD_SIZE);
+    let (response_sender, response_receiver) = oneshot::channel();
+    let query = Query::leaderboard(metric, window, limit, response_sender);
+
+    dispatcher
+        .dispatch(query)
+        .await
+        .map_err(|_| ErrorInternalServerError("unable to forward leaderboard query"))?;
+
+    let entries = response_receiver
+        .await
+        .map_err(|_| ErrorInternalServerError("leaderboard responder dropped"))??;
+
+    Ok(web::Json(LeaderboardDto {
+        metric,
+        window: window.to_string(),
+        entries,
+    }))
+}
+
+async fn handle_roll_stats(
+    dispatcher: web::Data<QueryDispatcher>,
+    params: web::Query<RollStatsParams>,
+) -> actix_web::Result<web::Json<RollStats>> {
+    tracing::info!("received roll stats request");
+    let RollStatsParams { from, to } = params.into_inner();
+    let (response_sender, response_receiver) = oneshot::channel();
+    let query = Query::roll_stats(from, to, response_sender);
+
+    dispatcher
+        .dispatch(query)
+        .await
+        .map_err(|_| ErrorInternalServerError("unable to forward roll stats query"))?;
+
+    let stats = response_receiver
+        .await
+        .map_err(|_| ErrorInternalServerError("roll stats responder dropped"))??;
+
+    Ok(web::Json(stats))
 }
 
 async fn handle_all_known_straps(
-    sender: web::Data<mpsc::Sender<Query>>,
+    dispatcher: web::Data<QueryDispatcher>,
 ) -> actix_web::Result<web::Json<Vec<StrapMetadataDto>>> {
     tracing::info!("received all known strap metadata request");
     let (response_sender, response_receiver) = oneshot::channel();
     let query = Query::all_known_straps(response_sender);
 
-    sender.get_ref().clone().send(query).await.map_err(|_| {
+    dispatcher.dispatch(query).await.map_err(|_| {
         ErrorInternalServerError("unable to forward all strap metadata query")
     })?;
 
-    let response = response_receiver
-        .await
-        .map_err(|_| ErrorInternalServerError("all strap metadata responder dropped"))?;
+    let response = response_receiver.await.map_err(|_| {
+        ErrorInternalServerError("all strap metadata responder dropped")
+    })??;
 
     let body: Vec<StrapMetadataDto> = response
         .into_iter()
@@ -296,15 +780,151 @@ async fn handle_all_known_straps(
     Ok(web::Json(body))
 }
 
+async fn handle_strap_supply(
+    dispatcher: web::Data<QueryDispatcher>,
+) -> actix_web::Result<web::Json<StrapSupplyReport>> {
+    tracing::info!("received strap supply request");
+    let (response_sender, response_receiver) = oneshot::channel();
+    let query = Query::strap_supply(response_sender);
+
+    dispatcher
+        .dispatch(query)
+        .await
+        .map_err(|_| ErrorInternalServerError("unable to forward strap supply query"))?;
+
+    let report = response_receiver
+        .await
+        .map_err(|_| ErrorInternalServerError("strap supply responder dropped"))??;
+
+    Ok(web::Json(report))
+}
+
+async fn handle_house_ledger(
+    dispatcher: web::Data<QueryDispatcher>,
+) -> actix_web::Result<web::Json<Vec<HouseLedgerEntryDto>>> {
+    tracing::info!("received house ledger request");
+    let (response_sender, response_receiver) = oneshot::channel();
+    let query = Query::house_ledger(response_sender);
+
+    dispatcher
+        .dispatch(query)
+        .await
+        .map_err(|_| ErrorInternalServerError("unable to forward house ledger query"))?;
+
+    let response = response_receiver
+        .await
+        .map_err(|_| ErrorInternalServerError("house ledger responder dropped"))??;
+
+    let body = response
+        .into_iter()
+        .map(|(entry, block_height)| HouseLedgerEntryDto {
+            entry,
+            block_height,
+        })
+        .collect();
+
+    Ok(web::Json(body))
+}
+
+async fn handle_block_timestamp(
+    dispatcher: web::Data<QueryDispatcher>,
+    height: web::Path<u32>,
+) -> actix_web::Result<web::Json<Option<BlockTimestampDto>>> {
+    tracing::info!("received block timestamp request for {}", height);
+    let (response_sender, response_receiver) = oneshot::channel();
+    let query = Query::block_timestamp(*height, response_sender);
+
+    dispatcher.dispatch(query).await.map_err(|_| {
+        ErrorInternalServerError("unable to forward block timestamp query")
+    })?;
+
+    let timestamp = response_receiver
+        .await
+        .map_err(|_| ErrorInternalServerError("block timestamp responder dropped"))??;
+
+    Ok(web::Json(timestamp.map(|timestamp| BlockTimestampDto {
+        block_height: *height,
+        timestamp,
+    })))
+}
+
+async fn handle_metrics(
+    dispatcher: web::Data<QueryDispatcher>,
+    metrics: web::Data<IndexerMetrics>,
+) -> actix_web::Result<HttpResponse> {
+    metrics.set_query_queue_depth(dispatcher.queue_depth());
+    let body = metrics
+        .encode()
+        .map_err(|_| ErrorInternalServerError("unable to encode metrics"))?;
+    Ok(HttpResponse::Ok()
+        .content_type(METRICS_CONTENT_TYPE)
+        .body(body))
+}
+
+async fn indexing_status(
+    dispatcher: &QueryDispatcher,
+    readiness: &Readiness,
+) -> actix_web::Result<IndexingStatus> {
+    let (response_sender, response_receiver) = oneshot::channel();
+    let query = Query::indexed_height(response_sender);
+
+    dispatcher.dispatch(query).await.map_err(|_| {
+        ErrorInternalServerError("unable to forward indexed height query")
+    })?;
+
+    let indexed_height = response_receiver
+        .await
+        .map_err(|_| ErrorInternalServerError("indexed height responder dropped"))?;
+
+    Ok(readiness.status(indexed_height))
+}
+
+/// Up as long as queries are being answered, however far behind indexing is
+async fn handle_health(
+    dispatcher: web::Data<QueryDispatcher>,
+    readiness: web::Data<Readiness>,
+) -> actix_web::Result<web::Json<IndexingStatus>> {
+    let status = indexing_status(&dispatcher, &readiness).await?;
+    Ok(web::Json(status))
+}
+
+/// 503 until indexing has caught up to within the allowed lag of the node's head
+async fn handle_ready(
+    dispatcher: web::Data<QueryDispatcher>,
+    readiness: web::Data<Readiness>,
+) -> actix_web::Result<HttpResponse> {
+    let status = indexing_status(&dispatcher, &readiness).await?;
+    let mut response = if status.ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    Ok(response.json(status))
+}
+
+async fn handle_graphql(
+    schema: web::Data<IndexerSchema>,
+    request: web::Json<async_graphql::Request>,
+) -> web::Json<async_graphql::Response> {
+    tracing::info!("received graphql request");
+    web::Json(schema.execute(request.into_inner()).await)
+}
+
 #[allow(non_snake_case)]
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        app::query_api::{
-            AccountSnapshotQuery,
-            HistoricalAccountSnapshotQuery,
-            HistoricalSnapshotQuery,
+        app::{
+            in_memory_metadata_storage::InMemoryMetadataStorage,
+            in_memory_snapshot_storage::InMemorySnapshotStorage,
+            query_api::{
+                AccountSnapshotQuery,
+                BlockTimestampQuery,
+                HistoricalAccountSnapshotQuery,
+                HistoricalSnapshotQuery,
+            },
+            snapshot_storage::SnapshotStorage,
         },
         events::{
             Modifier,
@@ -312,8 +932,15 @@ mod tests {
             Strap,
             StrapKind,
         },
-        snapshot::ActiveModifier,
+        snapshot::{
+            ActiveModifier,
+            PlayerGameStats,
+            StrapSupplyChange,
+            StrapSupplyChangeKind,
+        },
     };
+    use fuels::types::Address;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn query__can_get_and_respond_to_latest_overview_snapshot() {
@@ -338,7 +965,7 @@ mod tests {
         let query = api.query().await.unwrap().expect("expected query");
         if let Query::LatestSnapshot(sender) = query {
             sender
-                .send((OverviewSnapshot::new(), expected_height))
+                .send(Ok((OverviewSnapshot::new(), expected_height)))
                 .unwrap();
         } else {
             panic!("expected latest snapshot query got {:?}", query);
@@ -348,6 +975,217 @@ mod tests {
         assert_eq!(response, expected_response);
     }
 
+    #[tokio::test]
+    async fn query__nothing_indexed__is_not_found() {
+        // given
+        let reader = StorageReader::new(
+            InMemorySnapshotStorage::new(),
+            InMemoryMetadataStorage::default(),
+        );
+        let api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+
+        // when
+        let latest = client
+            .get(format!("{}/snapshot/latest", api.base_url()))
+            .send()
+            .await
+            .unwrap();
+        let historical = client
+            .get(format!("{}/historical/99999", api.base_url()))
+            .send()
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(latest.status(), reqwest::StatusCode::NOT_FOUND);
+        assert_eq!(historical.status(), reqwest::StatusCode::NOT_FOUND);
+        let body = historical.text().await.unwrap();
+        assert!(body.contains("game 99999"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn query__with_deployments__serves_each_under_its_contract_id() {
+        // given
+        let deployments = [
+            (ContractId::from([1u8; 32]), 10),
+            (ContractId::from([2u8; 32]), 20),
+        ]
+        .into_iter()
+        .map(|(contract_id, pot_size)| {
+            let snapshot = OverviewSnapshot {
+                pot_size,
+                ..OverviewSnapshot::default()
+            };
+            let storage = InMemorySnapshotStorage::new_with_snapshot(snapshot, 5);
+            let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+            (contract_id, reader)
+        })
+        .collect();
+        let apis = ActixQueryApi::with_deployments(None, deployments)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+        let root_url = apis[0]
+            .base_url()
+            .strip_suffix(&format!("/{:#x}", ContractId::from([1u8; 32])))
+            .unwrap()
+            .to_string();
+
+        // when
+        let mut pot_sizes = Vec::new();
+        for base_url in [apis[0].base_url(), apis[1].base_url(), &root_url] {
+            let latest = client
+                .get(format!("{base_url}/snapshot/latest"))
+                .send()
+                .await
+                .unwrap()
+                .json::<LatestSnapshotDto>()
+                .await
+                .unwrap();
+            pot_sizes.push(latest.snapshot.pot_size);
+        }
+
+        // then
+        assert_eq!(pot_sizes, vec![10, 20, 10]);
+        let second_url = format!("{root_url}/{:#x}", ContractId::from([2u8; 32]));
+        assert_eq!(apis[1].base_url(), second_url);
+    }
+
+    #[tokio::test]
+    async fn query__with_storage_reader__answers_without_the_event_loop() {
+        // given
+        let snapshot = OverviewSnapshot {
+            pot_size: 700,
+            current_block_height: 42,
+            ..OverviewSnapshot::default()
+        };
+        let mut storage =
+            InMemorySnapshotStorage::new_with_snapshot(snapshot.clone(), 42);
+        let identity = Identity::default();
+        let account_snapshot = AccountSnapshot {
+            total_chip_bet: 25,
+            ..AccountSnapshot::default()
+        };
+        storage
+            .update_account_snapshot(&identity, 0, &account_snapshot, 42)
+            .unwrap();
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let mut api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+        let Identity::Address(address) = identity else {
+            panic!("expected address identity");
+        };
+        let snapshot_url = format!("{}/snapshot/latest", api.base_url());
+        let account_url = format!("{}/account/{address}", api.base_url());
+
+        // when
+        let latest = client
+            .get(snapshot_url)
+            .send()
+            .await
+            .unwrap()
+            .json::<LatestSnapshotDto>()
+            .await
+            .unwrap();
+        let account = client
+            .get(account_url)
+            .send()
+            .await
+            .unwrap()
+            .json::<Option<LatestAccountSnapshotDto>>()
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(
+            latest,
+            LatestSnapshotDto {
+                snapshot,
+                block_height: 42,
+            }
+        );
+        let mut expected_account = account_snapshot;
+        normalize_account_snapshot(&mut expected_account);
+        assert_eq!(
+            account,
+            Some(LatestAccountSnapshotDto {
+                snapshot: expected_account,
+                block_height: 42,
+            })
+        );
+        let forwarded =
+            tokio::time::timeout(Duration::from_millis(50), api.query()).await;
+        assert!(forwarded.is_err(), "no query should reach the event loop");
+    }
+
+    #[tokio::test]
+    async fn query__update_stream__pushes_committed_snapshot_and_subscribed_account() {
+        // given
+        let mut api = ActixQueryApi::new(None).await.unwrap();
+        let client = reqwest::Client::new();
+        let subscribed = Identity::default();
+        let Identity::Address(address) = subscribed else {
+            panic!("expected address identity");
+        };
+        let other = Identity::Address(Address::from([7u8; 32]));
+        let url = format!("{}/snapshot/stream?identity={address}", api.base_url());
+        let mut response = client.get(url).send().await.unwrap();
+
+        let snapshot = OverviewSnapshot {
+            pot_size: 900,
+            ..OverviewSnapshot::default()
+        };
+        let mut account_snapshot = AccountSnapshot {
+            total_chip_bet: 30,
+            ..AccountSnapshot::default()
+        };
+        let changes = BlockChanges {
+            overview: Some((snapshot.clone(), 55)),
+            accounts: vec![
+                (other, 3, AccountSnapshot::default(), 55),
+                (subscribed, 3, account_snapshot.clone(), 55),
+            ],
+            ..BlockChanges::default()
+        };
+
+        // when
+        api.publish_committed(&changes);
+
+        // then
+        let mut body = String::new();
+        while body.matches("\n\n").count() < 2 {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+                .await
+                .unwrap()
+                .unwrap()
+                .expect("stream ended early");
+            body.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        let mut expected_snapshot = snapshot;
+        expected_snapshot.current_block_height = 55;
+        normalize_account_snapshot(&mut account_snapshot);
+        let expected = format!(
+            "event: snapshot\ndata: {}\n\nevent: account\ndata: {}\n\n",
+            serde_json::to_string(&LatestSnapshotDto {
+                snapshot: expected_snapshot,
+                block_height: 55,
+            })
+            .unwrap(),
+            serde_json::to_string(&AccountUpdateDto {
+                game_id: 3,
+                snapshot: account_snapshot,
+                block_height: 55,
+            })
+            .unwrap(),
+        );
+        assert_eq!(body, expected);
+    }
+
     #[tokio::test]
     async fn query__cors_allows_any_origin() {
         // given
@@ -367,7 +1205,7 @@ mod tests {
         // when
         let query = api.query().await.unwrap().expect("expected query");
         if let Query::LatestSnapshot(sender) = query {
-            sender.send((OverviewSnapshot::new(), 1)).unwrap();
+            sender.send(Ok((OverviewSnapshot::new(), 1))).unwrap();
         } else {
             panic!("expected latest snapshot query got {:?}", query);
         }
@@ -418,7 +1256,7 @@ mod tests {
             let AccountSnapshotQuery { identity, sender } = inner;
             assert_eq!(expected_identity, identity);
             sender
-                .send(Some((expected_snapshot.clone(), expected_height)))
+                .send(Ok(Some((expected_snapshot.clone(), expected_height))))
                 .unwrap();
         } else {
             panic!("expected latest account snapshot query got {:?}", query);
@@ -468,7 +1306,7 @@ mod tests {
             assert_eq!(expected_identity, identity);
             assert_eq!(expected_game_id, game_id);
             sender
-                .send(Some((expected_snapshot.clone(), expected_height)))
+                .send(Ok(Some((expected_snapshot.clone(), expected_height))))
                 .unwrap();
         } else {
             panic!("expected historical account snapshot query got {:?}", query);
@@ -479,6 +1317,66 @@ mod tests {
         assert_eq!(response, expected_response);
     }
 
+    #[tokio::test]
+    async fn query__account_snapshot__contract_identity_by_kind_flag() {
+        // given
+        let mut api = ActixQueryApi::new(None).await.unwrap();
+        let client = reqwest::Client::new();
+        let contract_id = ContractId::from([9u8; 32]);
+        let url = format!("{}/account/{contract_id:#x}?kind=contract", api.base_url());
+
+        let client_task = tokio::spawn(async move {
+            let response = client.get(url).send().await.unwrap();
+            response
+                .json::<Option<LatestAccountSnapshotDto>>()
+                .await
+                .unwrap()
+        });
+
+        // when
+        let query = api.query().await.unwrap().expect("expected query");
+
+        // then
+        let Query::LatestAccountSnapshot(AccountSnapshotQuery { identity, sender }) =
+            query
+        else {
+            panic!("expected latest account snapshot query got {query:?}");
+        };
+        assert_eq!(identity, Identity::ContractId(contract_id));
+        sender.send(Ok(None)).unwrap();
+        assert_eq!(client_task.await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn query__account_snapshot__bad_identity__is_bad_request_with_reason() {
+        // given
+        let api = ActixQueryApi::new(None).await.unwrap();
+        let client = reqwest::Client::new();
+
+        // when
+        let latest = client
+            .get(format!("{}/account/not-an-identity", api.base_url()))
+            .send()
+            .await
+            .unwrap();
+        let historical = client
+            .get(format!("{}/account/fuel1qqqq/3", api.base_url()))
+            .send()
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(latest.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body = latest.text().await.unwrap();
+        assert!(
+            body.contains("invalid identity `not-an-identity`"),
+            "{body}"
+        );
+        assert_eq!(historical.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body = historical.text().await.unwrap();
+        assert!(body.contains("invalid bech32 identity"), "{body}");
+    }
+
     #[tokio::test]
     async fn query__can_get_historical_snapshot() {
         // given
@@ -510,7 +1408,7 @@ mod tests {
         if let Query::HistoricalSnapshot(inner) = query {
             let HistoricalSnapshotQuery { game_id, sender } = inner;
             assert_eq!(expected_game_id, game_id);
-            sender.send(Some(expected_snapshot.clone())).unwrap();
+            sender.send(Ok(expected_snapshot.clone())).unwrap();
         } else {
             panic!("expected historical snapshot query got {:?}", query);
         }
@@ -520,6 +1418,168 @@ mod tests {
         assert_eq!(response, expected_response);
     }
 
+    #[tokio::test]
+    async fn query__historical_games__lists_a_page_of_summaries() {
+        // given
+        let mut storage = InMemorySnapshotStorage::new();
+        for game_id in 1..=5 {
+            let snapshot = HistoricalSnapshot::new(
+                game_id,
+                vec![Roll::Six, Roll::Seven],
+                Vec::new(),
+            );
+            storage
+                .write_historical_snapshot(game_id, &snapshot)
+                .unwrap();
+        }
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+        let url = format!("{}/historical?from=4&limit=2&order=desc", api.base_url());
+
+        // when
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap()
+            .json::<HistoricalGamesDto>()
+            .await
+            .unwrap();
+
+        // then
+        let summary = |game_id| HistoricalGameSummary {
+            game_id,
+            roll_count: 2,
+            last_roll: Some(Roll::Seven),
+            strap_rewards: Vec::new(),
+            player_count: 0,
+        };
+        let expected = HistoricalGamesDto {
+            games: vec![summary(4), summary(3)],
+            next_from: Some(2),
+        };
+        assert_eq!(response, expected);
+    }
+
+    #[tokio::test]
+    async fn query__leaderboard__ranks_players_within_window() {
+        // given
+        let snapshot = OverviewSnapshot {
+            game_id: 3,
+            ..OverviewSnapshot::default()
+        };
+        let mut storage = InMemorySnapshotStorage::new_with_snapshot(snapshot, 10);
+        let early = Identity::Address(Address::from([1u8; 32]));
+        let late = Identity::Address(Address::from([2u8; 32]));
+        let stats = |chips_won| PlayerGameStats {
+            chips_wagered: 10,
+            chips_won,
+            hit: chips_won > 0,
+            highest_strap_level: 0,
+        };
+        // `early` only won big outside the window
+        storage
+            .update_player_stats(&early, 1, &stats(1_000), 5)
+            .unwrap();
+        storage
+            .update_player_stats(&early, 2, &stats(0), 7)
+            .unwrap();
+        storage
+            .update_player_stats(&late, 3, &stats(50), 9)
+            .unwrap();
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+        let url = format!(
+            "{}/leaderboard?metric=chips_won&window=last:2",
+            api.base_url()
+        );
+        let invalid_url = format!("{}/leaderboard?window=week", api.base_url());
+
+        // when
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap()
+            .json::<LeaderboardDto>()
+            .await
+            .unwrap();
+        let invalid = client.get(invalid_url).send().await.unwrap();
+
+        // then
+        assert_eq!(response.metric, LeaderboardMetric::ChipsWon);
+        assert_eq!(response.window, "last:2");
+        let ranked: Vec<_> = response
+            .entries
+            .iter()
+            .map(|entry| (entry.rank, entry.player, entry.stats.chips_won))
+            .collect();
+        assert_eq!(ranked, vec![(1, late, 50), (2, early, 0)]);
+        assert_eq!(invalid.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn query__roll_stats__counts_finished_and_current_games_in_range() {
+        // given
+        let snapshot = OverviewSnapshot {
+            game_id: 3,
+            rolls: vec![Roll::Eight],
+            ..OverviewSnapshot::default()
+        };
+        let mut storage = InMemorySnapshotStorage::new_with_snapshot(snapshot, 10);
+        let finished = [
+            (1, vec![Roll::Two, Roll::Two]),
+            (2, vec![Roll::Seven, Roll::Six, Roll::Six]),
+        ];
+        for (game_id, rolls) in finished {
+            let snapshot = HistoricalSnapshot::new(game_id, rolls, Vec::new());
+            storage
+                .write_historical_snapshot(game_id, &snapshot)
+                .unwrap();
+        }
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+        let url = format!("{}/stats/rolls?from=2", api.base_url());
+        let invalid_url = format!("{}/stats/rolls?from=5&to=2", api.base_url());
+
+        // when
+        let stats = client
+            .get(url)
+            .send()
+            .await
+            .unwrap()
+            .json::<RollStats>()
+            .await
+            .unwrap();
+        let invalid = client.get(invalid_url).send().await.unwrap();
+
+        // then
+        assert_eq!(stats.games, 2);
+        assert_eq!(stats.total_rolls, 4);
+        let observed: Vec<_> = stats
+            .frequencies
+            .iter()
+            .filter(|frequency| frequency.observed > 0)
+            .map(|frequency| (frequency.roll, frequency.observed))
+            .collect();
+        assert_eq!(
+            observed,
+            vec![(Roll::Six, 2), (Roll::Seven, 1), (Roll::Eight, 1)]
+        );
+        assert_eq!(stats.streaks.current_without_seven, 3);
+        assert!(stats.p_value.is_some());
+        assert_eq!(invalid.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn query__can_get_all_known_straps() {
         // given
@@ -544,7 +1604,7 @@ mod tests {
         // when
         let query = api.query().await.unwrap().expect("expected query");
         if let Query::AllKnownStraps(sender) = query {
-            sender.send(expected.clone()).unwrap();
+            sender.send(Ok(expected.clone())).unwrap();
         } else {
             panic!("expected all known strap metadata query got {:?}", query);
         }
@@ -559,4 +1619,264 @@ mod tests {
         expected_sorted.sort_by_key(|entry| entry.asset_id);
         assert_eq!(response, expected_sorted);
     }
+
+    #[tokio::test]
+    async fn query__strap_supply__reports_committed_supply_per_kind() {
+        // given
+        let mut storage = InMemorySnapshotStorage::new();
+        let hat = Strap::new(1, StrapKind::Hat, Modifier::Lucky);
+        let upgraded_hat = Strap::new(2, StrapKind::Hat, Modifier::Lucky);
+        let changes = [
+            (hat.clone(), StrapSupplyChangeKind::Minted, 3),
+            (hat.clone(), StrapSupplyChangeKind::Escrowed, 1),
+            (hat, StrapSupplyChangeKind::UpgradeConsumed, 1),
+            (
+                upgraded_hat.clone(),
+                StrapSupplyChangeKind::UpgradeProduced,
+                1,
+            ),
+            (upgraded_hat, StrapSupplyChangeKind::Minted, 1),
+        ];
+        for (strap, kind, amount) in changes {
+            let change = StrapSupplyChange {
+                strap,
+                kind,
+                amount,
+            };
+            storage.record_strap_supply_change(&change, 10).unwrap();
+        }
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+        let url = format!("{}/straps/supply", api.base_url());
+
+        // when
+        let report = client
+            .get(url)
+            .send()
+            .await
+            .unwrap()
+            .json::<StrapSupplyReport>()
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(report.straps.len(), 2);
+        let hats = &report.kinds[0];
+        assert_eq!(hats.kind, StrapKind::Hat);
+        let histogram: Vec<_> = hats
+            .levels
+            .iter()
+            .map(|level| (level.level, level.supply.circulating))
+            .collect();
+        assert_eq!(histogram, vec![(1, 2), (2, 1)]);
+        assert_eq!(hats.total.upgrades_produced, 1);
+    }
+
+    #[tokio::test]
+    async fn query__metrics__counts_queries_per_route_pattern() {
+        // given
+        let storage =
+            InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::new(), 7);
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let client = reqwest::Client::new();
+        for path in ["/snapshot/latest", "/snapshot/latest", "/historical/1"] {
+            let url = format!("{}{path}", api.base_url());
+            client.get(url).send().await.unwrap();
+        }
+        let url = format!("{}/metrics", api.base_url());
+
+        // when
+        let response = client.get(url).send().await.unwrap();
+
+        // then
+        assert_eq!(response.headers()["content-type"], METRICS_CONTENT_TYPE);
+        let body = response.text().await.unwrap();
+        let expected_lines = [
+            "strapped_indexer_queries_total{route=\"/snapshot/latest\"} 2",
+            "strapped_indexer_queries_total{route=\"/historical/{game_id}\"} 1",
+            "strapped_indexer_query_queue_depth 0",
+        ];
+        for line in expected_lines {
+            assert!(
+                body.lines().any(|actual| actual == line),
+                "{line} in {body}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn query__ready__fails_until_indexing_is_within_max_lag() {
+        // given
+        let storage =
+            InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::new(), 90);
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let api = ActixQueryApi::with_storage_reader(None, reader)
+            .await
+            .unwrap();
+        let readiness = api.readiness();
+        readiness.set_max_lag(5);
+        let client = reqwest::Client::new();
+        let ready_url = format!("{}/ready", api.base_url());
+        let health_url = format!("{}/health", api.base_url());
+
+        // when
+        let unknown_head = client.get(&ready_url).send().await.unwrap();
+        readiness.record_node_height(100);
+        let behind = client.get(&ready_url).send().await.unwrap();
+        let health = client.get(&health_url).send().await.unwrap();
+        readiness.record_node_height(94);
+        let caught_up = client.get(&ready_url).send().await.unwrap();
+
+        // then
+        assert_eq!(
+            unknown_head.status(),
+            reqwest::StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(behind.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let behind = behind.json::<IndexingStatus>().await.unwrap();
+        let expected = IndexingStatus {
+            ready: false,
+            indexed_height: Some(90),
+            node_height: Some(100),
+            lag: Some(10),
+            max_lag: 5,
+        };
+        assert_eq!(behind, expected);
+        assert_eq!(health.status(), reqwest::StatusCode::OK);
+        assert_eq!(caught_up.status(), reqwest::StatusCode::OK);
+        let caught_up = caught_up.json::<IndexingStatus>().await.unwrap();
+        assert_eq!(caught_up.lag, Some(4));
+    }
+
+    #[tokio::test]
+    async fn query__can_get_house_ledger() {
+        // given
+        let mut api = ActixQueryApi::new(None).await.unwrap();
+        let client = reqwest::Client::new();
+        let url = format!("{}/house/ledger", api.base_url());
+        let owner = Identity::default();
+        let expected = vec![
+            (
+                HouseLedgerEntry::Fund {
+                    amount: 1_000,
+                    funder: owner,
+                },
+                10,
+            ),
+            (
+                HouseLedgerEntry::Withdrawal {
+                    amount: 400,
+                    to: owner,
+                },
+                20,
+            ),
+            (
+                HouseLedgerEntry::FailedWithdrawal {
+                    requested_amount: 900,
+                    available_amount: 600,
+                    to: owner,
+                },
+                30,
+            ),
+        ];
+        let client_task = tokio::spawn(async move {
+            let response = client.get(url).send().await.unwrap();
+            response.json::<Vec<HouseLedgerEntryDto>>().await.unwrap()
+        });
+
+        // when
+        let query = api.query().await.unwrap().expect("expected query");
+        if let Query::HouseLedger(sender) = query {
+            sender.send(Ok(expected.clone())).unwrap();
+        } else {
+            panic!("expected house ledger query got {:?}", query);
+        }
+
+        // then
+        let response = client_task.await.unwrap();
+        let expected: Vec<HouseLedgerEntryDto> = expected
+            .into_iter()
+            .map(|(entry, block_height)| HouseLedgerEntryDto {
+                entry,
+                block_height,
+            })
+            .collect();
+        assert_eq!(response, expected);
+    }
+
+    #[tokio::test]
+    async fn query__can_get_block_timestamp() {
+        // given
+        let mut api = ActixQueryApi::new(None).await.unwrap();
+        let client = reqwest::Client::new();
+        let expected_height = 1234u32;
+        let url = format!("{}/block/{expected_height}/timestamp", api.base_url());
+        let expected_timestamp = 1_700_000_000u64;
+
+        let client_task = tokio::spawn(async move {
+            let response = client.get(url).send().await.unwrap();
+            response.json::<Option<BlockTimestampDto>>().await.unwrap()
+        });
+
+        // when
+        let query = api.query().await.unwrap().expect("expected query");
+        if let Query::BlockTimestamp(inner) = query {
+            let BlockTimestampQuery { height, sender } = inner;
+            assert_eq!(expected_height, height);
+            sender.send(Ok(Some(expected_timestamp))).unwrap();
+        } else {
+            panic!("expected block timestamp query got {:?}", query);
+        }
+
+        // then
+        let response = client_task.await.unwrap();
+        let expected = BlockTimestampDto {
+            block_height: expected_height,
+            timestamp: expected_timestamp,
+        };
+        assert_eq!(response, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn query__graphql__answers_selected_fields_through_the_event_loop() {
+        // given
+        let mut api = ActixQueryApi::new(None).await.unwrap();
+        let client = reqwest::Client::new();
+        let url = format!("{}/graphql", api.base_url());
+        let body = serde_json::json!({
+            "query": "{ latestSnapshot { gameId potSize blockHeight } }",
+        });
+        let client_task = tokio::spawn(async move {
+            let response = client.post(url).json(&body).send().await.unwrap();
+            response.json::<serde_json::Value>().await.unwrap()
+        });
+        let snapshot = OverviewSnapshot {
+            game_id: 4,
+            pot_size: 1_000,
+            ..OverviewSnapshot::default()
+        };
+
+        // when
+        let query = api.query().await.unwrap().expect("expected query");
+        if let Query::LatestSnapshot(sender) = query {
+            sender.send(Ok((snapshot, 77))).unwrap();
+        } else {
+            panic!("expected latest snapshot query got {:?}", query);
+        }
+
+        // then
+        let response = client_task.await.unwrap();
+        let expected = serde_json::json!({
+            "data": {
+                "latestSnapshot": { "gameId": 4, "potSize": 1000, "blockHeight": 77 },
+            },
+        });
+        assert_eq!(response, expected);
+    }
 }
@@ -0,0 +1,254 @@
+// Append-only archive of decoded event batches, and an `EventSource` that replays it.
+use crate::{
+    Result,
+    app::event_source::{
+        EventBatch,
+        EventSource,
+    },
+};
+use anyhow::Context;
+use std::{
+    fs::{
+        self,
+        File,
+        OpenOptions,
+    },
+    future::pending,
+    io::{
+        BufRead,
+        BufReader,
+        Lines,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::Arc,
+};
+use tokio::sync::Notify;
+
+/// One JSON encoded `EventBatch` per line, in the order they were received
+pub struct EventArchive {
+    file: File,
+}
+
+impl EventArchive {
+    /// Open (or create) the archive at `path`, dropping any batches at or above
+    /// `truncate_from`, since the event source will stream those heights again.
+    pub fn open<P: AsRef<Path>>(path: P, truncate_from: u32) -> Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            Self::truncate_from(path, truncate_from)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("open event archive {}", path.display()))?;
+        Ok(Self { file })
+    }
+
+    pub fn append(&mut self, batch: &EventBatch) -> Result<()> {
+        let mut line = serde_json::to_vec(batch).context("serialize event batch")?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .context("append event batch to archive")?;
+        self.file.flush().context("flush event archive")?;
+        Ok(())
+    }
+
+    fn truncate_from(path: &Path, truncate_from: u32) -> Result<()> {
+        let reader = BufReader::new(
+            File::open(path)
+                .with_context(|| format!("open event archive {}", path.display()))?,
+        );
+        let mut kept = Vec::new();
+        let mut dropped = 0usize;
+        for line in reader.lines() {
+            let line = line.context("read event archive")?;
+            let keep = match decode_line(&line) {
+                Some(EventBatch::Events { height, .. }) => height < truncate_from,
+                Some(EventBatch::Rollback { to_height }) => to_height < truncate_from,
+                None => false,
+            };
+            if keep {
+                kept.extend_from_slice(line.as_bytes());
+                kept.push(b'\n');
+            } else {
+                dropped += 1;
+            }
+        }
+        if dropped == 0 {
+            return Ok(());
+        }
+        tracing::info!(
+            "Dropping {} archived event batches at or above block height {}",
+            dropped,
+            truncate_from
+        );
+        // Write to a sibling file first so a crash can't leave a half-written archive
+        let mut temp_path = PathBuf::from(path);
+        temp_path.set_extension("tmp");
+        fs::write(&temp_path, kept).context("write truncated event archive")?;
+        fs::rename(&temp_path, path).context("replace event archive")?;
+        Ok(())
+    }
+}
+
+fn decode_line(line: &str) -> Option<EventBatch> {
+    match serde_json::from_str(line) {
+        Ok(batch) => Some(batch),
+        Err(e) => {
+            // Most likely the last line of an archive whose writer was interrupted
+            tracing::warn!("Skipping unreadable event archive entry: {e}");
+            None
+        }
+    }
+}
+
+/// Wraps another `EventSource`, appending every batch it yields to an `EventArchive`
+pub struct ArchivingEventSource<Events> {
+    inner: Events,
+    archive: EventArchive,
+}
+
+impl<Events> ArchivingEventSource<Events> {
+    pub fn new(inner: Events, archive: EventArchive) -> Self {
+        Self { inner, archive }
+    }
+}
+
+impl<Events: EventSource> EventSource for ArchivingEventSource<Events> {
+    async fn next_event_batch(&mut self) -> Result<Option<EventBatch>> {
+        let batch = self.inner.next_event_batch().await?;
+        if let Some(batch) = &batch {
+            self.archive.append(batch)?;
+        }
+        Ok(batch)
+    }
+}
+
+/// Replays an archive written by `EventArchive`. Once every batch has been yielded the
+/// source stays pending and `finished` is notified.
+pub struct FileEventSource {
+    lines: Lines<BufReader<File>>,
+    finished: Arc<Notify>,
+}
+
+impl FileEventSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("open event archive {}", path.display()))?;
+        Ok(Self {
+            lines: BufReader::new(file).lines(),
+            finished: Arc::new(Notify::new()),
+        })
+    }
+
+    pub fn finished(&self) -> Arc<Notify> {
+        self.finished.clone()
+    }
+}
+
+impl EventSource for FileEventSource {
+    async fn next_event_batch(&mut self) -> Result<Option<EventBatch>> {
+        for line in self.lines.by_ref() {
+            let line = line.context("read event archive")?;
+            if let Some(batch) = decode_line(&line) {
+                return Ok(Some(batch));
+            }
+        }
+        self.finished.notify_one();
+        pending().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+    use crate::events::{
+        Event,
+        Roll,
+    };
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    fn arb_batches() -> Vec<EventBatch> {
+        vec![
+            EventBatch::Events {
+                events: vec![Event::init_event([0; 32].into(), [1; 32].into(), 10, 100)],
+                height: 100,
+            },
+            EventBatch::Events {
+                events: vec![Event::roll_event(0, 1, Roll::Six, 0, 0, 0, 110)],
+                height: 110,
+            },
+            EventBatch::Rollback { to_height: 105 },
+            EventBatch::Events {
+                events: vec![Event::block_event(120, 1_700_000_000)],
+                height: 120,
+            },
+        ]
+    }
+
+    async fn read_all(source: &mut FileEventSource) -> Vec<EventBatch> {
+        let finished = source.finished();
+        let mut batches = Vec::new();
+        loop {
+            tokio::select! {
+                batch = source.next_event_batch() => batches.push(batch.unwrap().unwrap()),
+                _ = finished.notified() => return batches,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn file_event_source__replays_archived_batches_in_order() {
+        // given
+        let temp_dir = TempDir::new("event_archive").unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+        let mut archive = EventArchive::open(&path, 0).unwrap();
+        let expected = arb_batches();
+        for batch in &expected {
+            archive.append(batch).unwrap();
+        }
+
+        // when
+        let mut source = FileEventSource::open(&path).unwrap();
+        let actual = tokio::time::timeout(Duration::from_secs(1), read_all(&mut source))
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn event_archive__reopening_drops_batches_at_or_above_height() {
+        // given
+        let temp_dir = TempDir::new("event_archive_truncate").unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+        let mut archive = EventArchive::open(&path, 0).unwrap();
+        for batch in arb_batches() {
+            archive.append(&batch).unwrap();
+        }
+        drop(archive);
+
+        // when
+        let _archive = EventArchive::open(&path, 110).unwrap();
+
+        // then
+        let mut source = FileEventSource::open(&path).unwrap();
+        let actual = tokio::time::timeout(Duration::from_secs(1), read_all(&mut source))
+            .await
+            .unwrap();
+        let mut expected = arb_batches();
+        expected.truncate(1);
+        expected.push(EventBatch::Rollback { to_height: 105 });
+        assert_eq!(actual, expected);
+    }
+}
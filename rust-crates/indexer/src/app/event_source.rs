@@ -2,9 +2,20 @@ use crate::{
     Result,
     events::Event,
 };
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventBatch<E = Event> {
+    /// All events decoded at the given block height
+    Events { events: Vec<E>, height: u32 },
+    /// The chain reorganized; every block above `to_height` is orphaned
+    Rollback { to_height: u32 },
+}
 
-pub trait EventSource {
-    fn next_event_batch(
-        &mut self,
-    ) -> impl Future<Output = Result<Option<(Vec<Event>, u32)>>>;
+pub trait EventSource<E = Event> {
+    fn next_event_batch(&mut self)
+    -> impl Future<Output = Result<Option<EventBatch<E>>>>;
 }
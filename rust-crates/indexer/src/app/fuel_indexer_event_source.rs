@@ -1,11 +1,15 @@
 use crate::{
     Result,
-    app::event_source::EventSource,
+    app::event_source::{
+        EventBatch,
+        EventSource,
+    },
     events::{
         ClaimRewardsEvent,
         ContractEvent,
         Event,
         FundPotEvent,
+        InsufficientHouseWithdrawalEvent,
         Modifier as AppModifier,
         ModifierTriggeredEvent,
         NewGameEvent as AppNewGameEvent,
@@ -13,8 +17,10 @@ use crate::{
         PlaceStrapBetEvent,
         PurchaseModifierEvent,
         Roll as AppRoll,
+        ScopedEvent,
         Strap as AppStrap,
         StrapKind as AppStrapKind,
+        WithdrawHousePotEvent,
     },
 };
 use anyhow::anyhow;
@@ -55,6 +61,7 @@ use generated_abi::strapped_types::{
     ClaimRewardsEvent as AbiClaimRewardsEvent,
     FundPotEvent as AbiFundPotEvent,
     InitializedEvent,
+    InsufficientHouseWithdrawalEvent as AbiInsufficientHouseWithdrawalEvent,
     Modifier as AbiModifier,
     ModifierTriggeredEvent as AbiModifierTriggeredEvent,
     NewGameEvent as AbiNewGameEvent,
@@ -65,6 +72,7 @@ use generated_abi::strapped_types::{
     RollEvent as AbiRollEvent,
     Strap as AbiStrap,
     StrapKind as AbiStrapKind,
+    WithdrawHousePotEvent as AbiWithdrawHousePotEvent,
 };
 use tokio_stream::StreamExt;
 
@@ -73,7 +81,11 @@ mod tests;
 
 pub struct FuelIndexerEventSource<Fn>
 where
-    Fn: FnOnce(DecoderConfig, &Receipt) -> Option<Event> + Copy + Send + Sync + 'static,
+    Fn: FnOnce(DecoderConfig, &Receipt) -> Option<ScopedEvent>
+        + Copy
+        + Send
+        + Sync
+        + 'static,
 {
     _service: ServiceRunner<
         Task<
@@ -82,16 +94,20 @@ where
             fuel_receipts_manager::rocksdb::Storage,
         >,
     >,
-    stream: BoxStream<Result<UnstableEvent<Event>>>,
+    stream: BoxStream<Result<UnstableEvent<ScopedEvent>>>,
 }
 
-impl StorableEvent for Event {}
+impl StorableEvent for ScopedEvent {}
 
-impl<Fn> EventSource for FuelIndexerEventSource<Fn>
+impl<Fn> EventSource<ScopedEvent> for FuelIndexerEventSource<Fn>
 where
-    Fn: FnOnce(DecoderConfig, &Receipt) -> Option<Event> + Copy + Send + Sync + 'static,
+    Fn: FnOnce(DecoderConfig, &Receipt) -> Option<ScopedEvent>
+        + Copy
+        + Send
+        + Sync
+        + 'static,
 {
-    async fn next_event_batch(&mut self) -> Result<Option<(Vec<Event>, u32)>> {
+    async fn next_event_batch(&mut self) -> Result<Option<EventBatch<ScopedEvent>>> {
         let unstable_event = self
             .stream
             .next()
@@ -101,18 +117,41 @@ where
         match unstable_event {
             UnstableEvent::Transaction(TransactionEvents {
                 tx_pointer, events, ..
-            }) => Ok(Some((events, *tx_pointer.block_height()))),
-            UnstableEvent::Checkpoint(CheckpointEvent { block_height, .. }) => {
-                Ok(Some((vec![], block_height.into())))
+            }) => Ok(Some(EventBatch::Events {
+                events,
+                height: *tx_pointer.block_height(),
+            })),
+            UnstableEvent::Checkpoint(CheckpointEvent {
+                block_height,
+                block_time,
+                ..
+            }) => {
+                let height: u32 = block_height.into();
+                // TAI64 can represent instants before the unix epoch, clamp those to zero
+                let timestamp = u64::try_from(block_time.to_unix()).unwrap_or_default();
+                Ok(Some(EventBatch::Events {
+                    events: vec![ScopedEvent::chain(Event::block_event(
+                        height, timestamp,
+                    ))],
+                    height,
+                }))
+            }
+            UnstableEvent::Rollback(to_height) => {
+                let to_height: u32 = to_height.into();
+                tracing::warn!("Received rollback to block height {}", to_height);
+                Ok(Some(EventBatch::Rollback { to_height }))
             }
-            UnstableEvent::Rollback(_) => Ok(None),
         }
     }
 }
 
 impl<Fn> FuelIndexerEventSource<Fn>
 where
-    Fn: FnOnce(DecoderConfig, &Receipt) -> Option<Event> + Copy + Send + Sync + 'static,
+    Fn: FnOnce(DecoderConfig, &Receipt) -> Option<ScopedEvent>
+        + Copy
+        + Send
+        + Sync
+        + 'static,
 {
     pub async fn new(
         handler: Fn,
@@ -209,6 +248,18 @@ fn map_identity(identity: Identity) -> Identity {
     identity
 }
 
+/// `parse_event_logs`, tagged with the contract that emitted the receipt
+pub fn parse_scoped_event_logs(
+    decoder: DecoderConfig,
+    receipt: &Receipt,
+) -> Option<ScopedEvent> {
+    let event = parse_event_logs(decoder, receipt)?;
+    Some(ScopedEvent {
+        contract_id: receipt.id().copied(),
+        event,
+    })
+}
+
 pub fn parse_event_logs(decoder: DecoderConfig, receipt: &Receipt) -> Option<Event> {
     try_parse_events!(
         [decoder, receipt]
@@ -330,6 +381,21 @@ pub fn parse_event_logs(decoder: DecoderConfig, receipt: &Receipt) -> Option<Eve
                 purchaser: map_identity(event.purchaser),
             };
             Some(Event::ContractEvent(ContractEvent::PurchaseModifier(inner)))
+        },
+        AbiWithdrawHousePotEvent => |event| {
+            let inner = WithdrawHousePotEvent {
+                amount: event.amount,
+                to: map_identity(event.to),
+            };
+            Some(Event::ContractEvent(ContractEvent::WithdrawHousePot(inner)))
+        },
+        AbiInsufficientHouseWithdrawalEvent => |event| {
+            let inner = InsufficientHouseWithdrawalEvent {
+                requested_amount: event.requested_amount,
+                available_amount: event.available_amount,
+                to: map_identity(event.to),
+            };
+            Some(Event::ContractEvent(ContractEvent::InsufficientHouseWithdrawal(inner)))
         }
 
     )
@@ -1,7 +1,10 @@
 #![allow(non_snake_case)]
 
 use super::*;
-use crate::events::ContractEvent;
+use crate::{
+    app::shared_event_source::SharedEventSource,
+    events::ContractEvent,
+};
 use fuel_core::state::rocks_db::ColumnsPolicy;
 use fuels::{
     prelude::{
@@ -18,6 +21,7 @@ use fuels::{
 };
 use generated_abi::{
     get_contract_instance,
+    strapped_types::MyContract,
     vrf_types::FakeVRFContract,
 };
 use url::Url;
@@ -56,7 +60,7 @@ async fn next_event_batch__can_get_init_event() {
     .expect("failed to launch local provider");
     let wallet = wallets.pop().unwrap();
 
-    let (contract_instance, _contract_id) = get_contract_instance(wallet.clone()).await;
+    let (contract_instance, contract_id) = get_contract_instance(wallet.clone()).await;
     let address = wallet.provider().url();
     let indexer_config = fuel_event_streams::service::Config::new(
         0u32.into(),
@@ -65,7 +69,7 @@ async fn next_event_batch__can_get_init_event() {
     );
 
     let mut event_source = FuelIndexerEventSource::new(
-        parse_event_logs,
+        parse_scoped_event_logs,
         temp_dir,
         database_config,
         indexer_config,
@@ -88,13 +92,139 @@ async fn next_event_batch__can_get_init_event() {
     // then
     let _should_be_empty_first_block = event_source.next_event_batch().await.unwrap();
     let _ = event_source.next_event_batch().await.unwrap();
-    let (events, _) = event_source.next_event_batch().await.unwrap().unwrap();
+    let Some(EventBatch::Events { events, .. }) =
+        event_source.next_event_batch().await.unwrap()
+    else {
+        panic!("expected event batch");
+    };
     let actual = events.first().unwrap();
-    let expected = Event::init_event(fake_vrf_contract_id.into(), chip_asset_id, 100, 2);
+    let expected = ScopedEvent::contract(
+        contract_id,
+        Event::init_event(fake_vrf_contract_id.into(), chip_asset_id, 100, 2),
+    );
 
     assert_eq!(actual, &expected);
 }
 
+#[tokio::test]
+async fn next_event_batch__drops_events_from_other_contracts() {
+    let chip_asset_id = AssetId::new([1u8; 32]);
+    let base_assets = vec![
+        AssetConfig {
+            id: AssetId::zeroed(),
+            num_coins: 1,
+            coin_amount: 10_000_000_000,
+        },
+        AssetConfig {
+            id: chip_asset_id,
+            num_coins: 1,
+            coin_amount: 10_000_000_000,
+        },
+    ];
+    let temp_dir = tempdir::TempDir::new("database")
+        .unwrap()
+        .path()
+        .to_path_buf();
+
+    let database_config = DatabaseConfig {
+        cache_capacity: None,
+        max_fds: 512,
+        columns_policy: ColumnsPolicy::Lazy,
+    };
+    let mut wallets = launch_custom_provider_and_get_wallets(
+        WalletsConfig::new_multiple_assets(1, base_assets),
+        None,
+        None,
+    )
+    .await
+    .expect("failed to launch local provider");
+    let wallet = wallets.pop().unwrap();
+
+    let (contract_instance, contract_id) = get_contract_instance(wallet.clone()).await;
+    let strapped_bin_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../sway-projects/strapped/out/release/strapped.bin");
+    let other_contract = Contract::load_from(
+        strapped_bin_path,
+        LoadConfiguration::default().with_salt([7u8; 32]),
+    )
+    .expect("failed to load strapped contract");
+    let other_deployment = other_contract
+        .deploy(&wallet, TxPolicies::default())
+        .await
+        .expect("failed to deploy second strapped contract");
+    let other_instance = MyContract::new(other_deployment.contract_id, wallet.clone());
+
+    let address = wallet.provider().url();
+    let indexer_config = fuel_event_streams::service::Config::new(
+        0u32.into(),
+        false,
+        Url::parse(address).unwrap(),
+    );
+
+    let mut shared = SharedEventSource::new(
+        FuelIndexerEventSource::new(
+            parse_scoped_event_logs,
+            temp_dir,
+            database_config,
+            indexer_config,
+            BlockHeight::from(0u32),
+        )
+        .await
+        .unwrap(),
+    );
+    let rejected_receipts = shared.rejected_receipts();
+    let mut event_source = shared.subscribe(contract_id, 0);
+    let local = tokio::task::LocalSet::new();
+    local.spawn_local(shared.run());
+
+    // given
+    let other_vrf_contract_id = [6; 32];
+    let fake_vrf_contract_id = [5; 32];
+    other_instance
+        .methods()
+        .initialize(Bits256(other_vrf_contract_id), chip_asset_id.clone(), 100)
+        .call()
+        .await
+        .unwrap();
+
+    // when
+    contract_instance
+        .methods()
+        .initialize(Bits256(fake_vrf_contract_id), chip_asset_id.clone(), 100)
+        .call()
+        .await
+        .unwrap();
+
+    // then
+    let mut initialized_vrf_ids = Vec::new();
+    local
+        .run_until(async {
+            for _ in 0..10 {
+                let Some(EventBatch::Events { events, .. }) =
+                    event_source.next_event_batch().await.unwrap()
+                else {
+                    continue;
+                };
+                for event in events {
+                    if let Event::ContractEvent(ContractEvent::Initialized(inner)) = event
+                    {
+                        initialized_vrf_ids.push(inner.vrf_contract_id);
+                    }
+                }
+                if !initialized_vrf_ids.is_empty() {
+                    break;
+                }
+            }
+        })
+        .await;
+
+    assert_eq!(
+        initialized_vrf_ids,
+        vec![ContractId::from(fake_vrf_contract_id)]
+    );
+    assert_eq!(rejected_receipts.count(), 1);
+}
+
 #[tokio::test]
 async fn next_event_batch__can_get_roll_event() {
     let chip_asset_id = AssetId::new([1u8; 32]);
@@ -150,7 +280,7 @@ async fn next_event_batch__can_get_roll_event() {
     );
 
     let mut event_source = FuelIndexerEventSource::new(
-        parse_event_logs,
+        parse_scoped_event_logs,
         temp_dir,
         database_config,
         indexer_config,
@@ -206,9 +336,14 @@ async fn next_event_batch__can_get_roll_event() {
     // then
     let mut actual_event = None;
     for _ in 0..10 {
-        let (events, _) = event_source.next_event_batch().await.unwrap().unwrap();
+        let Some(EventBatch::Events { events, .. }) =
+            event_source.next_event_batch().await.unwrap()
+        else {
+            continue;
+        };
         if let Some(event) = events
             .into_iter()
+            .map(|scoped| scoped.event)
             .find(|event| matches!(event, Event::ContractEvent(ContractEvent::Roll(_))))
         {
             actual_event = Some(event);
@@ -281,7 +416,7 @@ async fn next_event_batch__can_get_new_game_event() {
     );
 
     let mut event_source = FuelIndexerEventSource::new(
-        parse_event_logs,
+        parse_scoped_event_logs,
         temp_dir,
         database_config,
         indexer_config,
@@ -336,8 +471,12 @@ async fn next_event_batch__can_get_new_game_event() {
 
     let mut actual_new_game = None;
     for _ in 0..10 {
-        let (events, _) = event_source.next_event_batch().await.unwrap().unwrap();
-        for event in events {
+        let Some(EventBatch::Events { events, .. }) =
+            event_source.next_event_batch().await.unwrap()
+        else {
+            continue;
+        };
+        for ScopedEvent { event, .. } in events {
             if let Event::ContractEvent(ContractEvent::NewGame(inner)) = event {
                 actual_new_game = Some(inner);
                 break;
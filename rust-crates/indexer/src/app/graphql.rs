@@ -0,0 +1,638 @@
+// GraphQL schema over the same `Query` enum the REST routes use.
+use crate::{
+    app::{
+        identity,
+        query_api::{
+            Query,
+            QueryDispatcher,
+            QueryError,
+            QueryResult,
+        },
+    },
+    events,
+    snapshot::{
+        ALL_ROLLS,
+        AccountBetKind,
+        AccountSnapshot,
+        ActiveModifier,
+        HistoricalSnapshot,
+        ModifierShopEntry,
+        OverviewSnapshot,
+    },
+};
+use async_graphql::{
+    ComplexObject,
+    Context,
+    EmptyMutation,
+    EmptySubscription,
+    Enum,
+    Object,
+    Schema,
+    SimpleObject,
+};
+use fuels::types::Identity;
+use tokio::sync::oneshot;
+
+pub type IndexerSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn schema(dispatcher: QueryDispatcher) -> IndexerSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(dispatcher)
+        .finish()
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(remote = "crate::events::Roll")]
+pub enum Roll {
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Eleven,
+    Twelve,
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(remote = "crate::events::StrapKind")]
+pub enum StrapKind {
+    Shirt,
+    Pants,
+    Shoes,
+    Dress,
+    Hat,
+    Glasses,
+    Watch,
+    Ring,
+    Necklace,
+    Earring,
+    Bracelet,
+    Tattoo,
+    Skirt,
+    Piercing,
+    Coat,
+    Scarf,
+    Gloves,
+    Gown,
+    Belt,
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(remote = "crate::events::Modifier")]
+pub enum Modifier {
+    Nothing,
+    Burnt,
+    Lucky,
+    Holy,
+    Holey,
+    Scotch,
+    Soaked,
+    Moldy,
+    Starched,
+    Evil,
+    Groovy,
+    Delicate,
+}
+
+#[derive(SimpleObject)]
+pub struct Strap {
+    level: u8,
+    kind: StrapKind,
+    modifier: Modifier,
+}
+
+impl From<&events::Strap> for Strap {
+    fn from(strap: &events::Strap) -> Self {
+        Self {
+            level: strap.level,
+            kind: strap.kind.into(),
+            modifier: strap.modifier.into(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct StrapAmount {
+    strap: Strap,
+    amount: u64,
+}
+
+fn strap_amounts(straps: &[(events::Strap, u64)]) -> Vec<StrapAmount> {
+    straps
+        .iter()
+        .map(|(strap, amount)| StrapAmount {
+            strap: strap.into(),
+            amount: *amount,
+        })
+        .collect()
+}
+
+#[derive(SimpleObject)]
+pub struct StrapReward {
+    roll: Roll,
+    strap: Strap,
+    amount: u64,
+}
+
+fn strap_rewards(rewards: &[(events::Roll, events::Strap, u64)]) -> Vec<StrapReward> {
+    rewards
+        .iter()
+        .map(|(roll, strap, amount)| StrapReward {
+            roll: (*roll).into(),
+            strap: strap.into(),
+            amount: *amount,
+        })
+        .collect()
+}
+
+#[derive(SimpleObject)]
+pub struct StrapMetadata {
+    asset_id: String,
+    strap: Strap,
+}
+
+#[derive(SimpleObject)]
+pub struct RollBets {
+    roll: Roll,
+    chips: u64,
+    straps: Vec<StrapAmount>,
+}
+
+#[derive(SimpleObject)]
+pub struct RollModifier {
+    roll: Roll,
+    modifier: Modifier,
+}
+
+#[derive(SimpleObject)]
+pub struct ShopModifier {
+    trigger_roll: Roll,
+    modifier_roll: Roll,
+    modifier: Modifier,
+    triggered: bool,
+    purchased: bool,
+    price: u64,
+}
+
+impl From<&ModifierShopEntry> for ShopModifier {
+    fn from(entry: &ModifierShopEntry) -> Self {
+        Self {
+            trigger_roll: entry.trigger_roll.into(),
+            modifier_roll: entry.modifier_roll.into(),
+            modifier: entry.modifier.into(),
+            triggered: entry.triggered,
+            purchased: entry.purchased,
+            price: entry.price,
+        }
+    }
+}
+
+/// State of the game in progress
+pub struct Overview {
+    snapshot: OverviewSnapshot,
+    block_height: u32,
+}
+
+#[Object]
+impl Overview {
+    async fn game_id(&self) -> u32 {
+        self.snapshot.game_id
+    }
+
+    async fn block_height(&self) -> u32 {
+        self.block_height
+    }
+
+    async fn rolls(&self) -> Vec<Roll> {
+        self.snapshot
+            .rolls
+            .iter()
+            .map(|roll| (*roll).into())
+            .collect()
+    }
+
+    async fn pot_size(&self) -> u64 {
+        self.snapshot.pot_size
+    }
+
+    async fn chips_owed(&self) -> u64 {
+        self.snapshot.chips_owed
+    }
+
+    async fn total_chip_bets(&self) -> u64 {
+        self.snapshot.total_chip_bets
+    }
+
+    async fn next_roll_height(&self) -> Option<u32> {
+        self.snapshot.next_roll_height
+    }
+
+    async fn next_roll_time(&self) -> Option<u64> {
+        self.snapshot.next_roll_time
+    }
+
+    async fn roll_frequency(&self) -> Option<u32> {
+        self.snapshot.roll_frequency
+    }
+
+    async fn first_roll_height(&self) -> Option<u32> {
+        self.snapshot.first_roll_height
+    }
+
+    async fn rewards(&self) -> Vec<StrapReward> {
+        strap_rewards(&self.snapshot.rewards)
+    }
+
+    /// Bets on the table, per roll
+    async fn bets(&self) -> Vec<RollBets> {
+        ALL_ROLLS
+            .iter()
+            .zip(&self.snapshot.specific_bets)
+            .map(|(roll, (chips, straps))| RollBets {
+                roll: (*roll).into(),
+                chips: *chips,
+                straps: strap_amounts(straps),
+            })
+            .collect()
+    }
+
+    async fn active_modifiers(&self) -> Vec<RollModifier> {
+        ALL_ROLLS
+            .iter()
+            .zip(&self.snapshot.modifiers_active)
+            .filter_map(|(roll, modifier)| {
+                modifier.map(|modifier| RollModifier {
+                    roll: (*roll).into(),
+                    modifier: modifier.into(),
+                })
+            })
+            .collect()
+    }
+
+    async fn modifier_shop(&self) -> Vec<ShopModifier> {
+        self.snapshot.modifier_shop.iter().map(Into::into).collect()
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ClaimedRewards {
+    chips: u64,
+    straps: Vec<StrapAmount>,
+}
+
+#[derive(SimpleObject)]
+pub struct Bet {
+    roll: Roll,
+    bet_roll_index: u32,
+    amount: u64,
+    /// Unset for chip bets
+    strap: Option<Strap>,
+}
+
+/// A player's bets and winnings in one game
+pub struct Account {
+    snapshot: AccountSnapshot,
+    block_height: Option<u32>,
+}
+
+#[Object]
+impl Account {
+    /// Height of the last change to the account, unset inside historical games
+    async fn block_height(&self) -> Option<u32> {
+        self.block_height
+    }
+
+    async fn total_chip_bet(&self) -> u64 {
+        self.snapshot.total_chip_bet
+    }
+
+    async fn total_chip_won(&self) -> u64 {
+        self.snapshot.total_chip_won
+    }
+
+    async fn strap_bets(&self) -> Vec<StrapAmount> {
+        strap_amounts(&self.snapshot.strap_bets)
+    }
+
+    async fn claimed_rewards(&self) -> Option<ClaimedRewards> {
+        self.snapshot
+            .claimed_rewards
+            .as_ref()
+            .map(|(chips, straps)| ClaimedRewards {
+                chips: *chips,
+                straps: strap_amounts(straps),
+            })
+    }
+
+    /// Every bet placed, optionally only those on `roll`
+    async fn bets(&self, roll: Option<Roll>) -> Vec<Bet> {
+        let roll = roll.map(events::Roll::from);
+        self.snapshot
+            .per_roll_bets
+            .iter()
+            .filter(|roll_bets| roll.is_none_or(|roll| roll == roll_bets.roll))
+            .flat_map(|roll_bets| {
+                roll_bets.bets.iter().map(|bet| Bet {
+                    roll: roll_bets.roll.into(),
+                    bet_roll_index: bet.bet_roll_index,
+                    amount: bet.amount,
+                    strap: match &bet.kind {
+                        AccountBetKind::Chip => None,
+                        AccountBetKind::Strap(strap) => Some(strap.into()),
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct HistoricalModifier {
+    roll_index: u32,
+    modifier: Modifier,
+    modifier_roll: Roll,
+}
+
+impl From<&ActiveModifier> for HistoricalModifier {
+    fn from(modifier: &ActiveModifier) -> Self {
+        Self {
+            roll_index: modifier.roll_index,
+            modifier: modifier.modifier.into(),
+            modifier_roll: modifier.modifier_roll.into(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct HistoricalAccount {
+    identity: String,
+    account: Account,
+}
+
+/// A finished game
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct HistoricalGame {
+    game_id: u32,
+    rolls: Vec<Roll>,
+    modifiers: Vec<HistoricalModifier>,
+    strap_rewards: Vec<StrapReward>,
+    #[graphql(skip)]
+    snapshot: HistoricalSnapshot,
+}
+
+#[ComplexObject]
+impl HistoricalGame {
+    /// Players of the game, optionally only `identity`
+    async fn accounts(
+        &self,
+        identity: Option<String>,
+    ) -> async_graphql::Result<Vec<HistoricalAccount>> {
+        let identity = identity.as_deref().map(parse_identity).transpose()?;
+        Ok(self
+            .snapshot
+            .accounts
+            .iter()
+            .filter(|account| {
+                identity.is_none_or(|identity| identity == account.identity)
+            })
+            .map(|account| HistoricalAccount {
+                identity: identity_string(&account.identity),
+                account: Account {
+                    snapshot: account.snapshot.clone(),
+                    block_height: None,
+                },
+            })
+            .collect())
+    }
+}
+
+impl From<HistoricalSnapshot> for HistoricalGame {
+    fn from(snapshot: HistoricalSnapshot) -> Self {
+        Self {
+            game_id: snapshot.game_id,
+            rolls: snapshot.rolls.iter().map(|roll| (*roll).into()).collect(),
+            modifiers: snapshot.modifiers.iter().map(Into::into).collect(),
+            strap_rewards: strap_rewards(&snapshot.strap_rewards),
+            snapshot,
+        }
+    }
+}
+
+fn parse_identity(identity: &str) -> async_graphql::Result<Identity> {
+    identity::parse_identity(identity, None)
+        .map_err(|e| async_graphql::Error::new(e.to_string()))
+}
+
+fn identity_string(identity: &Identity) -> String {
+    match identity {
+        Identity::Address(address) => address.to_string(),
+        Identity::ContractId(contract_id) => contract_id.to_string(),
+    }
+}
+
+/// Send the query built by `query` and wait for its response
+async fn ask<T>(
+    ctx: &Context<'_>,
+    query: impl FnOnce(oneshot::Sender<QueryResult<T>>) -> Query,
+) -> async_graphql::Result<QueryResult<T>> {
+    let (sender, receiver) = oneshot::channel();
+    ctx.data::<QueryDispatcher>()?
+        .dispatch(query(sender))
+        .await
+        .map_err(|_| async_graphql::Error::new("unable to forward query"))?;
+    receiver
+        .await
+        .map_err(|_| async_graphql::Error::new("query responder dropped"))
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn latest_snapshot(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Overview> {
+        let (mut snapshot, block_height) = ask(ctx, Query::LatestSnapshot).await??;
+        snapshot.current_block_height = block_height;
+        Ok(Overview {
+            snapshot,
+            block_height,
+        })
+    }
+
+    /// A player's account in the current game, or in `game_id` if given
+    async fn account(
+        &self,
+        ctx: &Context<'_>,
+        identity: String,
+        game_id: Option<u32>,
+    ) -> async_graphql::Result<Option<Account>> {
+        let identity = parse_identity(&identity)?;
+        let account = match game_id {
+            Some(game_id) => {
+                ask(ctx, |sender| {
+                    Query::historical_account_summary(identity, game_id, sender)
+                })
+                .await??
+            }
+            None => {
+                ask(ctx, |sender| {
+                    Query::latest_account_summary(identity, sender)
+                })
+                .await??
+            }
+        };
+        Ok(account.map(|(snapshot, block_height)| Account {
+            snapshot,
+            block_height: Some(block_height),
+        }))
+    }
+
+    async fn historical_game(
+        &self,
+        ctx: &Context<'_>,
+        game_id: u32,
+    ) -> async_graphql::Result<Option<HistoricalGame>> {
+        match ask(ctx, |sender| Query::historical_snapshot(game_id, sender)).await? {
+            Ok(snapshot) => Ok(Some(snapshot.into())),
+            Err(QueryError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every strap the indexer has seen
+    async fn straps(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<StrapMetadata>> {
+        let straps = ask(ctx, Query::all_known_straps).await??;
+        Ok(straps
+            .iter()
+            .map(|(asset_id, strap)| StrapMetadata {
+                asset_id: asset_id.to_string(),
+                strap: strap.into(),
+            })
+            .collect())
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::{
+            in_memory_metadata_storage::InMemoryMetadataStorage,
+            in_memory_snapshot_storage::InMemorySnapshotStorage,
+            query_api::StorageReader,
+            snapshot_storage::SnapshotStorage,
+        },
+        snapshot::{
+            AccountBetPlacement,
+            HistoricalAccountSnapshot,
+        },
+    };
+    use fuels::types::Address;
+    use serde_json::json;
+
+    fn arb_account(total_chip_bet: u64) -> AccountSnapshot {
+        let mut snapshot = AccountSnapshot {
+            total_chip_bet,
+            ..AccountSnapshot::default()
+        };
+        snapshot.per_roll_bets[4].bets.push(AccountBetPlacement {
+            bet_roll_index: 0,
+            amount: total_chip_bet,
+            kind: AccountBetKind::Chip,
+        });
+        snapshot
+    }
+
+    #[tokio::test]
+    async fn schema__historical_game__selects_fields_and_filters_accounts() {
+        // given
+        let player = Identity::Address(Address::from([1u8; 32]));
+        let other = Identity::Address(Address::from([2u8; 32]));
+        let historical = HistoricalSnapshot {
+            game_id: 3,
+            rolls: vec![events::Roll::Six, events::Roll::Seven],
+            modifiers: vec![ActiveModifier::new(
+                1,
+                events::Modifier::Lucky,
+                events::Roll::Six,
+            )],
+            strap_rewards: Vec::new(),
+            accounts: vec![
+                HistoricalAccountSnapshot {
+                    identity: player,
+                    snapshot: arb_account(40),
+                },
+                HistoricalAccountSnapshot {
+                    identity: other,
+                    snapshot: arb_account(70),
+                },
+            ],
+        };
+        let mut storage = InMemorySnapshotStorage::new();
+        storage.write_historical_snapshot(3, &historical).unwrap();
+        let reader = StorageReader::new(storage, InMemoryMetadataStorage::default());
+        let schema = schema(QueryDispatcher::Storage(reader));
+        let player_id = identity_string(&player);
+        let request = format!(
+            r#"{{
+                historicalGame(gameId: 3) {{
+                    rolls
+                    modifiers {{ modifier modifierRoll }}
+                    accounts(identity: "{player_id}") {{
+                        identity
+                        account {{ totalChipBet bets(roll: SIX) {{ amount strap {{ level }} }} }}
+                    }}
+                }}
+            }}"#
+        );
+
+        // when
+        let response = schema.execute(request).await;
+
+        // then
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let expected = json!({
+            "historicalGame": {
+                "rolls": ["SIX", "SEVEN"],
+                "modifiers": [{ "modifier": "LUCKY", "modifierRoll": "SIX" }],
+                "accounts": [{
+                    "identity": player_id,
+                    "account": {
+                        "totalChipBet": 40,
+                        "bets": [{ "amount": 40, "strap": null }],
+                    },
+                }],
+            }
+        });
+        assert_eq!(response.data.into_json().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn schema__invalid_identity_argument__returns_error() {
+        // given
+        let reader = StorageReader::new(
+            InMemorySnapshotStorage::new(),
+            InMemoryMetadataStorage::default(),
+        );
+        let schema = schema(QueryDispatcher::Storage(reader));
+
+        // when
+        let response = schema
+            .execute(r#"{ account(identity: "not-an-address") { totalChipBet } }"#)
+            .await;
+
+        // then
+        assert_eq!(response.errors.len(), 1);
+    }
+}
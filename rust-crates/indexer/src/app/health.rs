@@ -0,0 +1,113 @@
+// Indexing lag against the node's head, for the `/ready` probe.
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+/// Blocks the indexer may trail the node's head by and still count as ready
+pub const DEFAULT_MAX_READY_LAG: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexingStatus {
+    pub ready: bool,
+    /// Height of the latest committed snapshot, unset until the first block is indexed
+    pub indexed_height: Option<u32>,
+    /// Latest block height reported by the node, unset until it has been polled
+    pub node_height: Option<u32>,
+    /// `node_height - indexed_height`, unset unless both are known
+    pub lag: Option<u32>,
+    pub max_lag: u32,
+}
+
+/// The node's head height, shared between the task polling the node and the query
+/// server. Cheap to clone; every clone sees the same state.
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    inner: Arc<Mutex<ReadinessState>>,
+}
+
+#[derive(Debug)]
+struct ReadinessState {
+    node_height: Option<u32>,
+    max_lag: u32,
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_READY_LAG)
+    }
+}
+
+impl Readiness {
+    pub fn new(max_lag: u32) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(ReadinessState {
+                node_height: None,
+                max_lag,
+            })),
+        }
+    }
+
+    pub fn set_max_lag(&self, max_lag: u32) {
+        self.inner.lock().unwrap().max_lag = max_lag;
+    }
+
+    pub fn record_node_height(&self, height: u32) {
+        self.inner.lock().unwrap().node_height = Some(height);
+    }
+
+    /// Ready once the node has been heard from and `indexed_height` is within the
+    /// allowed lag of its head
+    pub fn status(&self, indexed_height: Option<u32>) -> IndexingStatus {
+        let state = self.inner.lock().unwrap();
+        let lag = match (indexed_height, state.node_height) {
+            (Some(indexed), Some(node)) => Some(node.saturating_sub(indexed)),
+            _ => None,
+        };
+        IndexingStatus {
+            ready: lag.is_some_and(|lag| lag <= state.max_lag),
+            indexed_height,
+            node_height: state.node_height,
+            lag,
+            max_lag: state.max_lag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+
+    #[test]
+    fn status__ready_only_within_max_lag_of_known_head() {
+        // given
+        let readiness = Readiness::new(5);
+
+        // when
+        let unknown_head = readiness.status(Some(100));
+        readiness.record_node_height(110);
+        let behind = readiness.status(Some(100));
+        let caught_up = readiness.status(Some(105));
+        let nothing_indexed = readiness.status(None);
+
+        // then
+        assert!(!unknown_head.ready);
+        assert_eq!(unknown_head.lag, None);
+        assert!(!behind.ready);
+        assert_eq!(behind.lag, Some(10));
+        let expected = IndexingStatus {
+            ready: true,
+            indexed_height: Some(105),
+            node_height: Some(110),
+            lag: Some(5),
+            max_lag: 5,
+        };
+        assert_eq!(caught_up, expected);
+        assert!(!nothing_indexed.ready);
+    }
+}
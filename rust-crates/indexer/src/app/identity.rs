@@ -0,0 +1,176 @@
+// Account identities as given by clients: hex or bech32, address or contract id.
+use anyhow::{
+    anyhow,
+    bail,
+};
+use bech32::FromBase32;
+use fuels::types::{
+    Address,
+    ContractId,
+    Identity,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::str::FromStr;
+
+/// Human readable part of Fuel's bech32 addresses
+pub const FUEL_BECH32_HRP: &str = "fuel";
+
+const ADDRESS_PREFIX: &str = "address:";
+const CONTRACT_PREFIX: &str = "contract:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityKind {
+    Address,
+    Contract,
+}
+
+/// Parse `raw` as 32 bytes of hex (with or without `0x`) or a `fuel1...` bech32 string.
+/// It is an `Address` unless prefixed with `contract:` or `kind` says otherwise; an
+/// `address:` prefix is also accepted. A prefix that contradicts `kind` is an error.
+pub fn parse_identity(raw: &str, kind: Option<IdentityKind>) -> crate::Result<Identity> {
+    let (prefixed, body) = if let Some(body) = raw.strip_prefix(CONTRACT_PREFIX) {
+        (Some(IdentityKind::Contract), body)
+    } else if let Some(body) = raw.strip_prefix(ADDRESS_PREFIX) {
+        (Some(IdentityKind::Address), body)
+    } else {
+        (None, raw)
+    };
+    let kind = match (prefixed, kind) {
+        (Some(prefixed), Some(kind)) if prefixed != kind => {
+            bail!(
+                "identity `{raw}` is prefixed as {prefixed:?} but {kind:?} was requested"
+            )
+        }
+        (prefixed, kind) => prefixed.or(kind).unwrap_or(IdentityKind::Address),
+    };
+
+    let bytes = if is_bech32(body) {
+        decode_bech32(body)
+            .map_err(|e| anyhow!("invalid bech32 identity `{raw}`: {e}"))?
+    } else {
+        let address = Address::from_str(body).map_err(|_| {
+            anyhow!(
+                "invalid identity `{raw}`: expected 32 bytes of hex (0x...) or a bech32 \
+                 `{FUEL_BECH32_HRP}1...` string, optionally prefixed with \
+                 `{ADDRESS_PREFIX}` or `{CONTRACT_PREFIX}`"
+            )
+        })?;
+        <[u8; 32]>::from(address)
+    };
+
+    Ok(match kind {
+        IdentityKind::Address => Identity::Address(Address::from(bytes)),
+        IdentityKind::Contract => Identity::ContractId(ContractId::from(bytes)),
+    })
+}
+
+fn is_bech32(value: &str) -> bool {
+    let prefix = "fuel1";
+    value
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+fn decode_bech32(value: &str) -> crate::Result<[u8; 32]> {
+    let (hrp, data, _variant) = bech32::decode(value)?;
+    if hrp != FUEL_BECH32_HRP {
+        bail!("expected the `{FUEL_BECH32_HRP}` prefix, got `{hrp}`");
+    }
+    let bytes = Vec::<u8>::from_base32(&data)?;
+    let len = bytes.len();
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("expected 32 bytes, got {len}"))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+    use bech32::{
+        ToBase32,
+        Variant,
+    };
+
+    const BYTES: [u8; 32] = [7; 32];
+
+    fn bech32(bytes: &[u8]) -> String {
+        bech32::encode(FUEL_BECH32_HRP, bytes.to_base32(), Variant::Bech32m).unwrap()
+    }
+
+    #[test]
+    fn parse_identity__accepts_hex_with_or_without_0x() {
+        // given
+        let address = Address::from(BYTES);
+
+        // when
+        let bare = parse_identity(&format!("{address:x}"), None).unwrap();
+        let prefixed = parse_identity(&format!("{address:#x}"), None).unwrap();
+
+        // then
+        assert_eq!(bare, Identity::Address(address));
+        assert_eq!(prefixed, Identity::Address(address));
+    }
+
+    #[test]
+    fn parse_identity__contract_by_prefix_or_kind() {
+        // given
+        let hex = format!("{:#x}", Address::from(BYTES));
+        let expected = Identity::ContractId(ContractId::from(BYTES));
+
+        // when
+        let by_prefix = parse_identity(&format!("contract:{hex}"), None).unwrap();
+        let by_kind = parse_identity(&hex, Some(IdentityKind::Contract)).unwrap();
+        let conflicting =
+            parse_identity(&format!("address:{hex}"), Some(IdentityKind::Contract));
+
+        // then
+        assert_eq!(by_prefix, expected);
+        assert_eq!(by_kind, expected);
+        let message = conflicting.unwrap_err().to_string();
+        assert!(message.contains("prefixed as Address"), "{message}");
+    }
+
+    #[test]
+    fn parse_identity__accepts_bech32() {
+        // given
+        let encoded = bech32(&BYTES);
+
+        // when
+        let address = parse_identity(&encoded, None).unwrap();
+        let contract = parse_identity(&format!("contract:{encoded}"), None).unwrap();
+        let upper = parse_identity(&encoded.to_uppercase(), None).unwrap();
+
+        // then
+        assert_eq!(address, Identity::Address(Address::from(BYTES)));
+        assert_eq!(contract, Identity::ContractId(ContractId::from(BYTES)));
+        assert_eq!(upper, address);
+    }
+
+    #[test]
+    fn parse_identity__rejects_malformed_input_with_reason() {
+        // given
+        let mut corrupted = bech32(&BYTES);
+        let last = corrupted.pop().unwrap();
+        corrupted.push(if last == 'q' { 'p' } else { 'q' });
+        let cases = [
+            ("not-an-identity".to_string(), "expected 32 bytes of hex"),
+            ("0x1234".to_string(), "expected 32 bytes of hex"),
+            (corrupted, "invalid bech32 identity"),
+            (bech32(&BYTES[..20]), "expected 32 bytes, got 20"),
+        ];
+
+        for (raw, reason) in cases {
+            // when
+            let result = parse_identity(&raw, None);
+
+            // then
+            let message = result.unwrap_err().to_string();
+            assert!(message.contains(reason), "{raw}: {message}");
+        }
+    }
+}
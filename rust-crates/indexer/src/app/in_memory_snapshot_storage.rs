@@ -1,49 +1,95 @@
 use crate::{
-    app::snapshot_storage::SnapshotStorage,
+    app::snapshot_storage::{
+        HistoryOrder,
+        NotFound,
+        SnapshotStorage,
+    },
     snapshot::{
         AccountSnapshot,
+        GameWorkingState,
         HistoricalSnapshot,
+        HouseLedgerEntry,
         OverviewSnapshot,
+        PlayerGameStats,
+        StrapSupplyChange,
     },
 };
 use fuels::types::Identity;
 use std::{
-    collections::HashMap,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
     sync::{
         Arc,
         Mutex,
     },
 };
 
-type AccountSnapshotMap = HashMap<String, HashMap<u32, (AccountSnapshot, u32)>>;
+// Identity, then game id, then every height the account changed at in that game
+type AccountSnapshotMap = HashMap<String, HashMap<u32, BTreeMap<u32, AccountSnapshot>>>;
 type SharedAccountSnapshots = Arc<Mutex<AccountSnapshotMap>>;
 type SharedHistoricalSnapshots = Arc<Mutex<HashMap<u32, HistoricalSnapshot>>>;
+type HistoricalAccountUpdates = Vec<(u32, Identity, AccountSnapshot, u32)>;
+type SharedHistoricalAccounts = Arc<Mutex<HistoricalAccountUpdates>>;
+type SharedGameId = Arc<Mutex<u32>>;
 type SharedOverviewSnapshot = Arc<Mutex<Option<(OverviewSnapshot, u32)>>>;
+type SharedOverviewHistory = Arc<Mutex<BTreeMap<u32, OverviewSnapshot>>>;
+type SharedWorkingState = Arc<Mutex<BTreeMap<u32, GameWorkingState>>>;
+type SharedHouseLedger = Arc<Mutex<Vec<(HouseLedgerEntry, u32)>>>;
+type SharedStrapSupply = Arc<Mutex<Vec<(StrapSupplyChange, u32)>>>;
+type SharedBlockTimestamps = Arc<Mutex<BTreeMap<u32, u64>>>;
+// Game id and identity, then every height the stats changed at
+type PlayerStatsMap = BTreeMap<(u32, String), (Identity, BTreeMap<u32, PlayerGameStats>)>;
+type SharedPlayerStats = Arc<Mutex<PlayerStatsMap>>;
 
 #[derive(Clone)]
 pub struct InMemorySnapshotStorage {
-    latest_game_id: u32,
+    latest_game_id: SharedGameId,
     snapshot: SharedOverviewSnapshot,
+    overview_history: SharedOverviewHistory,
     account_snapshots: SharedAccountSnapshots,
     historical_snapshots: SharedHistoricalSnapshots,
+    // Oldest first, so applying them in order leaves the latest update of each account
+    historical_accounts: SharedHistoricalAccounts,
+    working_state: SharedWorkingState,
+    house_ledger: SharedHouseLedger,
+    strap_supply: SharedStrapSupply,
+    block_timestamps: SharedBlockTimestamps,
+    player_stats: SharedPlayerStats,
 }
 
 impl InMemorySnapshotStorage {
     pub fn new() -> Self {
         Self {
-            latest_game_id: 0,
+            latest_game_id: Arc::new(Mutex::new(0)),
             snapshot: Arc::new(Mutex::new(None)),
+            overview_history: Arc::new(Mutex::new(BTreeMap::new())),
             account_snapshots: Arc::new(Mutex::new(HashMap::new())),
             historical_snapshots: Arc::new(Mutex::new(HashMap::new())),
+            historical_accounts: Arc::new(Mutex::new(Vec::new())),
+            working_state: Arc::new(Mutex::new(BTreeMap::new())),
+            house_ledger: Arc::new(Mutex::new(Vec::new())),
+            strap_supply: Arc::new(Mutex::new(Vec::new())),
+            block_timestamps: Arc::new(Mutex::new(BTreeMap::new())),
+            player_stats: Arc::new(Mutex::new(BTreeMap::new())),
         }
     }
 
     pub fn new_with_snapshot(snapshot: OverviewSnapshot, height: u32) -> Self {
+        let history = BTreeMap::from([(height, snapshot.clone())]);
         Self {
-            latest_game_id: 0,
+            latest_game_id: Arc::new(Mutex::new(snapshot.game_id)),
             snapshot: Arc::new(Mutex::new(Some((snapshot, height)))),
+            overview_history: Arc::new(Mutex::new(history)),
             account_snapshots: Arc::new(Mutex::new(HashMap::new())),
             historical_snapshots: Arc::new(Mutex::new(HashMap::new())),
+            historical_accounts: Arc::new(Mutex::new(Vec::new())),
+            working_state: Arc::new(Mutex::new(BTreeMap::new())),
+            house_ledger: Arc::new(Mutex::new(Vec::new())),
+            strap_supply: Arc::new(Mutex::new(Vec::new())),
+            block_timestamps: Arc::new(Mutex::new(BTreeMap::new())),
+            player_stats: Arc::new(Mutex::new(BTreeMap::new())),
         }
     }
 
@@ -59,9 +105,44 @@ impl InMemorySnapshotStorage {
         self.historical_snapshots.clone()
     }
 
+    pub fn working_state(&self) -> SharedWorkingState {
+        self.working_state.clone()
+    }
+
+    pub fn house_ledger(&self) -> SharedHouseLedger {
+        self.house_ledger.clone()
+    }
+
+    pub fn strap_supply(&self) -> SharedStrapSupply {
+        self.strap_supply.clone()
+    }
+
+    pub fn block_timestamps(&self) -> SharedBlockTimestamps {
+        self.block_timestamps.clone()
+    }
+
+    pub fn player_stats(&self) -> SharedPlayerStats {
+        self.player_stats.clone()
+    }
+
     pub fn identity_key(account: &Identity) -> String {
         format!("{:?}", account)
     }
+
+    fn with_account_updates(
+        &self,
+        mut snapshot: HistoricalSnapshot,
+    ) -> HistoricalSnapshot {
+        let updates = self.historical_accounts.lock().unwrap();
+        let game_id = snapshot.game_id;
+        for (_, account, account_snapshot, _) in updates
+            .iter()
+            .filter(|(update_game_id, ..)| *update_game_id == game_id)
+        {
+            snapshot.upsert_account(*account, account_snapshot.clone());
+        }
+        snapshot
+    }
 }
 
 impl Default for InMemorySnapshotStorage {
@@ -75,7 +156,7 @@ impl SnapshotStorage for InMemorySnapshotStorage {
         let guard = self.snapshot.lock().unwrap();
         match &*guard {
             Some(snapshot) => Ok(snapshot.clone()),
-            None => Err(anyhow::anyhow!("No snapshot found")),
+            None => Err(NotFound("No snapshot found".to_string()).into()),
         }
     }
 
@@ -83,14 +164,8 @@ impl SnapshotStorage for InMemorySnapshotStorage {
         &self,
         account: &Identity,
     ) -> crate::Result<Option<(AccountSnapshot, u32)>> {
-        let key = Self::identity_key(account);
-        let guard = self.account_snapshots.lock().unwrap();
-        let game_id = self.latest_game_id;
-        let maybe_snapshot = guard
-            .get(&key)
-            .and_then(|inner| inner.get(&game_id))
-            .cloned();
-        Ok(maybe_snapshot)
+        let game_id = *self.latest_game_id.lock().unwrap();
+        self.account_snapshot_at(account, game_id)
     }
 
     fn account_snapshot_at(
@@ -103,7 +178,8 @@ impl SnapshotStorage for InMemorySnapshotStorage {
         let maybe_snapshot = guard
             .get(&key)
             .and_then(|inner| inner.get(&game_id))
-            .cloned();
+            .and_then(|versions| versions.iter().next_back())
+            .map(|(height, snapshot)| (snapshot.clone(), *height));
         Ok(maybe_snapshot)
     }
 
@@ -113,8 +189,12 @@ impl SnapshotStorage for InMemorySnapshotStorage {
         height: u32,
     ) -> crate::Result<()> {
         let mut guard = self.snapshot.lock().unwrap();
-        self.latest_game_id = snapshot.game_id;
+        *self.latest_game_id.lock().unwrap() = snapshot.game_id;
         *guard = Some((snapshot.clone(), height));
+        self.overview_history
+            .lock()
+            .unwrap()
+            .insert(height, snapshot.clone());
         Ok(())
     }
 
@@ -128,20 +208,203 @@ impl SnapshotStorage for InMemorySnapshotStorage {
         let key = Self::identity_key(account);
         let mut guard = self.account_snapshots.lock().unwrap();
         let inner_map = guard.entry(key).or_default();
-        inner_map.insert(game_id, (account_snapshot.clone(), height));
+        inner_map
+            .entry(game_id)
+            .or_default()
+            .insert(height, account_snapshot.clone());
         Ok(())
     }
 
-    fn roll_back_snapshots(&mut self, _to_height: u32) -> crate::Result<()> {
-        todo!()
+    fn player_stats_at(
+        &self,
+        player: &Identity,
+        game_id: u32,
+    ) -> crate::Result<Option<PlayerGameStats>> {
+        let guard = self.player_stats.lock().unwrap();
+        Ok(guard
+            .get(&(game_id, Self::identity_key(player)))
+            .and_then(|(_, versions)| versions.values().next_back())
+            .cloned())
     }
 
-    fn historical_snapshots(&self, game_id: u32) -> crate::Result<HistoricalSnapshot> {
-        let guard = self.historical_snapshots.lock().unwrap();
+    fn update_player_stats(
+        &mut self,
+        player: &Identity,
+        game_id: u32,
+        stats: &PlayerGameStats,
+        height: u32,
+    ) -> crate::Result<()> {
+        let mut guard = self.player_stats.lock().unwrap();
         guard
+            .entry((game_id, Self::identity_key(player)))
+            .or_insert_with(|| (*player, BTreeMap::new()))
+            .1
+            .insert(height, stats.clone());
+        Ok(())
+    }
+
+    fn player_stats_in_games(
+        &self,
+        from_game: u32,
+        to_game: u32,
+    ) -> crate::Result<Vec<(Identity, u32, PlayerGameStats)>> {
+        if from_game > to_game {
+            return Ok(Vec::new());
+        }
+        let guard = self.player_stats.lock().unwrap();
+        Ok(guard
+            .range((from_game, String::new())..)
+            .take_while(|((game_id, _), _)| *game_id <= to_game)
+            .filter_map(|((game_id, _), (player, versions))| {
+                let stats = versions.values().next_back()?;
+                Some((*player, *game_id, stats.clone()))
+            })
+            .collect())
+    }
+
+    fn latest_working_state(&self) -> crate::Result<Option<(GameWorkingState, u32)>> {
+        let guard = self.working_state.lock().unwrap();
+        Ok(guard
+            .iter()
+            .next_back()
+            .map(|(height, state)| (state.clone(), *height)))
+    }
+
+    fn update_working_state(
+        &mut self,
+        state: &GameWorkingState,
+        height: u32,
+    ) -> crate::Result<()> {
+        let mut guard = self.working_state.lock().unwrap();
+        guard.insert(height, state.clone());
+        Ok(())
+    }
+
+    fn record_house_ledger_entry(
+        &mut self,
+        entry: &HouseLedgerEntry,
+        height: u32,
+    ) -> crate::Result<()> {
+        let mut guard = self.house_ledger.lock().unwrap();
+        guard.push((entry.clone(), height));
+        Ok(())
+    }
+
+    fn house_ledger(&self) -> crate::Result<Vec<(HouseLedgerEntry, u32)>> {
+        let guard = self.house_ledger.lock().unwrap();
+        Ok(guard.clone())
+    }
+
+    fn record_strap_supply_change(
+        &mut self,
+        change: &StrapSupplyChange,
+        height: u32,
+    ) -> crate::Result<()> {
+        let mut guard = self.strap_supply.lock().unwrap();
+        guard.push((change.clone(), height));
+        Ok(())
+    }
+
+    fn strap_supply_changes(&self) -> crate::Result<Vec<(StrapSupplyChange, u32)>> {
+        let guard = self.strap_supply.lock().unwrap();
+        Ok(guard.clone())
+    }
+
+    fn record_block_timestamp(
+        &mut self,
+        height: u32,
+        timestamp: u64,
+    ) -> crate::Result<()> {
+        let mut guard = self.block_timestamps.lock().unwrap();
+        guard.insert(height, timestamp);
+        Ok(())
+    }
+
+    fn block_timestamp_at_or_before(
+        &self,
+        height: u32,
+    ) -> crate::Result<Option<(u32, u64)>> {
+        let guard = self.block_timestamps.lock().unwrap();
+        Ok(guard
+            .range(..=height)
+            .next_back()
+            .map(|(height, timestamp)| (*height, *timestamp)))
+    }
+
+    fn roll_back_snapshots(&mut self, to_height: u32) -> crate::Result<()> {
+        let mut history = self.overview_history.lock().unwrap();
+        history.retain(|height, _| *height <= to_height);
+        let latest = history
+            .iter()
+            .next_back()
+            .map(|(height, snapshot)| (snapshot.clone(), *height));
+        *self.latest_game_id.lock().unwrap() = latest
+            .as_ref()
+            .map(|(snapshot, _)| snapshot.game_id)
+            .unwrap_or_default();
+        *self.snapshot.lock().unwrap() = latest;
+
+        let mut accounts = self.account_snapshots.lock().unwrap();
+        for per_game in accounts.values_mut() {
+            for versions in per_game.values_mut() {
+                versions.retain(|height, _| *height <= to_height);
+            }
+            per_game.retain(|_, versions| !versions.is_empty());
+        }
+        accounts.retain(|_, per_game| !per_game.is_empty());
+
+        let mut player_stats = self.player_stats.lock().unwrap();
+        for (_, versions) in player_stats.values_mut() {
+            versions.retain(|height, _| *height <= to_height);
+        }
+        player_stats.retain(|_, (_, versions)| !versions.is_empty());
+
+        let mut working_state = self.working_state.lock().unwrap();
+        working_state.retain(|height, _| *height <= to_height);
+
+        let mut house_ledger = self.house_ledger.lock().unwrap();
+        house_ledger.retain(|(_, height)| *height <= to_height);
+
+        let mut strap_supply = self.strap_supply.lock().unwrap();
+        strap_supply.retain(|(_, height)| *height <= to_height);
+
+        let mut block_timestamps = self.block_timestamps.lock().unwrap();
+        block_timestamps.retain(|height, _| *height <= to_height);
+
+        let mut historical_accounts = self.historical_accounts.lock().unwrap();
+        historical_accounts.retain(|(.., height)| *height <= to_height);
+        Ok(())
+    }
+
+    fn prune_from(&mut self, from_height: u32) -> crate::Result<()> {
+        if let Some(to_height) = from_height.checked_sub(1) {
+            return self.roll_back(to_height);
+        }
+        *self.latest_game_id.lock().unwrap() = 0;
+        *self.snapshot.lock().unwrap() = None;
+        self.overview_history.lock().unwrap().clear();
+        self.account_snapshots.lock().unwrap().clear();
+        self.historical_snapshots.lock().unwrap().clear();
+        self.historical_accounts.lock().unwrap().clear();
+        self.working_state.lock().unwrap().clear();
+        self.house_ledger.lock().unwrap().clear();
+        self.strap_supply.lock().unwrap().clear();
+        self.block_timestamps.lock().unwrap().clear();
+        self.player_stats.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn historical_snapshots(&self, game_id: u32) -> crate::Result<HistoricalSnapshot> {
+        let snapshot = self
+            .historical_snapshots
+            .lock()
+            .unwrap()
             .get(&game_id)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("No historical snapshot found"))
+            .cloned();
+        let snapshot = snapshot.ok_or_else(|| {
+            NotFound(format!("No historical snapshot found for game {game_id}"))
+        })?;
+        Ok(self.with_account_updates(snapshot))
     }
 
     fn write_historical_snapshot(
@@ -153,4 +416,62 @@ impl SnapshotStorage for InMemorySnapshotStorage {
         guard.insert(game_id, snapshot.clone());
         Ok(())
     }
+
+    fn update_historical_account(
+        &mut self,
+        game_id: u32,
+        account: &Identity,
+        account_snapshot: &AccountSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        let mut guard = self.historical_accounts.lock().unwrap();
+        guard.push((game_id, *account, account_snapshot.clone(), height));
+        Ok(())
+    }
+
+    fn historical_snapshot_range(
+        &self,
+        from: Option<u32>,
+        limit: usize,
+        order: HistoryOrder,
+    ) -> crate::Result<Vec<HistoricalSnapshot>> {
+        let guard = self.historical_snapshots.lock().unwrap();
+        let mut game_ids: Vec<u32> = guard
+            .keys()
+            .copied()
+            .filter(|game_id| match (order, from) {
+                (_, None) => true,
+                (HistoryOrder::Ascending, Some(from)) => *game_id >= from,
+                (HistoryOrder::Descending, Some(from)) => *game_id <= from,
+            })
+            .collect();
+        game_ids.sort_unstable();
+        if order == HistoryOrder::Descending {
+            game_ids.reverse();
+        }
+        Ok(game_ids
+            .into_iter()
+            .take(limit)
+            .map(|game_id| self.with_account_updates(guard[&game_id].clone()))
+            .collect())
+    }
+
+    fn remove_historical_snapshots_from(&mut self, game_id: u32) -> crate::Result<()> {
+        let mut guard = self.historical_snapshots.lock().unwrap();
+        guard.retain(|id, _| *id < game_id);
+        let mut historical_accounts = self.historical_accounts.lock().unwrap();
+        historical_accounts.retain(|(id, ..)| *id < game_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InMemorySnapshotStorage;
+    use crate::app::in_memory_metadata_storage::InMemoryMetadataStorage;
+
+    crate::snapshot_storage_conformance_tests!(|| (
+        InMemorySnapshotStorage::new(),
+        InMemoryMetadataStorage::new()
+    ));
 }
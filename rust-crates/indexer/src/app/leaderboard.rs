@@ -0,0 +1,263 @@
+// Per-player aggregates over a window of games, ranked by one metric.
+use crate::snapshot::PlayerGameStats;
+use anyhow::{
+    Context,
+    anyhow,
+};
+use fuels::types::Identity;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    ChipsWagered,
+    ChipsWon,
+    #[default]
+    NetPnl,
+    GamesPlayed,
+    HighestStrapLevel,
+    HitRate,
+}
+
+/// Games a leaderboard covers. Parsed from `all`, `last:<n>` or `games:<from>-<to>`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderboardWindow {
+    #[default]
+    AllTime,
+    /// The last `n` games, counting the one in progress
+    LastGames(u32),
+    /// Games `from..=to`
+    GameRange { from: u32, to: u32 },
+}
+
+impl LeaderboardWindow {
+    /// First and last game id covered while `current_game` is in progress. The range is
+    /// empty (`from > to`) if the window covers no games.
+    pub fn game_range(&self, current_game: u32) -> (u32, u32) {
+        match *self {
+            Self::AllTime => (0, u32::MAX),
+            Self::LastGames(0) => (1, 0),
+            Self::LastGames(n) => (current_game.saturating_sub(n - 1), current_game),
+            Self::GameRange { from, to } => (from, to),
+        }
+    }
+}
+
+impl FromStr for LeaderboardWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(window: &str) -> crate::Result<Self> {
+        if window == "all" {
+            return Ok(Self::AllTime);
+        }
+        if let Some(n) = window.strip_prefix("last:") {
+            let n = n.parse().context("parse number of games")?;
+            return Ok(Self::LastGames(n));
+        }
+        if let Some(range) = window.strip_prefix("games:") {
+            let (from, to) = range
+                .split_once('-')
+                .ok_or_else(|| anyhow!("game range must look like <from>-<to>"))?;
+            let from = from.parse().context("parse first game id")?;
+            let to = to.parse().context("parse last game id")?;
+            return Ok(Self::GameRange { from, to });
+        }
+        Err(anyhow!("unknown leaderboard window {window}"))
+    }
+}
+
+impl fmt::Display for LeaderboardWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AllTime => write!(f, "all"),
+            Self::LastGames(n) => write!(f, "last:{n}"),
+            Self::GameRange { from, to } => write!(f, "games:{from}-{to}"),
+        }
+    }
+}
+
+/// A player's `PlayerGameStats` summed over a window
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerAggregate {
+    pub chips_wagered: u64,
+    pub chips_won: u64,
+    pub net_pnl: i64,
+    pub games_played: u32,
+    /// Games in which the player's claim paid out
+    pub games_hit: u32,
+    pub highest_strap_level: u8,
+    /// `games_hit / games_played`
+    pub hit_rate: f64,
+}
+
+impl PlayerAggregate {
+    fn add_game(&mut self, stats: &PlayerGameStats) {
+        self.chips_wagered = self.chips_wagered.saturating_add(stats.chips_wagered);
+        self.chips_won = self.chips_won.saturating_add(stats.chips_won);
+        self.games_played += 1;
+        if stats.hit {
+            self.games_hit += 1;
+        }
+        self.highest_strap_level =
+            self.highest_strap_level.max(stats.highest_strap_level);
+    }
+
+    fn finish(&mut self) {
+        let won = i64::try_from(self.chips_won).unwrap_or(i64::MAX);
+        let wagered = i64::try_from(self.chips_wagered).unwrap_or(i64::MAX);
+        self.net_pnl = won.saturating_sub(wagered);
+        self.hit_rate = f64::from(self.games_hit) / f64::from(self.games_played.max(1));
+    }
+
+    fn compare(&self, other: &Self, metric: LeaderboardMetric) -> Ordering {
+        match metric {
+            LeaderboardMetric::ChipsWagered => {
+                self.chips_wagered.cmp(&other.chips_wagered)
+            }
+            LeaderboardMetric::ChipsWon => self.chips_won.cmp(&other.chips_won),
+            LeaderboardMetric::NetPnl => self.net_pnl.cmp(&other.net_pnl),
+            LeaderboardMetric::GamesPlayed => self.games_played.cmp(&other.games_played),
+            LeaderboardMetric::HighestStrapLevel => {
+                self.highest_strap_level.cmp(&other.highest_strap_level)
+            }
+            LeaderboardMetric::HitRate => self.hit_rate.total_cmp(&other.hit_rate),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub player: Identity,
+    pub stats: PlayerAggregate,
+}
+
+/// Sum per-game stats by player and return the top `limit` players by `metric`
+pub fn rank_players(
+    stats: impl IntoIterator<Item = (Identity, u32, PlayerGameStats)>,
+    metric: LeaderboardMetric,
+    limit: usize,
+) -> Vec<LeaderboardEntry> {
+    let mut players: HashMap<String, (Identity, PlayerAggregate)> = HashMap::new();
+    for (player, _, game_stats) in stats {
+        players
+            .entry(format!("{player:?}"))
+            .or_insert_with(|| (player, PlayerAggregate::default()))
+            .1
+            .add_game(&game_stats);
+    }
+    let mut players: Vec<_> = players.into_iter().collect();
+    for (_, (_, aggregate)) in &mut players {
+        aggregate.finish();
+    }
+    // Best first; ties go to the more active player, then to a stable identity order
+    players.sort_by(|(a_key, (_, a)), (b_key, (_, b))| {
+        b.compare(a, metric)
+            .then(b.games_played.cmp(&a.games_played))
+            .then(a_key.cmp(b_key))
+    });
+    players
+        .into_iter()
+        .take(limit)
+        .zip(1..)
+        .map(|((_, (player, stats)), rank)| LeaderboardEntry {
+            rank,
+            player,
+            stats,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+    use fuels::types::Address;
+
+    fn player(byte: u8) -> Identity {
+        Identity::Address(Address::from([byte; 32]))
+    }
+
+    fn game(chips_wagered: u64, chips_won: u64, hit: bool) -> PlayerGameStats {
+        PlayerGameStats {
+            chips_wagered,
+            chips_won,
+            hit,
+            highest_strap_level: 0,
+        }
+    }
+
+    #[test]
+    fn rank_players__sums_games_per_player_and_orders_by_metric() {
+        // given
+        let stats = vec![
+            (player(1), 1, game(100, 0, false)),
+            (player(2), 1, game(50, 200, true)),
+            (player(1), 2, game(100, 300, true)),
+            (player(3), 2, game(10, 0, false)),
+        ];
+
+        // when
+        let ranked = rank_players(stats, LeaderboardMetric::NetPnl, 2);
+
+        // then
+        let expected = vec![
+            LeaderboardEntry {
+                rank: 1,
+                player: player(2),
+                stats: PlayerAggregate {
+                    chips_wagered: 50,
+                    chips_won: 200,
+                    net_pnl: 150,
+                    games_played: 1,
+                    games_hit: 1,
+                    highest_strap_level: 0,
+                    hit_rate: 1.0,
+                },
+            },
+            LeaderboardEntry {
+                rank: 2,
+                player: player(1),
+                stats: PlayerAggregate {
+                    chips_wagered: 200,
+                    chips_won: 300,
+                    net_pnl: 100,
+                    games_played: 2,
+                    games_hit: 1,
+                    highest_strap_level: 0,
+                    hit_rate: 0.5,
+                },
+            },
+        ];
+        assert_eq!(ranked, expected);
+    }
+
+    #[test]
+    fn leaderboard_window__parses_and_resolves_game_ranges() {
+        // given
+        let windows = ["all", "last:3", "last:0", "games:4-9"];
+
+        // when
+        let parsed: Vec<LeaderboardWindow> = windows
+            .iter()
+            .map(|window| window.parse().unwrap())
+            .collect();
+
+        // then
+        let ranges: Vec<_> = parsed.iter().map(|window| window.game_range(10)).collect();
+        assert_eq!(ranges, vec![(0, u32::MAX), (8, 10), (1, 0), (4, 9)]);
+        let printed: Vec<_> = parsed.iter().map(ToString::to_string).collect();
+        assert_eq!(printed, windows);
+        assert!("last:x".parse::<LeaderboardWindow>().is_err());
+        assert!("week".parse::<LeaderboardWindow>().is_err());
+    }
+}
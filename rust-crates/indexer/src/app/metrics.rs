@@ -0,0 +1,230 @@
+// Prometheus metrics shared by the event loop and the query server, served at `/metrics`.
+use crate::snapshot::OverviewSnapshot;
+use anyhow::anyhow;
+use prometheus_client::{
+    encoding::{
+        EncodeLabelSet,
+        text::encode,
+    },
+    metrics::{
+        counter::Counter,
+        family::Family,
+        gauge::Gauge,
+        histogram::{
+            Histogram,
+            exponential_buckets,
+        },
+    },
+    registry::Registry,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// Content type of `IndexerMetrics::encode`'s output
+pub const METRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct EventLabels {
+    event: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RouteLabels {
+    route: String,
+}
+
+type HistogramFamily<Labels> = Family<Labels, Histogram, fn() -> Histogram>;
+
+// 100us up to ~3s
+fn latency_histogram() -> Histogram {
+    Histogram::new(exponential_buckets(0.0001, 2.0, 16))
+}
+
+/// Cheap to clone; every clone records into the same registry
+#[derive(Clone)]
+pub struct IndexerMetrics {
+    registry: Arc<Registry>,
+    last_indexed_height: Gauge,
+    events_processed: Family<EventLabels, Counter>,
+    event_handler_seconds: HistogramFamily<EventLabels>,
+    storage_commit_seconds: Histogram,
+    queries: Family<RouteLabels, Counter>,
+    query_seconds: HistogramFamily<RouteLabels>,
+    query_queue_depth: Gauge,
+    pot_size: Gauge,
+    chips_owed: Gauge,
+}
+
+impl Default for IndexerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IndexerMetrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::with_prefix("strapped_indexer");
+        let last_indexed_height = Gauge::default();
+        registry.register(
+            "last_indexed_height",
+            "Block height of the latest committed snapshot",
+            last_indexed_height.clone(),
+        );
+        let events_processed = Family::<EventLabels, Counter>::default();
+        registry.register(
+            "events_processed",
+            "Events applied, by event variant",
+            events_processed.clone(),
+        );
+        let event_handler_seconds: HistogramFamily<EventLabels> =
+            Family::new_with_constructor(latency_histogram);
+        registry.register(
+            "event_handler_seconds",
+            "Time spent applying one event, by event variant",
+            event_handler_seconds.clone(),
+        );
+        let storage_commit_seconds = latency_histogram();
+        registry.register(
+            "storage_commit_seconds",
+            "Time spent writing one block of changes to snapshot storage",
+            storage_commit_seconds.clone(),
+        );
+        let queries = Family::<RouteLabels, Counter>::default();
+        registry.register("queries", "HTTP queries served, by route", queries.clone());
+        let query_seconds: HistogramFamily<RouteLabels> =
+            Family::new_with_constructor(latency_histogram);
+        registry.register(
+            "query_seconds",
+            "Time spent serving one HTTP query, by route",
+            query_seconds.clone(),
+        );
+        let query_queue_depth = Gauge::default();
+        registry.register(
+            "query_queue_depth",
+            "Queries waiting for the event loop to answer them",
+            query_queue_depth.clone(),
+        );
+        let pot_size = Gauge::default();
+        registry.register(
+            "pot_size",
+            "Chips in the house pot as of the latest snapshot",
+            pot_size.clone(),
+        );
+        let chips_owed = Gauge::default();
+        registry.register(
+            "chips_owed",
+            "Chips owed to players as of the latest snapshot",
+            chips_owed.clone(),
+        );
+
+        Self {
+            registry: Arc::new(registry),
+            last_indexed_height,
+            events_processed,
+            event_handler_seconds,
+            storage_commit_seconds,
+            queries,
+            query_seconds,
+            query_queue_depth,
+            pot_size,
+            chips_owed,
+        }
+    }
+
+    pub fn observe_event(&self, event: &'static str, elapsed: Duration) {
+        let labels = EventLabels { event };
+        self.events_processed.get_or_create(&labels).inc();
+        self.event_handler_seconds
+            .get_or_create(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_commit(&self, elapsed: Duration) {
+        self.storage_commit_seconds.observe(elapsed.as_secs_f64());
+    }
+
+    /// Track the height and pot of the latest committed snapshot
+    pub fn record_snapshot(&self, snapshot: &OverviewSnapshot, height: u32) {
+        self.last_indexed_height.set(i64::from(height));
+        self.pot_size.set(saturating_i64(snapshot.pot_size));
+        self.chips_owed.set(saturating_i64(snapshot.chips_owed));
+    }
+
+    /// Track the height of a committed checkpoint, which leaves the snapshot as it was
+    pub fn record_checkpoint(&self, height: u32) {
+        self.last_indexed_height.set(i64::from(height));
+    }
+
+    pub fn observe_query(&self, route: &str, elapsed: Duration) {
+        let labels = RouteLabels {
+            route: route.to_string(),
+        };
+        self.queries.get_or_create(&labels).inc();
+        self.query_seconds
+            .get_or_create(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn set_query_queue_depth(&self, depth: usize) {
+        self.query_queue_depth
+            .set(i64::try_from(depth).unwrap_or(i64::MAX));
+    }
+
+    /// Every metric in the OpenMetrics text format
+    pub fn encode(&self) -> crate::Result<String> {
+        let mut body = String::new();
+        encode(&mut body, &self.registry)
+            .map_err(|e| anyhow!("failed to encode metrics: {e}"))?;
+        Ok(body)
+    }
+}
+
+fn saturating_i64(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+
+    #[test]
+    fn encode__includes_recorded_values() {
+        // given
+        let metrics = IndexerMetrics::new();
+        let snapshot = OverviewSnapshot {
+            pot_size: 1_500,
+            chips_owed: 200,
+            ..OverviewSnapshot::default()
+        };
+
+        // when
+        metrics.record_snapshot(&snapshot, 42);
+        metrics.observe_event("Roll", Duration::from_millis(1));
+        metrics.observe_event("Roll", Duration::from_millis(3));
+        metrics.observe_query("/snapshot/latest", Duration::from_millis(2));
+        metrics.set_query_queue_depth(3);
+
+        // then
+        let body = metrics.encode().unwrap();
+        let expected_lines = [
+            "strapped_indexer_last_indexed_height 42",
+            "strapped_indexer_pot_size 1500",
+            "strapped_indexer_chips_owed 200",
+            "strapped_indexer_events_processed_total{event=\"Roll\"} 2",
+            "strapped_indexer_event_handler_seconds_count{event=\"Roll\"} 2",
+            "strapped_indexer_queries_total{route=\"/snapshot/latest\"} 1",
+            "strapped_indexer_query_queue_depth 3",
+            "# EOF",
+        ];
+        for line in expected_lines {
+            assert!(
+                body.lines().any(|actual| actual == line),
+                "{line} in {body}"
+            );
+        }
+    }
+}
@@ -1,37 +1,357 @@
 use crate::{
-    events::Strap,
+    app::{
+        leaderboard::{
+            LeaderboardEntry,
+            LeaderboardMetric,
+            LeaderboardWindow,
+            rank_players,
+        },
+        roll_stats::{
+            RollStats,
+            roll_stats,
+        },
+        snapshot_storage::{
+            BlockChanges,
+            HistoryOrder,
+            MetadataStorage,
+            NotFound,
+            SnapshotStorage,
+        },
+        strap_supply::{
+            StrapSupplyReport,
+            strap_supply,
+        },
+    },
+    events::{
+        Roll,
+        Strap,
+    },
     snapshot::{
         AccountSnapshot,
+        HistoricalGameSummary,
         HistoricalSnapshot,
+        HouseLedgerEntry,
         OverviewSnapshot,
     },
 };
+use anyhow::anyhow;
 use fuels::types::{
     AssetId,
     Identity,
 };
-use std::future::Future;
-use tokio::sync::oneshot;
+use std::{
+    fmt,
+    future::Future,
+    sync::Arc,
+};
+use tokio::sync::{
+    mpsc,
+    oneshot,
+};
 
 pub trait QueryAPI {
     /// Returns the next query if the API is still running.
     /// Ok(None) indicates the server has shut down and no more queries will arrive.
     fn query(&mut self) -> impl Future<Output = crate::Result<Option<Query>>>;
+
+    /// Called with everything written by an event batch once it has been committed, so the
+    /// API can push updates to connected clients
+    fn publish_committed(&mut self, _changes: &BlockChanges) {}
+}
+
+/// Query API for offline runs, e.g. replaying an event archive, that never yields queries
+pub struct DisabledQueryApi;
+
+impl QueryAPI for DisabledQueryApi {
+    async fn query(&mut self) -> crate::Result<Option<Query>> {
+        std::future::pending().await
+    }
+}
+
+/// Shared read handles onto committed storage, so a query server can answer queries on its
+/// own threads instead of waiting for the event loop
+#[derive(Clone)]
+pub struct StorageReader {
+    snapshots: Arc<dyn SnapshotStorage + Send + Sync>,
+    metadata: Arc<dyn MetadataStorage + Send + Sync>,
+}
+
+impl StorageReader {
+    pub fn new<Snapshots, Metadata>(snapshots: Snapshots, metadata: Metadata) -> Self
+    where
+        Snapshots: SnapshotStorage + Send + Sync + 'static,
+        Metadata: MetadataStorage + Send + Sync + 'static,
+    {
+        Self {
+            snapshots: Arc::new(snapshots),
+            metadata: Arc::new(metadata),
+        }
+    }
+
+    pub fn answer(&self, query: Query) {
+        answer_query(query, self.snapshots.as_ref(), self.metadata.as_ref());
+    }
+}
+
+/// Where a query server sends its queries
+#[derive(Clone)]
+pub enum QueryDispatcher {
+    /// Answered by the event loop, through `QueryAPI::query`
+    EventLoop(mpsc::Sender<Query>),
+    /// Answered from committed storage on the server's own worker threads
+    Storage(StorageReader),
+}
+
+impl QueryDispatcher {
+    /// Queries sent but not yet picked up by the event loop
+    pub fn queue_depth(&self) -> usize {
+        match self {
+            Self::EventLoop(sender) => sender.max_capacity() - sender.capacity(),
+            Self::Storage(_) => 0,
+        }
+    }
+
+    pub async fn dispatch(&self, query: Query) -> crate::Result<()> {
+        match self {
+            Self::EventLoop(sender) => sender
+                .send(query)
+                .await
+                .map_err(|_| anyhow!("query channel closed")),
+            // Storage reads block, range scans for long, so keep them off the executor
+            Self::Storage(reader) => {
+                let reader = reader.clone();
+                tokio::task::spawn_blocking(move || reader.answer(query))
+                    .await
+                    .map_err(|e| anyhow!("storage query task failed: {e}"))
+            }
+        }
+    }
+}
+
+/// Why a query could not be answered, sent to its responder in place of a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// Asks for something that has not been indexed
+    NotFound(String),
+    /// Asks for something that can never exist, e.g. an inverted range
+    BadRequest(String),
+    /// Storage failed to answer
+    Internal(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(message)
+            | Self::BadRequest(message)
+            | Self::Internal(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl From<anyhow::Error> for QueryError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast_ref::<NotFound>() {
+            Some(not_found) => Self::NotFound(not_found.to_string()),
+            None => Self::Internal(format!("{error:#}")),
+        }
+    }
+}
+
+pub type QueryResult<T> = std::result::Result<T, QueryError>;
+
+/// Read the data a query asks for and send it, or why it could not be read, to the
+/// query's responder. Never fails, so a bad read can't take down whoever answers queries.
+pub fn answer_query<Snapshots, Metadata>(
+    query: Query,
+    snapshots: &Snapshots,
+    metadata: &Metadata,
+) where
+    Snapshots: SnapshotStorage + ?Sized,
+    Metadata: MetadataStorage + ?Sized,
+{
+    match query {
+        Query::LatestSnapshot(sender) => {
+            let snapshot = snapshots.latest_snapshot().map_err(QueryError::from);
+            respond("LatestSnapshot", sender, snapshot);
+        }
+        Query::IndexedHeight(sender) => {
+            // Checkpoints only record a timestamp, so the overview can trail them
+            let overview_height =
+                snapshots.latest_snapshot().ok().map(|(_, height)| height);
+            let checkpoint_height = snapshots
+                .block_timestamp_at_or_before(u32::MAX)
+                .ok()
+                .flatten()
+                .map(|(height, _)| height);
+            let height = overview_height.max(checkpoint_height);
+            respond("IndexedHeight", sender, height);
+        }
+        Query::LatestAccountSnapshot(inner) => {
+            let AccountSnapshotQuery { identity, sender } = inner;
+            let snapshot = snapshots
+                .latest_account_snapshot(&identity)
+                .map_err(QueryError::from);
+            respond("LatestAccountSnapshot", sender, snapshot);
+        }
+        Query::HistoricalSnapshot(inner) => {
+            let HistoricalSnapshotQuery { game_id, sender } = inner;
+            let snapshot = snapshots
+                .historical_snapshots(game_id)
+                .map_err(QueryError::from);
+            respond("HistoricalSnapshot", sender, snapshot);
+        }
+        Query::HistoricalGames(inner) => {
+            let HistoricalGamesQuery {
+                from,
+                limit,
+                order,
+                sender,
+            } = inner;
+            let summaries = snapshots
+                .historical_snapshot_range(from, limit, order)
+                .map(|snapshots| {
+                    snapshots.iter().map(HistoricalGameSummary::from).collect()
+                })
+                .map_err(QueryError::from);
+            respond("HistoricalGames", sender, summaries);
+        }
+        Query::HistoricalAccountSnapshot(inner) => {
+            let HistoricalAccountSnapshotQuery {
+                identity,
+                game_id,
+                sender,
+            } = inner;
+            let snapshot = snapshots
+                .account_snapshot_at(&identity, game_id)
+                .map_err(QueryError::from);
+            respond("HistoricalAccountSnapshot", sender, snapshot);
+        }
+        Query::Leaderboard(inner) => {
+            let LeaderboardQuery {
+                metric,
+                window,
+                limit,
+                sender,
+            } = inner;
+            let current_game = snapshots
+                .latest_snapshot()
+                .map(|(snapshot, _)| snapshot.game_id)
+                .unwrap_or_default();
+            let (from_game, to_game) = window.game_range(current_game);
+            let entries = snapshots
+                .player_stats_in_games(from_game, to_game)
+                .map(|stats| rank_players(stats, metric, limit))
+                .map_err(QueryError::from);
+            respond("Leaderboard", sender, entries);
+        }
+        Query::RollStats(inner) => {
+            let RollStatsQuery {
+                from_game,
+                to_game,
+                sender,
+            } = inner;
+            let stats = answer_roll_stats(snapshots, from_game, to_game);
+            respond("RollStats", sender, stats);
+        }
+        Query::AllKnownStraps(sender) => {
+            let straps = metadata.all_known_straps().map_err(QueryError::from);
+            respond("AllKnownStraps", sender, straps);
+        }
+        Query::StrapSupply(sender) => {
+            let report = snapshots
+                .strap_supply_changes()
+                .map(|changes| strap_supply(changes.iter().map(|(change, _)| change)))
+                .map_err(QueryError::from);
+            respond("StrapSupply", sender, report);
+        }
+        Query::HouseLedger(sender) => {
+            let ledger = snapshots.house_ledger().map_err(QueryError::from);
+            respond("HouseLedger", sender, ledger);
+        }
+        Query::BlockTimestamp(inner) => {
+            let BlockTimestampQuery { height, sender } = inner;
+            let timestamp = snapshots
+                .block_timestamp_at_or_before(height)
+                .map(|known| {
+                    known
+                        .filter(|(known_height, _)| *known_height == height)
+                        .map(|(_, timestamp)| timestamp)
+                })
+                .map_err(QueryError::from);
+            respond("BlockTimestamp", sender, timestamp);
+        }
+    }
+}
+
+// A dropped responder only means the client went away
+fn respond<T: fmt::Debug>(query: &str, sender: oneshot::Sender<T>, response: T) {
+    if let Err(response) = sender.send(response) {
+        tracing::debug!("`{query}` responder dropped before receiving {response:?}");
+    }
+}
+
+fn answer_roll_stats<Snapshots>(
+    snapshots: &Snapshots,
+    from_game: Option<u32>,
+    to_game: Option<u32>,
+) -> QueryResult<RollStats>
+where
+    Snapshots: SnapshotStorage + ?Sized,
+{
+    if let (Some(from), Some(to)) = (from_game, to_game)
+        && from > to
+    {
+        return Err(QueryError::BadRequest(
+            "`from` must not be after `to`".to_string(),
+        ));
+    }
+    let to_game = to_game.unwrap_or(u32::MAX);
+    let mut games: Vec<(u32, Vec<Roll>)> = snapshots
+        .historical_snapshot_range(from_game, usize::MAX, HistoryOrder::Ascending)?
+        .into_iter()
+        .take_while(|snapshot| snapshot.game_id <= to_game)
+        .map(|snapshot| (snapshot.game_id, snapshot.rolls))
+        .collect();
+    // The game in progress only has a historical snapshot once it is over
+    if let Ok((current, _)) = snapshots.latest_snapshot() {
+        let game_id = current.game_id;
+        let in_range = from_game.is_none_or(|from| game_id >= from) && game_id <= to_game;
+        let finished = games.iter().any(|(id, _)| *id == game_id);
+        if in_range && !finished {
+            games.push((game_id, current.rolls));
+        }
+    }
+    Ok(roll_stats(games.iter().map(|(_, rolls)| rolls.as_slice())))
 }
 
 #[derive(Debug)]
 pub enum Query {
-    LatestSnapshot(oneshot::Sender<(OverviewSnapshot, u32)>),
+    LatestSnapshot(oneshot::Sender<QueryResult<(OverviewSnapshot, u32)>>),
+    /// Height of the latest snapshot, `None` until something has been indexed
+    IndexedHeight(oneshot::Sender<Option<u32>>),
     LatestAccountSnapshot(AccountSnapshotQuery),
     HistoricalSnapshot(HistoricalSnapshotQuery),
     HistoricalAccountSnapshot(HistoricalAccountSnapshotQuery),
-    AllKnownStraps(oneshot::Sender<Vec<(AssetId, Strap)>>),
+    HistoricalGames(HistoricalGamesQuery),
+    Leaderboard(LeaderboardQuery),
+    RollStats(RollStatsQuery),
+    AllKnownStraps(oneshot::Sender<QueryResult<Vec<(AssetId, Strap)>>>),
+    StrapSupply(oneshot::Sender<QueryResult<StrapSupplyReport>>),
+    HouseLedger(oneshot::Sender<QueryResult<Vec<(HouseLedgerEntry, u32)>>>),
+    BlockTimestamp(BlockTimestampQuery),
 }
 
 impl Query {
+    pub fn indexed_height(sender: oneshot::Sender<Option<u32>>) -> Query {
+        Query::IndexedHeight(sender)
+    }
+
     pub fn latest_account_summary(
         identity: Identity,
-        sender: oneshot::Sender<Option<(AccountSnapshot, u32)>>,
+        sender: oneshot::Sender<QueryResult<Option<(AccountSnapshot, u32)>>>,
     ) -> Query {
         let inner = AccountSnapshotQuery { identity, sender };
         Query::LatestAccountSnapshot(inner)
@@ -39,7 +359,7 @@ impl Query {
 
     pub fn historical_snapshot(
         game_id: u32,
-        sender: oneshot::Sender<Option<HistoricalSnapshot>>,
+        sender: oneshot::Sender<QueryResult<HistoricalSnapshot>>,
     ) -> Query {
         let inner = HistoricalSnapshotQuery { game_id, sender };
         Query::HistoricalSnapshot(inner)
@@ -48,7 +368,7 @@ impl Query {
     pub fn historical_account_summary(
         identity: Identity,
         game_id: u32,
-        sender: oneshot::Sender<Option<(AccountSnapshot, u32)>>,
+        sender: oneshot::Sender<QueryResult<Option<(AccountSnapshot, u32)>>>,
     ) -> Query {
         let inner = HistoricalAccountSnapshotQuery {
             identity,
@@ -58,26 +378,147 @@ impl Query {
         Query::HistoricalAccountSnapshot(inner)
     }
 
-    pub fn all_known_straps(sender: oneshot::Sender<Vec<(AssetId, Strap)>>) -> Query {
+    pub fn historical_games(
+        from: Option<u32>,
+        limit: usize,
+        order: HistoryOrder,
+        sender: oneshot::Sender<QueryResult<Vec<HistoricalGameSummary>>>,
+    ) -> Query {
+        let inner = HistoricalGamesQuery {
+            from,
+            limit,
+            order,
+            sender,
+        };
+        Query::HistoricalGames(inner)
+    }
+
+    pub fn leaderboard(
+        metric: LeaderboardMetric,
+        window: LeaderboardWindow,
+        limit: usize,
+        sender: oneshot::Sender<QueryResult<Vec<LeaderboardEntry>>>,
+    ) -> Query {
+        let inner = LeaderboardQuery {
+            metric,
+            window,
+            limit,
+            sender,
+        };
+        Query::Leaderboard(inner)
+    }
+
+    pub fn roll_stats(
+        from_game: Option<u32>,
+        to_game: Option<u32>,
+        sender: oneshot::Sender<QueryResult<RollStats>>,
+    ) -> Query {
+        let inner = RollStatsQuery {
+            from_game,
+            to_game,
+            sender,
+        };
+        Query::RollStats(inner)
+    }
+
+    pub fn all_known_straps(
+        sender: oneshot::Sender<QueryResult<Vec<(AssetId, Strap)>>>,
+    ) -> Query {
         Query::AllKnownStraps(sender)
     }
+
+    pub fn strap_supply(
+        sender: oneshot::Sender<QueryResult<StrapSupplyReport>>,
+    ) -> Query {
+        Query::StrapSupply(sender)
+    }
+
+    pub fn house_ledger(
+        sender: oneshot::Sender<QueryResult<Vec<(HouseLedgerEntry, u32)>>>,
+    ) -> Query {
+        Query::HouseLedger(sender)
+    }
+
+    pub fn block_timestamp(
+        height: u32,
+        sender: oneshot::Sender<QueryResult<Option<u64>>>,
+    ) -> Query {
+        let inner = BlockTimestampQuery { height, sender };
+        Query::BlockTimestamp(inner)
+    }
 }
 
 #[derive(Debug)]
 pub struct AccountSnapshotQuery {
     pub identity: Identity,
-    pub sender: oneshot::Sender<Option<(AccountSnapshot, u32)>>,
+    pub sender: oneshot::Sender<QueryResult<Option<(AccountSnapshot, u32)>>>,
 }
 
 #[derive(Debug)]
 pub struct HistoricalSnapshotQuery {
     pub game_id: u32,
-    pub sender: oneshot::Sender<Option<HistoricalSnapshot>>,
+    pub sender: oneshot::Sender<QueryResult<HistoricalSnapshot>>,
 }
 
 #[derive(Debug)]
 pub struct HistoricalAccountSnapshotQuery {
     pub identity: Identity,
     pub game_id: u32,
-    pub sender: oneshot::Sender<Option<(AccountSnapshot, u32)>>,
+    pub sender: oneshot::Sender<QueryResult<Option<(AccountSnapshot, u32)>>>,
+}
+
+#[derive(Debug)]
+pub struct HistoricalGamesQuery {
+    pub from: Option<u32>,
+    pub limit: usize,
+    pub order: HistoryOrder,
+    pub sender: oneshot::Sender<QueryResult<Vec<HistoricalGameSummary>>>,
+}
+
+#[derive(Debug)]
+pub struct LeaderboardQuery {
+    pub metric: LeaderboardMetric,
+    pub window: LeaderboardWindow,
+    pub limit: usize,
+    pub sender: oneshot::Sender<QueryResult<Vec<LeaderboardEntry>>>,
+}
+
+#[derive(Debug)]
+pub struct RollStatsQuery {
+    pub from_game: Option<u32>,
+    pub to_game: Option<u32>,
+    pub sender: oneshot::Sender<QueryResult<RollStats>>,
+}
+
+#[derive(Debug)]
+pub struct BlockTimestampQuery {
+    pub height: u32,
+    pub sender: oneshot::Sender<QueryResult<Option<u64>>>,
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+
+    #[test]
+    fn query_error__from_storage_error__keeps_not_found_apart() {
+        // given
+        let not_found = anyhow::Error::from(NotFound("No snapshot found".to_string()));
+        let failure = anyhow!("disk on fire").context("reading snapshot");
+
+        // when
+        let not_found = QueryError::from(not_found);
+        let failure = QueryError::from(failure);
+
+        // then
+        assert_eq!(
+            not_found,
+            QueryError::NotFound("No snapshot found".to_string())
+        );
+        assert_eq!(
+            failure,
+            QueryError::Internal("reading snapshot: disk on fire".to_string())
+        );
+    }
 }
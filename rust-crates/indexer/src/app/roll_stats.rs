@@ -0,0 +1,225 @@
+// Observed roll distribution against the 2d6 odds the contract rolls with.
+use crate::{
+    events::Roll,
+    snapshot::ALL_ROLLS,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Ways out of 36 that the contract's `u64_to_roll` maps to each roll, in `ALL_ROLLS`
+/// order
+pub const EXPECTED_ROLL_WEIGHTS: [u32; 11] = [1, 2, 3, 4, 5, 6, 5, 4, 3, 2, 1];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollFrequency {
+    pub roll: Roll,
+    pub observed: u64,
+    pub observed_share: f64,
+    pub expected_share: f64,
+    /// Rolls expected out of the observed total
+    pub expected: f64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollStreaks {
+    pub longest_without_seven: u32,
+    /// Rolls since the last Seven
+    pub current_without_seven: u32,
+    /// Longest run of one roll repeating, and the roll
+    pub longest_repeat: Option<(Roll, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollStats {
+    pub games: u32,
+    pub total_rolls: u64,
+    pub frequencies: Vec<RollFrequency>,
+    /// Pearson's chi-square goodness of fit, unset until something has been rolled
+    pub chi_square: Option<f64>,
+    pub degrees_of_freedom: u32,
+    /// Chance of a fit at least this bad from fair dice
+    pub p_value: Option<f64>,
+    pub streaks: RollStreaks,
+}
+
+/// Statistics over the rolls of each game, given in game order
+pub fn roll_stats<'a>(games: impl IntoIterator<Item = &'a [Roll]>) -> RollStats {
+    let mut counts = [0u64; 11];
+    let mut streaks = RollStreaks::default();
+    let mut repeat: Option<(Roll, u32)> = None;
+    let mut game_count = 0;
+    for rolls in games {
+        game_count += 1;
+        for roll in rolls {
+            counts[roll_index(roll)] += 1;
+
+            if *roll == Roll::Seven {
+                streaks.current_without_seven = 0;
+            } else {
+                streaks.current_without_seven += 1;
+                streaks.longest_without_seven = streaks
+                    .longest_without_seven
+                    .max(streaks.current_without_seven);
+            }
+
+            let run = match repeat {
+                Some((last, run)) if last == *roll => run + 1,
+                _ => 1,
+            };
+            repeat = Some((*roll, run));
+            if streaks
+                .longest_repeat
+                .is_none_or(|(_, longest)| run > longest)
+            {
+                streaks.longest_repeat = Some((*roll, run));
+            }
+        }
+    }
+
+    let total_rolls: u64 = counts.iter().sum();
+    let total = total_rolls as f64;
+    let frequencies: Vec<RollFrequency> = ALL_ROLLS
+        .iter()
+        .zip(counts)
+        .zip(EXPECTED_ROLL_WEIGHTS)
+        .map(|((roll, observed), weight)| {
+            let expected_share = f64::from(weight) / 36.0;
+            RollFrequency {
+                roll: *roll,
+                observed,
+                observed_share: if total_rolls == 0 {
+                    0.0
+                } else {
+                    observed as f64 / total
+                },
+                expected_share,
+                expected: expected_share * total,
+            }
+        })
+        .collect();
+
+    let degrees_of_freedom = ALL_ROLLS.len() as u32 - 1;
+    let chi_square = (total_rolls > 0).then(|| {
+        frequencies
+            .iter()
+            .map(|frequency| {
+                let difference = frequency.observed as f64 - frequency.expected;
+                difference * difference / frequency.expected
+            })
+            .sum::<f64>()
+    });
+    let p_value =
+        chi_square.map(|statistic| chi_square_survival(statistic, degrees_of_freedom));
+
+    RollStats {
+        games: game_count,
+        total_rolls,
+        frequencies,
+        chi_square,
+        degrees_of_freedom,
+        p_value,
+        streaks,
+    }
+}
+
+fn roll_index(roll: &Roll) -> usize {
+    ALL_ROLLS
+        .iter()
+        .position(|candidate| candidate == roll)
+        .expect("ALL_ROLLS covers every roll")
+}
+
+// P(X >= statistic) for X ~ chi-square. Even degrees of freedom have the closed form
+// e^(-x/2) * sum_{i < df/2} (x/2)^i / i!
+fn chi_square_survival(statistic: f64, degrees_of_freedom: u32) -> f64 {
+    debug_assert!(degrees_of_freedom.is_multiple_of(2));
+    let half = statistic / 2.0;
+    let mut term = 1.0;
+    let mut sum = 0.0;
+    for i in 0..degrees_of_freedom / 2 {
+        if i > 0 {
+            term *= half / f64::from(i);
+        }
+        sum += term;
+    }
+    ((-half).exp() * sum).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+
+    #[test]
+    fn roll_stats__exactly_expected_rolls__fit_perfectly() {
+        // given
+        let rolls: Vec<Roll> = ALL_ROLLS
+            .iter()
+            .zip(EXPECTED_ROLL_WEIGHTS)
+            .flat_map(|(roll, weight)| std::iter::repeat_n(*roll, weight as usize))
+            .collect();
+
+        // when
+        let stats = roll_stats([rolls.as_slice()]);
+
+        // then
+        assert_eq!(stats.games, 1);
+        assert_eq!(stats.total_rolls, 36);
+        assert_eq!(stats.chi_square, Some(0.0));
+        assert_eq!(stats.p_value, Some(1.0));
+        assert_eq!(stats.degrees_of_freedom, 10);
+        let seven = &stats.frequencies[5];
+        assert_eq!(seven.roll, Roll::Seven);
+        assert_eq!(seven.observed, 6);
+        assert_eq!(seven.expected, 6.0);
+    }
+
+    #[test]
+    fn roll_stats__streaks_span_games() {
+        // given
+        let first = [Roll::Six, Roll::Six, Roll::Seven, Roll::Two];
+        let second = [Roll::Three, Roll::Three, Roll::Three, Roll::Four];
+
+        // when
+        let stats = roll_stats([first.as_slice(), second.as_slice()]);
+
+        // then
+        let expected = RollStreaks {
+            longest_without_seven: 5,
+            current_without_seven: 5,
+            longest_repeat: Some((Roll::Three, 3)),
+        };
+        assert_eq!(stats.streaks, expected);
+        assert_eq!(stats.games, 2);
+    }
+
+    #[test]
+    fn chi_square_survival__matches_table_critical_values() {
+        // given
+        let critical_values = [(18.307, 0.05), (23.209, 0.01), (9.342, 0.5)];
+
+        for (statistic, expected) in critical_values {
+            // when
+            let actual = chi_square_survival(statistic, 10);
+
+            // then
+            assert!(
+                (actual - expected).abs() < 1e-3,
+                "{statistic}: {actual} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn roll_stats__no_rolls__has_no_fit() {
+        // when
+        let stats = roll_stats(std::iter::empty());
+
+        // then
+        assert_eq!(stats.total_rolls, 0);
+        assert_eq!(stats.chi_square, None);
+        assert_eq!(stats.p_value, None);
+    }
+}
@@ -0,0 +1,309 @@
+// Fans one receipts stream out to every deployment indexed by the process.
+use crate::{
+    Result,
+    app::event_source::{
+        EventBatch,
+        EventSource,
+    },
+    events::ScopedEvent,
+};
+use anyhow::anyhow;
+use fuels::types::ContractId;
+use std::sync::{
+    Arc,
+    atomic::{
+        AtomicU64,
+        Ordering,
+    },
+};
+use tokio::sync::mpsc;
+
+// Batches a deployment may fall behind the shared stream before it holds everyone up
+const SUBSCRIBER_BUFFER: usize = 64;
+
+/// Reads scoped events from `Source` and hands each subscribed deployment the events
+/// of its own contract, along with every chain-wide event
+pub struct SharedEventSource<Source> {
+    source: Source,
+    subscribers: Vec<Subscriber>,
+    rejected_receipts: RejectedReceipts,
+}
+
+/// Running count of receipts that decoded as strapped events but were emitted by a
+/// contract no deployment is indexing
+#[derive(Debug, Clone, Default)]
+pub struct RejectedReceipts(Arc<AtomicU64>);
+
+impl RejectedReceipts {
+    pub fn count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn record(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+struct Subscriber {
+    contract_id: ContractId,
+    from_height: u32,
+    sender: mpsc::Sender<EventBatch>,
+}
+
+impl Subscriber {
+    /// The part of `batch` this deployment should see, if any
+    fn scope(&self, batch: &EventBatch<ScopedEvent>) -> Option<EventBatch> {
+        match batch {
+            EventBatch::Rollback { to_height } => Some(EventBatch::Rollback {
+                to_height: *to_height,
+            }),
+            EventBatch::Events { height, .. } if *height < self.from_height => None,
+            EventBatch::Events { events, height } => {
+                let events: Vec<_> = events
+                    .iter()
+                    .filter(|scoped| scoped.is_visible_to(&self.contract_id))
+                    .map(|scoped| scoped.event.clone())
+                    .collect();
+                (!events.is_empty()).then_some(EventBatch::Events {
+                    events,
+                    height: *height,
+                })
+            }
+        }
+    }
+}
+
+impl<Source: EventSource<ScopedEvent>> SharedEventSource<Source> {
+    pub fn new(source: Source) -> Self {
+        Self {
+            source,
+            subscribers: Vec::new(),
+            rejected_receipts: RejectedReceipts::default(),
+        }
+    }
+
+    /// Counter of events dropped for belonging to no subscribed contract, e.g. another
+    /// deployment of the same bytecode
+    pub fn rejected_receipts(&self) -> RejectedReceipts {
+        self.rejected_receipts.clone()
+    }
+
+    /// Events for `contract_id` at or above `from_height`. Rollbacks are always
+    /// forwarded.
+    pub fn subscribe(
+        &mut self,
+        contract_id: ContractId,
+        from_height: u32,
+    ) -> SubscribedEventSource {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER);
+        self.subscribers.push(Subscriber {
+            contract_id,
+            from_height,
+            sender,
+        });
+        SubscribedEventSource {
+            contract_id,
+            receiver,
+        }
+    }
+
+    /// Forward batches until every subscriber has gone away, or fail once the shared
+    /// stream does
+    pub async fn run(mut self) -> Result<()> {
+        while !self.subscribers.is_empty() {
+            if let Some(batch) = self.source.next_event_batch().await? {
+                self.dispatch(&batch).await;
+            }
+        }
+        tracing::info!("Every deployment unsubscribed, closing the shared event stream");
+        Ok(())
+    }
+
+    async fn dispatch(&mut self, batch: &EventBatch<ScopedEvent>) {
+        if let EventBatch::Events { events, .. } = batch {
+            for scoped in events.iter().filter(|scoped| {
+                !self
+                    .subscribers
+                    .iter()
+                    .any(|subscriber| scoped.is_visible_to(&subscriber.contract_id))
+            }) {
+                let rejected = self.rejected_receipts.record();
+                tracing::debug!(
+                    "Dropping event from contract {:?} ({} rejected receipts so far)",
+                    scoped.contract_id,
+                    rejected
+                );
+            }
+        }
+        let mut open = Vec::with_capacity(self.subscribers.len());
+        for subscriber in std::mem::take(&mut self.subscribers) {
+            let delivered = match subscriber.scope(batch) {
+                Some(scoped) => subscriber.sender.send(scoped).await.is_ok(),
+                None => !subscriber.sender.is_closed(),
+            };
+            if delivered {
+                open.push(subscriber);
+            } else {
+                tracing::info!(
+                    "Deployment {:#x} stopped listening to the shared event stream",
+                    subscriber.contract_id
+                );
+            }
+        }
+        self.subscribers = open;
+    }
+}
+
+/// One deployment's view of a `SharedEventSource`
+pub struct SubscribedEventSource {
+    contract_id: ContractId,
+    receiver: mpsc::Receiver<EventBatch>,
+}
+
+impl EventSource for SubscribedEventSource {
+    async fn next_event_batch(&mut self) -> Result<Option<EventBatch>> {
+        match self.receiver.recv().await {
+            Some(batch) => Ok(Some(batch)),
+            None => Err(anyhow!(
+                "shared event stream closed for deployment {:#x}",
+                self.contract_id
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+    use crate::events::Event;
+
+    struct ChannelEventSource {
+        receiver: mpsc::Receiver<EventBatch<ScopedEvent>>,
+    }
+
+    impl EventSource<ScopedEvent> for ChannelEventSource {
+        async fn next_event_batch(&mut self) -> Result<Option<EventBatch<ScopedEvent>>> {
+            self.receiver
+                .recv()
+                .await
+                .map(Some)
+                .ok_or_else(|| anyhow!("No more events"))
+        }
+    }
+
+    fn shared_source() -> (
+        SharedEventSource<ChannelEventSource>,
+        mpsc::Sender<EventBatch<ScopedEvent>>,
+    ) {
+        let (sender, receiver) = mpsc::channel(10);
+        (
+            SharedEventSource::new(ChannelEventSource { receiver }),
+            sender,
+        )
+    }
+
+    fn contract(byte: u8) -> ContractId {
+        ContractId::from([byte; 32])
+    }
+
+    #[tokio::test]
+    async fn run__forwards_own_and_chain_wide_events_from_start_height() {
+        // given
+        let (mut shared, sender) = shared_source();
+        let mut first = shared.subscribe(contract(1), 10);
+        let mut second = shared.subscribe(contract(2), 20);
+        let rejected_receipts = shared.rejected_receipts();
+        let task = tokio::spawn(shared.run());
+        let first_init = Event::init_event(contract(9), Default::default(), 10, 10);
+        let second_init = Event::init_event(contract(9), Default::default(), 20, 20);
+
+        // when
+        let batches = vec![
+            EventBatch::Events {
+                events: vec![ScopedEvent::contract(contract(1), first_init.clone())],
+                height: 10,
+            },
+            EventBatch::Events {
+                events: vec![ScopedEvent::chain(Event::block_event(15, 1))],
+                height: 15,
+            },
+            EventBatch::Events {
+                events: vec![
+                    ScopedEvent::contract(contract(2), second_init.clone()),
+                    ScopedEvent::contract(contract(3), first_init.clone()),
+                ],
+                height: 20,
+            },
+            EventBatch::Rollback { to_height: 12 },
+        ];
+        for batch in batches {
+            sender.send(batch).await.unwrap();
+        }
+
+        // then
+        let mut received_by_first = Vec::new();
+        for _ in 0..3 {
+            received_by_first.push(first.next_event_batch().await.unwrap().unwrap());
+        }
+        let expected_by_first = vec![
+            EventBatch::Events {
+                events: vec![first_init],
+                height: 10,
+            },
+            EventBatch::Events {
+                events: vec![Event::block_event(15, 1)],
+                height: 15,
+            },
+            EventBatch::Rollback { to_height: 12 },
+        ];
+        assert_eq!(received_by_first, expected_by_first);
+        let mut received_by_second = Vec::new();
+        for _ in 0..2 {
+            received_by_second.push(second.next_event_batch().await.unwrap().unwrap());
+        }
+        let expected_by_second = vec![
+            EventBatch::Events {
+                events: vec![second_init],
+                height: 20,
+            },
+            EventBatch::Rollback { to_height: 12 },
+        ];
+        assert_eq!(received_by_second, expected_by_second);
+        assert_eq!(rejected_receipts.count(), 1);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn run__once_every_subscriber_is_dropped__returns() {
+        // given
+        let (mut shared, sender) = shared_source();
+        let subscribed = shared.subscribe(contract(1), 0);
+        let task = tokio::spawn(shared.run());
+
+        // when
+        drop(subscribed);
+        sender
+            .send(EventBatch::Rollback { to_height: 0 })
+            .await
+            .unwrap();
+
+        // then
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn next_event_batch__shared_stream_failed__errors() {
+        // given
+        let (mut shared, sender) = shared_source();
+        let mut subscribed = shared.subscribe(contract(1), 0);
+        let task = tokio::spawn(shared.run());
+
+        // when
+        drop(sender);
+
+        // then
+        assert!(task.await.unwrap().is_err());
+        assert!(subscribed.next_event_batch().await.is_err());
+    }
+}
@@ -1,19 +1,29 @@
 // Sled-backed storage implementations for snapshot and metadata persistence.
 use crate::{
     app::snapshot_storage::{
+        BlockChanges,
+        HistoryOrder,
         MetadataStorage,
+        NotFound,
+        PersistentSnapshotStorage,
+        RetentionPolicy,
         SnapshotStorage,
     },
     events::Strap,
     snapshot::{
         AccountSnapshot,
+        GameWorkingState,
         HistoricalSnapshot,
+        HouseLedgerEntry,
         OverviewSnapshot,
+        PlayerGameStats,
+        StrapSupplyChange,
     },
 };
 use anyhow::{
     Context,
     anyhow,
+    bail,
 };
 use fuel_core::types::fuel_tx::AssetId;
 use fuels::types::Identity;
@@ -23,17 +33,32 @@ use serde::{
     de::DeserializeOwned,
 };
 use sled::{
+    Batch,
     Config,
     Db,
+    IVec,
+    Transactional,
     Tree,
+    transaction::ConflictableTransactionError,
 };
 use std::{
+    collections::HashMap,
     convert::TryInto,
     path::Path,
     str::FromStr,
 };
 
+mod encoding;
+mod migrations;
+
+pub use encoding::RecordEncoding;
+pub use migrations::SCHEMA_VERSION;
+
 const LATEST_HEIGHT_KEY: &[u8] = b"latest_height";
+// Lowest height whose overview survived compaction, kept next to the latest height
+const COMPACTED_BELOW_KEY: &[u8] = b"compacted_below";
+// Kept in the metadata tree, whose other keys are strap asset ids
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
 
 #[derive(Clone)]
 pub struct SledSnapshotStorage {
@@ -41,6 +66,13 @@ pub struct SledSnapshotStorage {
     overview_meta: Tree,
     account_tree: Tree,
     historical_tree: Tree,
+    historical_account_tree: Tree,
+    working_state_tree: Tree,
+    house_ledger_tree: Tree,
+    block_timestamp_tree: Tree,
+    player_stats_tree: Tree,
+    strap_supply_tree: Tree,
+    encoding: RecordEncoding,
 }
 
 #[derive(Clone)]
@@ -48,35 +80,98 @@ pub struct SledMetadataStorage {
     tree: Tree,
 }
 
+// The empty namespace keeps the tree names used before deployments were namespaced
+fn open_tree(db: &Db, namespace: &str, name: &str) -> crate::Result<Tree> {
+    let name = if namespace.is_empty() {
+        name.to_string()
+    } else {
+        format!("{namespace}/{name}")
+    };
+    db.open_tree(&name)
+        .with_context(|| format!("open {name} tree"))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SnapshotRecord<T> {
     snapshot: T,
     height: u32,
 }
 
+// Keys only carry the game id, a textual identity and the height, so keep the identity
+// itself in the value
+#[derive(Debug, Serialize, Deserialize)]
+struct PlayerStatsRecord {
+    player: Identity,
+    stats: PlayerGameStats,
+    height: u32,
+}
+
+// The game id and height are in the key
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoricalAccountRecord {
+    account: Identity,
+    snapshot: AccountSnapshot,
+}
+
+// Keys a rollback removes from each tree, gathered up front so they go in one transaction
+#[derive(Default)]
+struct RollbackPlan {
+    // Latest overview height left once the rollback is applied
+    latest_height: Option<u32>,
+    overviews: Vec<IVec>,
+    accounts: Vec<IVec>,
+    player_stats: Vec<IVec>,
+    working_state: Vec<IVec>,
+    house_ledger: Vec<IVec>,
+    strap_supply: Vec<IVec>,
+    block_timestamps: Vec<IVec>,
+    historical: Vec<IVec>,
+    historical_accounts: Vec<IVec>,
+}
+
 impl SledSnapshotStorage {
     pub fn new(db: &Db) -> crate::Result<Self> {
-        let overview_tree = db
-            .open_tree("snapshot_overview")
-            .context("open snapshot_overview tree")?;
-        let overview_meta = db
-            .open_tree("snapshot_overview_meta")
-            .context("open snapshot_overview_meta tree")?;
-        let account_tree = db
-            .open_tree("account_snapshots")
-            .context("open account_snapshots tree")?;
-        let historical_tree = db
-            .open_tree("historical_snapshots")
-            .context("open historical_snapshots tree")?;
+        Self::in_namespace(db, "")
+    }
+
+    /// Keep this storage's trees apart from other deployments indexed into the same
+    /// database, by prefixing their names with `namespace`. Trees written by an older
+    /// indexer are migrated to `SCHEMA_VERSION` first.
+    pub fn in_namespace(db: &Db, namespace: &str) -> crate::Result<Self> {
+        migrations::migrate(db, namespace)?;
+        let overview_tree = open_tree(db, namespace, "snapshot_overview")?;
+        let overview_meta = open_tree(db, namespace, "snapshot_overview_meta")?;
+        let account_tree = open_tree(db, namespace, "account_snapshots")?;
+        let historical_tree = open_tree(db, namespace, "historical_snapshots")?;
+        let historical_account_tree = open_tree(db, namespace, "historical_accounts")?;
+        let working_state_tree = open_tree(db, namespace, "game_working_state")?;
+        let house_ledger_tree = open_tree(db, namespace, "house_ledger")?;
+        let block_timestamp_tree = open_tree(db, namespace, "block_timestamps")?;
+        let player_stats_tree = open_tree(db, namespace, "player_stats")?;
+        let strap_supply_tree = open_tree(db, namespace, "strap_supply")?;
 
         Ok(Self {
             overview_tree,
             overview_meta,
             account_tree,
             historical_tree,
+            historical_account_tree,
+            working_state_tree,
+            house_ledger_tree,
+            block_timestamp_tree,
+            player_stats_tree,
+            strap_supply_tree,
+            encoding: RecordEncoding::default(),
         })
     }
 
+    /// Write new records with `encoding`. Records stored in another encoding still read,
+    /// and are only rewritten by schema migrations.
+    pub fn with_encoding(mut self, encoding: RecordEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
     pub fn open<P: AsRef<Path>>(path: P) -> crate::Result<(Self, SledMetadataStorage)> {
         let config = Config::default().path(path);
         let db = config.open().context("open sled database")?;
@@ -85,37 +180,6 @@ impl SledSnapshotStorage {
         Ok((snapshots, metadata))
     }
 
-    /// Remove all snapshots (overview and account) with a block height greater than
-    /// or equal to `from_height`.
-    pub fn prune_from(&mut self, from_height: u32) -> crate::Result<()> {
-        if from_height == 0 {
-            self.overview_tree
-                .clear()
-                .context("clear overview snapshots during prune_from(0)")?;
-            self.overview_tree
-                .flush()
-                .context("flush overview snapshots during prune_from(0)")?;
-
-            self.account_tree
-                .clear()
-                .context("clear account snapshots during prune_from(0)")?;
-            self.account_tree
-                .flush()
-                .context("flush account snapshots during prune_from(0)")?;
-
-            self.clear_latest_height()?;
-
-            // Historical snapshots are game-scoped and immutable from the perspective of
-            // rollbacks, so we leave them untouched even when starting from genesis.
-            return Ok(());
-        }
-
-        let rollback_to = from_height
-            .checked_sub(1)
-            .expect("from_height > 0 so subtraction cannot underflow");
-        self.roll_back_snapshots(rollback_to)
-    }
-
     fn latest_height(&self) -> crate::Result<Option<u32>> {
         match self.overview_meta.get(LATEST_HEIGHT_KEY)? {
             Some(bytes) => {
@@ -129,6 +193,19 @@ impl SledSnapshotStorage {
         }
     }
 
+    fn compacted_below(&self) -> crate::Result<Option<u32>> {
+        match self.overview_meta.get(COMPACTED_BELOW_KEY)? {
+            Some(bytes) => {
+                let arr: [u8; 4] = bytes
+                    .as_ref()
+                    .try_into()
+                    .context("compaction cutoff should be 4 bytes")?;
+                Ok(Some(u32::from_be_bytes(arr)))
+            }
+            None => Ok(None),
+        }
+    }
+
     fn set_latest_height(&self, height: u32) -> crate::Result<()> {
         let height_bytes = height.to_be_bytes();
         self.overview_meta
@@ -163,25 +240,69 @@ impl SledSnapshotStorage {
         &self,
         height: u32,
     ) -> crate::Result<Option<SnapshotRecord<OverviewSnapshot>>> {
-        let key = height.to_be_bytes();
-        let value = match self.overview_tree.get(key)? {
-            Some(value) => value,
-            None => return Ok(None),
-        };
-        let record = deserialize::<SnapshotRecord<OverviewSnapshot>>(value.as_ref())?;
-        Ok(Some(record))
+        read_record(&self.overview_tree, &height.to_be_bytes())
+    }
+
+    // One record per height an account changed at, so a rollback can drop the newer
+    // ones and leave the one current at its target height
+    fn account_key(account: &Identity, game_id: u32, height: u32) -> Vec<u8> {
+        let mut key = Self::account_prefix(account, game_id);
+        key.extend_from_slice(&height.to_be_bytes());
+        key
     }
 
-    fn account_key(account: &Identity, game_id: u32) -> Vec<u8> {
-        format!("{}|{}", Self::identity_key(account), game_id).into_bytes()
+    fn account_prefix(account: &Identity, game_id: u32) -> Vec<u8> {
+        format!("{}|{}|", Self::identity_key(account), game_id).into_bytes()
+    }
+
+    // Game id first, so a range of games is a single range scan. Versioned by height
+    // like accounts.
+    fn player_stats_key(player: &Identity, game_id: u32, height: u32) -> Vec<u8> {
+        let mut key = Self::player_stats_prefix(player, game_id);
+        key.extend_from_slice(&height.to_be_bytes());
+        key
+    }
+
+    fn player_stats_prefix(player: &Identity, game_id: u32) -> Vec<u8> {
+        let mut key = game_id.to_be_bytes().to_vec();
+        key.extend_from_slice(Self::identity_key(player).as_bytes());
+        key
     }
 
     fn identity_key(account: &Identity) -> String {
         format!("{:?}", account)
     }
 
-    fn serialize_record<T: Serialize>(value: &T, label: &str) -> crate::Result<Vec<u8>> {
-        serde_json::to_vec(value).with_context(|| format!("serialize {label}"))
+    // Game id, then height, so a game's updates are one prefix scan in the order they
+    // were made
+    fn historical_account_key(game_id: u32, account: &Identity, height: u32) -> Vec<u8> {
+        let mut key = game_id.to_be_bytes().to_vec();
+        key.extend_from_slice(&height.to_be_bytes());
+        key.extend_from_slice(Self::identity_key(account).as_bytes());
+        key
+    }
+
+    fn with_account_updates(
+        &self,
+        mut snapshot: HistoricalSnapshot,
+    ) -> crate::Result<HistoricalSnapshot> {
+        for entry in self
+            .historical_account_tree
+            .scan_prefix(snapshot.game_id.to_be_bytes())
+        {
+            let (_, value) = entry.context("iterate historical accounts")?;
+            let record = deserialize::<HistoricalAccountRecord>(value.as_ref())?;
+            snapshot.upsert_account(record.account, record.snapshot);
+        }
+        Ok(snapshot)
+    }
+
+    fn serialize_record<T: Serialize>(
+        &self,
+        value: &T,
+        label: &str,
+    ) -> crate::Result<Vec<u8>> {
+        serialize(value, self.encoding, label)
     }
 
     fn persist_overview(
@@ -189,7 +310,7 @@ impl SledSnapshotStorage {
         record: &SnapshotRecord<OverviewSnapshot>,
     ) -> crate::Result<()> {
         let key = record.height.to_be_bytes();
-        let bytes = Self::serialize_record(record, "overview snapshot record")?;
+        let bytes = self.serialize_record(record, "overview snapshot record")?;
         self.overview_tree
             .insert(key, bytes)
             .context("persist overview snapshot")?;
@@ -204,7 +325,7 @@ impl SledSnapshotStorage {
         key: Vec<u8>,
         record: &SnapshotRecord<AccountSnapshot>,
     ) -> crate::Result<()> {
-        let bytes = Self::serialize_record(record, "account snapshot record")?;
+        let bytes = self.serialize_record(record, "account snapshot record")?;
         self.account_tree
             .insert(key, bytes)
             .context("persist account snapshot")?;
@@ -214,19 +335,280 @@ impl SledSnapshotStorage {
         Ok(())
     }
 
-    fn remove_account_entry(&self, key: &[u8]) -> crate::Result<()> {
-        self.account_tree
-            .remove(key)
-            .context("remove account snapshot entry")?;
+    // Several entries can land in one block, so keys are height followed by the entry's
+    // position within that block.
+    fn position_keyed_records<T: Serialize>(
+        &self,
+        tree: &Tree,
+        entries: &[(T, u32)],
+        label: &str,
+    ) -> crate::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut next_positions = HashMap::new();
+        let mut records = Vec::with_capacity(entries.len());
+        for (entry, height) in entries {
+            let position = next_positions
+                .entry(*height)
+                .or_insert_with(|| tree.scan_prefix(height.to_be_bytes()).count() as u32);
+            let mut key = height.to_be_bytes().to_vec();
+            key.extend_from_slice(&position.to_be_bytes());
+            *position += 1;
+            let record = SnapshotRecord {
+                snapshot: entry,
+                height: *height,
+            };
+            records.push((key, self.serialize_record(&record, label)?));
+        }
+        Ok(records)
+    }
+
+    fn position_keyed_entries<T: DeserializeOwned>(
+        tree: &Tree,
+        label: &str,
+    ) -> crate::Result<Vec<(T, u32)>> {
+        tree.iter()
+            .map(|entry| {
+                let (_, value) = entry.with_context(|| format!("iterate {label}"))?;
+                let record = deserialize::<SnapshotRecord<T>>(value.as_ref())?;
+                Ok((record.snapshot, record.height))
+            })
+            .collect()
+    }
+
+    // Keys of records above `to_height` in a tree keyed by big-endian height first
+    fn height_keyed_above(
+        tree: &Tree,
+        to_height: u32,
+        label: &str,
+    ) -> crate::Result<Vec<IVec>> {
+        let Some(first_orphaned) = to_height.checked_add(1) else {
+            return Ok(Vec::new());
+        };
+        tree.range(first_orphaned.to_be_bytes()..)
+            .keys()
+            .map(|key| key.with_context(|| format!("iterate {label}")))
+            .collect()
+    }
+
+    /// Work out which records rolling back to `to_height` removes, without writing
+    fn plan_rollback(&self, to_height: u32) -> crate::Result<RollbackPlan> {
+        if let Some(compacted_below) = self.compacted_below()?
+            && to_height < compacted_below
+        {
+            bail!(
+                "cannot roll back to height {to_height}, overview snapshots below \
+                 {compacted_below} were compacted"
+            );
+        }
+        let mut plan = RollbackPlan::default();
+
+        for entry in self.overview_tree.iter() {
+            let (key, _) = entry.context("iterate overview snapshots")?;
+            let height = u32::from_be_bytes(
+                key.as_ref()
+                    .try_into()
+                    .context("overview snapshot key must be 4 bytes")?,
+            );
+            if height > to_height {
+                plan.overviews.push(key);
+            } else {
+                plan.latest_height = Some(height);
+            }
+        }
+
+        for entry in self.account_tree.iter() {
+            let (key, value) = entry.context("iterate account snapshots")?;
+            let record = deserialize::<SnapshotRecord<AccountSnapshot>>(value.as_ref())?;
+            if record.height > to_height {
+                plan.accounts.push(key);
+            }
+        }
+
+        for entry in self.player_stats_tree.iter() {
+            let (key, value) = entry.context("iterate player stats")?;
+            let record = deserialize::<PlayerStatsRecord>(value.as_ref())?;
+            if record.height > to_height {
+                plan.player_stats.push(key);
+            }
+        }
+
+        plan.working_state = Self::height_keyed_above(
+            &self.working_state_tree,
+            to_height,
+            "working state",
+        )?;
+        plan.house_ledger =
+            Self::height_keyed_above(&self.house_ledger_tree, to_height, "house ledger")?;
+        plan.strap_supply =
+            Self::height_keyed_above(&self.strap_supply_tree, to_height, "strap supply")?;
+        plan.block_timestamps = Self::height_keyed_above(
+            &self.block_timestamp_tree,
+            to_height,
+            "block timestamps",
+        )?;
+
+        // Height follows the game id in these keys
+        for key in self.historical_account_tree.iter().keys() {
+            let key = key.context("iterate historical accounts")?;
+            let height = key
+                .get(4..8)
+                .and_then(|height| height.try_into().ok())
+                .map(u32::from_be_bytes)
+                .context("historical account key must hold a height")?;
+            if height > to_height {
+                plan.historical_accounts.push(key);
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Add the historical snapshots of `game_id` and every later game to `plan`
+    fn plan_historical_removal(
+        &self,
+        game_id: u32,
+        plan: &mut RollbackPlan,
+    ) -> crate::Result<()> {
+        let start = game_id.to_be_bytes();
+        for key in self.historical_tree.range(start..).keys() {
+            plan.historical
+                .push(key.context("iterate historical snapshots")?);
+        }
+        for key in self.historical_account_tree.range(start..).keys() {
+            plan.historical_accounts
+                .push(key.context("iterate historical accounts")?);
+        }
+        Ok(())
+    }
+
+    fn apply_rollback(&self, plan: &RollbackPlan) -> crate::Result<()> {
+        (
+            &self.overview_tree,
+            &self.overview_meta,
+            &self.account_tree,
+            &self.historical_tree,
+            &self.historical_account_tree,
+            &self.working_state_tree,
+            &self.house_ledger_tree,
+            &self.block_timestamp_tree,
+            &self.player_stats_tree,
+            &self.strap_supply_tree,
+        )
+            .transaction(
+                |(
+                    overview_tree,
+                    overview_meta,
+                    account_tree,
+                    historical_tree,
+                    historical_account_tree,
+                    working_state_tree,
+                    house_ledger_tree,
+                    block_timestamp_tree,
+                    player_stats_tree,
+                    strap_supply_tree,
+                )| {
+                    let removals = [
+                        (overview_tree, &plan.overviews),
+                        (account_tree, &plan.accounts),
+                        (historical_tree, &plan.historical),
+                        (historical_account_tree, &plan.historical_accounts),
+                        (working_state_tree, &plan.working_state),
+                        (house_ledger_tree, &plan.house_ledger),
+                        (block_timestamp_tree, &plan.block_timestamps),
+                        (player_stats_tree, &plan.player_stats),
+                        (strap_supply_tree, &plan.strap_supply),
+                    ];
+                    for (tree, keys) in removals {
+                        for key in keys {
+                            tree.remove(key)?;
+                        }
+                    }
+                    match plan.latest_height {
+                        Some(height) => {
+                            overview_meta.insert(
+                                LATEST_HEIGHT_KEY,
+                                height.to_be_bytes().as_slice(),
+                            )?;
+                        }
+                        None => {
+                            overview_meta.remove(LATEST_HEIGHT_KEY)?;
+                        }
+                    }
+                    overview_tree.flush();
+                    Ok::<_, ConflictableTransactionError>(())
+                },
+            )
+            .context("roll back snapshots")?;
         Ok(())
     }
 }
 
+impl PersistentSnapshotStorage for SledSnapshotStorage {
+    type Metadata = SledMetadataStorage;
+
+    // One database, its trees prefixed per namespace
+    fn open_namespaces(
+        path: &Path,
+        namespaces: &[String],
+    ) -> crate::Result<Vec<(Self, SledMetadataStorage)>> {
+        let config = Config::default().path(path);
+        let db = config.open().context("open sled database")?;
+        namespaces
+            .iter()
+            .map(|namespace| {
+                let snapshots = Self::in_namespace(&db, namespace)?;
+                let metadata = SledMetadataStorage::in_namespace(&db, namespace)?;
+                Ok((snapshots, metadata))
+            })
+            .collect()
+    }
+
+    fn compact_overviews(&self, policy: RetentionPolicy) -> crate::Result<usize> {
+        let Some(latest) = self.latest_height()? else {
+            return Ok(0);
+        };
+        let cutoff = latest.saturating_sub(policy.reorg_window);
+        // Record the cutoff first, so rollbacks below it are refused before any
+        // overview they would restore is gone
+        if self
+            .compacted_below()?
+            .is_none_or(|compacted_below| compacted_below < cutoff)
+        {
+            self.overview_meta
+                .insert(COMPACTED_BELOW_KEY, cutoff.to_be_bytes().as_slice())
+                .context("write compaction cutoff")?;
+            self.overview_meta
+                .flush()
+                .context("flush compaction cutoff")?;
+        }
+
+        // Heights ascend with their big-endian keys, so each game's last entry below
+        // the cutoff is the one left in `last_per_game`
+        let mut last_per_game: HashMap<u32, IVec> = HashMap::new();
+        let mut batch = Batch::default();
+        let mut removed = 0;
+        for entry in self.overview_tree.range(..cutoff.to_be_bytes()) {
+            let (key, value) = entry.context("iterate overview snapshots")?;
+            let record = deserialize::<SnapshotRecord<OverviewSnapshot>>(value.as_ref())?;
+            if let Some(previous) = last_per_game.insert(record.snapshot.game_id, key) {
+                batch.remove(previous);
+                removed += 1;
+            }
+        }
+        self.overview_tree
+            .apply_batch(batch)
+            .context("remove compacted overview snapshots")?;
+        self.overview_tree
+            .flush()
+            .context("flush overview snapshots")?;
+        Ok(removed)
+    }
+}
+
 impl SnapshotStorage for SledSnapshotStorage {
     fn latest_snapshot(&self) -> crate::Result<(OverviewSnapshot, u32)> {
         match self.load_latest_overview()? {
             Some(record) => Ok((record.snapshot, record.height)),
-            None => Err(anyhow!("No snapshot found")),
+            None => Err(NotFound("No snapshot found".to_string()).into()),
         }
     }
 
@@ -245,13 +627,12 @@ impl SnapshotStorage for SledSnapshotStorage {
         account: &Identity,
         game_id: u32,
     ) -> crate::Result<Option<(AccountSnapshot, u32)>> {
-        let key = Self::account_key(account, game_id);
-        let value = match self.account_tree.get(key)? {
-            Some(value) => value,
-            None => return Ok(None),
-        };
-        let record = deserialize::<SnapshotRecord<AccountSnapshot>>(value.as_ref())?;
-        Ok(Some((record.snapshot, record.height)))
+        let prefix = Self::account_prefix(account, game_id);
+        let record = read_latest_record::<SnapshotRecord<AccountSnapshot>>(
+            &self.account_tree,
+            &prefix,
+        )?;
+        Ok(record.map(|record| (record.snapshot, record.height)))
     }
 
     fn update_snapshot(
@@ -279,62 +660,304 @@ impl SnapshotStorage for SledSnapshotStorage {
             snapshot: account_snapshot.clone(),
             height,
         };
-        let key = Self::account_key(account, game_id);
+        let key = Self::account_key(account, game_id, height);
         self.persist_account(key, &record)
     }
 
-    fn roll_back_snapshots(&mut self, to_height: u32) -> crate::Result<()> {
-        let mut latest_candidate = None;
+    fn player_stats_at(
+        &self,
+        player: &Identity,
+        game_id: u32,
+    ) -> crate::Result<Option<PlayerGameStats>> {
+        let prefix = Self::player_stats_prefix(player, game_id);
+        let record =
+            read_latest_record::<PlayerStatsRecord>(&self.player_stats_tree, &prefix)?;
+        Ok(record.map(|record| record.stats))
+    }
 
-        for entry in self.overview_tree.iter() {
-            let (key, _) = entry.context("iterate overview snapshots")?;
-            let height = u32::from_be_bytes(
-                key.as_ref()
-                    .try_into()
-                    .context("overview snapshot key must be 4 bytes")?,
+    fn update_player_stats(
+        &mut self,
+        player: &Identity,
+        game_id: u32,
+        stats: &PlayerGameStats,
+        height: u32,
+    ) -> crate::Result<()> {
+        let record = PlayerStatsRecord {
+            player: *player,
+            stats: stats.clone(),
+            height,
+        };
+        let bytes = self.serialize_record(&record, "player stats record")?;
+        self.player_stats_tree
+            .insert(Self::player_stats_key(player, game_id, height), bytes)
+            .context("persist player stats")?;
+        self.player_stats_tree
+            .flush()
+            .context("flush player stats")?;
+        Ok(())
+    }
+
+    fn player_stats_in_games(
+        &self,
+        from_game: u32,
+        to_game: u32,
+    ) -> crate::Result<Vec<(Identity, u32, PlayerGameStats)>> {
+        let mut stats = Vec::new();
+        for entry in self.player_stats_tree.range(from_game.to_be_bytes()..) {
+            let (key, value) = entry.context("iterate player stats")?;
+            let game_id = u32::from_be_bytes(
+                key.get(..4)
+                    .and_then(|prefix| prefix.try_into().ok())
+                    .context("player stats key must start with a game id")?,
             );
-            if height > to_height {
-                self.overview_tree
-                    .remove(&key)
-                    .context("remove overview snapshot during rollback")?;
-            } else {
-                latest_candidate = Some(height);
+            if game_id > to_game {
+                break;
+            }
+            let record = deserialize::<PlayerStatsRecord>(value.as_ref())?;
+            // A player's versions in a game are adjacent and oldest first, so each
+            // replaces the one before
+            match stats.last_mut() {
+                Some((player, last_game, last_stats))
+                    if *player == record.player && *last_game == game_id =>
+                {
+                    *last_stats = record.stats;
+                }
+                _ => stats.push((record.player, game_id, record.stats)),
             }
         }
-        self.overview_tree
+        Ok(stats)
+    }
+
+    fn latest_working_state(&self) -> crate::Result<Option<(GameWorkingState, u32)>> {
+        let Some((_, value)) = self
+            .working_state_tree
+            .last()
+            .context("read latest working state")?
+        else {
+            return Ok(None);
+        };
+        let record = deserialize::<SnapshotRecord<GameWorkingState>>(value.as_ref())?;
+        Ok(Some((record.snapshot, record.height)))
+    }
+
+    fn update_working_state(
+        &mut self,
+        state: &GameWorkingState,
+        height: u32,
+    ) -> crate::Result<()> {
+        let record = SnapshotRecord {
+            snapshot: state.clone(),
+            height,
+        };
+        let bytes = self.serialize_record(&record, "working state record")?;
+        self.working_state_tree
+            .insert(height.to_be_bytes(), bytes)
+            .context("persist working state")?;
+        self.working_state_tree
             .flush()
-            .context("flush overview snapshots")?;
+            .context("flush working state")?;
+        Ok(())
+    }
 
-        if let Some(height) = latest_candidate {
-            self.set_latest_height(height)?;
-        } else {
-            self.clear_latest_height()?;
+    fn record_house_ledger_entry(
+        &mut self,
+        entry: &HouseLedgerEntry,
+        height: u32,
+    ) -> crate::Result<()> {
+        let records = self.position_keyed_records(
+            &self.house_ledger_tree,
+            &[(entry, height)],
+            "house ledger record",
+        )?;
+        for (key, bytes) in records {
+            self.house_ledger_tree
+                .insert(key, bytes)
+                .context("persist house ledger entry")?;
         }
+        self.house_ledger_tree
+            .flush()
+            .context("flush house ledger")?;
+        Ok(())
+    }
 
-        for entry in self.account_tree.iter() {
-            let (key, value) = entry.context("iterate account snapshots")?;
-            let record = deserialize::<SnapshotRecord<AccountSnapshot>>(value.as_ref())?;
-            if record.height > to_height {
-                self.remove_account_entry(key.as_ref())?;
-            }
+    fn house_ledger(&self) -> crate::Result<Vec<(HouseLedgerEntry, u32)>> {
+        Self::position_keyed_entries(&self.house_ledger_tree, "house ledger")
+    }
+
+    fn record_strap_supply_change(
+        &mut self,
+        change: &StrapSupplyChange,
+        height: u32,
+    ) -> crate::Result<()> {
+        let records = self.position_keyed_records(
+            &self.strap_supply_tree,
+            &[(change, height)],
+            "strap supply record",
+        )?;
+        for (key, bytes) in records {
+            self.strap_supply_tree
+                .insert(key, bytes)
+                .context("persist strap supply change")?;
         }
-        self.account_tree
+        self.strap_supply_tree
             .flush()
-            .context("flush account snapshots")?;
+            .context("flush strap supply")?;
+        Ok(())
+    }
 
-        // Historical snapshots are keyed by game id and are immutable once written,
-        // so we leave them untouched during rollback.
+    fn strap_supply_changes(&self) -> crate::Result<Vec<(StrapSupplyChange, u32)>> {
+        Self::position_keyed_entries(&self.strap_supply_tree, "strap supply")
+    }
+
+    fn record_block_timestamp(
+        &mut self,
+        height: u32,
+        timestamp: u64,
+    ) -> crate::Result<()> {
+        self.block_timestamp_tree
+            .insert(height.to_be_bytes(), &timestamp.to_be_bytes())
+            .context("persist block timestamp")?;
+        self.block_timestamp_tree
+            .flush()
+            .context("flush block timestamps")?;
         Ok(())
     }
 
+    fn block_timestamp_at_or_before(
+        &self,
+        height: u32,
+    ) -> crate::Result<Option<(u32, u64)>> {
+        let Some(entry) = self
+            .block_timestamp_tree
+            .range(..=height.to_be_bytes())
+            .next_back()
+        else {
+            return Ok(None);
+        };
+        let (key, value) = entry.context("read block timestamp")?;
+        let height = u32::from_be_bytes(
+            key.as_ref()
+                .try_into()
+                .context("block timestamp key must be 4 bytes")?,
+        );
+        let timestamp = u64::from_be_bytes(
+            value
+                .as_ref()
+                .try_into()
+                .context("block timestamp must be 8 bytes")?,
+        );
+        Ok(Some((height, timestamp)))
+    }
+
+    fn roll_back_snapshots(&mut self, to_height: u32) -> crate::Result<()> {
+        // Historical snapshots are keyed by game id rather than height, so callers
+        // invalidate them with `remove_historical_snapshots_from` or `roll_back`.
+        let plan = self.plan_rollback(to_height)?;
+        self.apply_rollback(&plan)
+    }
+
+    fn roll_back(&mut self, to_height: u32) -> crate::Result<()> {
+        let mut plan = self.plan_rollback(to_height)?;
+        let current_game = match plan.latest_height {
+            Some(height) => self
+                .overview_at_height(height)?
+                .map(|record| record.snapshot.game_id)
+                .unwrap_or_default(),
+            None => 0,
+        };
+        self.plan_historical_removal(current_game, &mut plan)?;
+        self.apply_rollback(&plan)
+    }
+
+    /// Remove all snapshots (overview, account, player stats, working state, house ledger,
+    /// strap supply, block timestamps and historical account updates) with a block height
+    /// greater than or equal to `from_height`. From 0, finished games go too.
+    fn prune_from(&mut self, from_height: u32) -> crate::Result<()> {
+        if from_height == 0 {
+            self.overview_tree
+                .clear()
+                .context("clear overview snapshots during prune_from(0)")?;
+            self.overview_tree
+                .flush()
+                .context("flush overview snapshots during prune_from(0)")?;
+
+            self.account_tree
+                .clear()
+                .context("clear account snapshots during prune_from(0)")?;
+            self.account_tree
+                .flush()
+                .context("flush account snapshots during prune_from(0)")?;
+
+            self.player_stats_tree
+                .clear()
+                .context("clear player stats during prune_from(0)")?;
+            self.player_stats_tree
+                .flush()
+                .context("flush player stats during prune_from(0)")?;
+
+            self.working_state_tree
+                .clear()
+                .context("clear working state during prune_from(0)")?;
+            self.working_state_tree
+                .flush()
+                .context("flush working state during prune_from(0)")?;
+
+            self.house_ledger_tree
+                .clear()
+                .context("clear house ledger during prune_from(0)")?;
+            self.house_ledger_tree
+                .flush()
+                .context("flush house ledger during prune_from(0)")?;
+
+            self.strap_supply_tree
+                .clear()
+                .context("clear strap supply during prune_from(0)")?;
+            self.strap_supply_tree
+                .flush()
+                .context("flush strap supply during prune_from(0)")?;
+
+            self.block_timestamp_tree
+                .clear()
+                .context("clear block timestamps during prune_from(0)")?;
+            self.block_timestamp_tree
+                .flush()
+                .context("flush block timestamps during prune_from(0)")?;
+
+            self.historical_tree
+                .clear()
+                .context("clear historical snapshots during prune_from(0)")?;
+            self.historical_tree
+                .flush()
+                .context("flush historical snapshots during prune_from(0)")?;
+
+            self.historical_account_tree
+                .clear()
+                .context("clear historical accounts during prune_from(0)")?;
+            self.historical_account_tree
+                .flush()
+                .context("flush historical accounts during prune_from(0)")?;
+
+            self.clear_latest_height()?;
+            self.overview_meta
+                .remove(COMPACTED_BELOW_KEY)
+                .context("remove compaction cutoff during prune_from(0)")?;
+
+            return Ok(());
+        }
+
+        let rollback_to = from_height
+            .checked_sub(1)
+            .expect("from_height > 0 so subtraction cannot underflow");
+        self.roll_back(rollback_to)
+    }
+
     fn historical_snapshots(&self, game_id: u32) -> crate::Result<HistoricalSnapshot> {
         let key = game_id.to_be_bytes();
-        let value = self
-            .historical_tree
-            .get(key)?
-            .ok_or_else(|| anyhow!("No historical snapshot found for game {game_id}"))?;
-        let snapshot = deserialize::<HistoricalSnapshot>(value.as_ref())?;
-        Ok(snapshot)
+        let snapshot = read_record(&self.historical_tree, &key)?;
+        let snapshot = snapshot.ok_or_else(|| {
+            NotFound(format!("No historical snapshot found for game {game_id}"))
+        })?;
+        self.with_account_updates(snapshot)
     }
 
     fn write_historical_snapshot(
@@ -343,7 +966,7 @@ impl SnapshotStorage for SledSnapshotStorage {
         snapshot: &HistoricalSnapshot,
     ) -> crate::Result<()> {
         let key = game_id.to_be_bytes();
-        let bytes = Self::serialize_record(snapshot, "historical snapshot record")?;
+        let bytes = self.serialize_record(snapshot, "historical snapshot record")?;
         self.historical_tree
             .insert(key, bytes)
             .context("persist historical snapshot")?;
@@ -352,11 +975,227 @@ impl SnapshotStorage for SledSnapshotStorage {
             .context("flush historical snapshots")?;
         Ok(())
     }
+
+    fn update_historical_account(
+        &mut self,
+        game_id: u32,
+        account: &Identity,
+        account_snapshot: &AccountSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        let record = HistoricalAccountRecord {
+            account: *account,
+            snapshot: account_snapshot.clone(),
+        };
+        let bytes = self.serialize_record(&record, "historical account record")?;
+        self.historical_account_tree
+            .insert(
+                Self::historical_account_key(game_id, account, height),
+                bytes,
+            )
+            .context("persist historical account")?;
+        self.historical_account_tree
+            .flush()
+            .context("flush historical accounts")?;
+        Ok(())
+    }
+
+    fn historical_snapshot_range(
+        &self,
+        from: Option<u32>,
+        limit: usize,
+        order: HistoryOrder,
+    ) -> crate::Result<Vec<HistoricalSnapshot>> {
+        // Keys are big endian game ids, so sled's byte order is game id order
+        let entries: Box<dyn Iterator<Item = sled::Result<(IVec, IVec)>>> =
+            match (order, from) {
+                (HistoryOrder::Ascending, None) => Box::new(self.historical_tree.iter()),
+                (HistoryOrder::Ascending, Some(from)) => {
+                    Box::new(self.historical_tree.range(from.to_be_bytes()..))
+                }
+                (HistoryOrder::Descending, None) => {
+                    Box::new(self.historical_tree.iter().rev())
+                }
+                (HistoryOrder::Descending, Some(from)) => {
+                    Box::new(self.historical_tree.range(..=from.to_be_bytes()).rev())
+                }
+            };
+        entries
+            .take(limit)
+            .map(|entry| {
+                let (_, value) = entry.context("iterate historical snapshots")?;
+                self.with_account_updates(deserialize(value.as_ref())?)
+            })
+            .collect()
+    }
+
+    fn remove_historical_snapshots_from(&mut self, game_id: u32) -> crate::Result<()> {
+        let start = game_id.to_be_bytes();
+        for entry in self.historical_tree.range(start..) {
+            let (key, _) = entry.context("iterate historical snapshots")?;
+            self.historical_tree
+                .remove(&key)
+                .context("remove historical snapshot")?;
+        }
+        self.historical_tree
+            .flush()
+            .context("flush historical snapshots")?;
+        for entry in self.historical_account_tree.range(start..) {
+            let (key, _) = entry.context("iterate historical accounts")?;
+            self.historical_account_tree
+                .remove(&key)
+                .context("remove historical account")?;
+        }
+        self.historical_account_tree
+            .flush()
+            .context("flush historical accounts")?;
+        Ok(())
+    }
+
+    fn commit_block(&mut self, changes: &BlockChanges) -> crate::Result<()> {
+        // Serialize up front so the transaction body can only fail on sled itself
+        let overview = match &changes.overview {
+            Some((snapshot, height)) => {
+                let record = SnapshotRecord {
+                    snapshot: snapshot.clone(),
+                    height: *height,
+                };
+                let bytes = self.serialize_record(&record, "overview snapshot record")?;
+                Some((height.to_be_bytes(), bytes))
+            }
+            None => None,
+        };
+        let mut accounts = Vec::with_capacity(changes.accounts.len());
+        for (account, game_id, snapshot, height) in &changes.accounts {
+            let record = SnapshotRecord {
+                snapshot: snapshot.clone(),
+                height: *height,
+            };
+            let bytes = self.serialize_record(&record, "account snapshot record")?;
+            accounts.push((Self::account_key(account, *game_id, *height), bytes));
+        }
+        let mut player_stats = Vec::with_capacity(changes.player_stats.len());
+        for (player, game_id, stats, height) in &changes.player_stats {
+            let record = PlayerStatsRecord {
+                player: *player,
+                stats: stats.clone(),
+                height: *height,
+            };
+            let bytes = self.serialize_record(&record, "player stats record")?;
+            player_stats.push((Self::player_stats_key(player, *game_id, *height), bytes));
+        }
+        let working_state = match &changes.working_state {
+            Some((state, height)) => {
+                let record = SnapshotRecord {
+                    snapshot: state.clone(),
+                    height: *height,
+                };
+                let bytes = self.serialize_record(&record, "working state record")?;
+                Some((height.to_be_bytes(), bytes))
+            }
+            None => None,
+        };
+        let house_ledger = self.position_keyed_records(
+            &self.house_ledger_tree,
+            &changes.house_ledger,
+            "house ledger record",
+        )?;
+        let strap_supply = self.position_keyed_records(
+            &self.strap_supply_tree,
+            &changes.strap_supply,
+            "strap supply record",
+        )?;
+        let mut historical = Vec::with_capacity(changes.historical.len());
+        for (game_id, snapshot) in &changes.historical {
+            let bytes = self.serialize_record(snapshot, "historical snapshot record")?;
+            historical.push((game_id.to_be_bytes(), bytes));
+        }
+        let mut historical_accounts =
+            Vec::with_capacity(changes.historical_accounts.len());
+        for (game_id, account, snapshot, height) in &changes.historical_accounts {
+            let record = HistoricalAccountRecord {
+                account: *account,
+                snapshot: snapshot.clone(),
+            };
+            let bytes = self.serialize_record(&record, "historical account record")?;
+            let key = Self::historical_account_key(*game_id, account, *height);
+            historical_accounts.push((key, bytes));
+        }
+
+        (
+            &self.overview_tree,
+            &self.overview_meta,
+            &self.account_tree,
+            &self.historical_tree,
+            &self.historical_account_tree,
+            &self.working_state_tree,
+            &self.house_ledger_tree,
+            &self.block_timestamp_tree,
+            &self.player_stats_tree,
+            &self.strap_supply_tree,
+        )
+            .transaction(
+                |(
+                    overview_tree,
+                    overview_meta,
+                    account_tree,
+                    historical_tree,
+                    historical_account_tree,
+                    working_state_tree,
+                    house_ledger_tree,
+                    block_timestamp_tree,
+                    player_stats_tree,
+                    strap_supply_tree,
+                )| {
+                    for (key, bytes) in &historical {
+                        historical_tree.insert(key.as_slice(), bytes.as_slice())?;
+                    }
+                    for (key, bytes) in &historical_accounts {
+                        historical_account_tree
+                            .insert(key.as_slice(), bytes.as_slice())?;
+                    }
+                    for (key, bytes) in &accounts {
+                        account_tree.insert(key.as_slice(), bytes.as_slice())?;
+                    }
+                    for (key, bytes) in &player_stats {
+                        player_stats_tree.insert(key.as_slice(), bytes.as_slice())?;
+                    }
+                    if let Some((key, bytes)) = &working_state {
+                        working_state_tree.insert(key.as_slice(), bytes.as_slice())?;
+                    }
+                    for (key, bytes) in &house_ledger {
+                        house_ledger_tree.insert(key.as_slice(), bytes.as_slice())?;
+                    }
+                    for (key, bytes) in &strap_supply {
+                        strap_supply_tree.insert(key.as_slice(), bytes.as_slice())?;
+                    }
+                    for (height, timestamp) in &changes.block_timestamps {
+                        block_timestamp_tree.insert(
+                            height.to_be_bytes().as_slice(),
+                            timestamp.to_be_bytes().as_slice(),
+                        )?;
+                    }
+                    if let Some((key, bytes)) = &overview {
+                        overview_tree.insert(key.as_slice(), bytes.as_slice())?;
+                        overview_meta.insert(LATEST_HEIGHT_KEY, key.as_slice())?;
+                    }
+                    // One fsync for the whole block
+                    overview_tree.flush();
+                    Ok::<_, ConflictableTransactionError>(())
+                },
+            )
+            .context("commit block changes")?;
+        Ok(())
+    }
 }
 
 impl SledMetadataStorage {
     pub fn new(db: &Db) -> crate::Result<Self> {
-        let tree = db.open_tree("metadata").context("open metadata tree")?;
+        Self::in_namespace(db, "")
+    }
+
+    pub fn in_namespace(db: &Db, namespace: &str) -> crate::Result<Self> {
+        let tree = open_tree(db, namespace, "metadata")?;
         Ok(Self { tree })
     }
 
@@ -369,12 +1208,7 @@ impl SledMetadataStorage {
 impl MetadataStorage for SledMetadataStorage {
     fn strap_asset_id(&self, strap_id: &AssetId) -> crate::Result<Option<Strap>> {
         let key = Self::strap_key(strap_id)?;
-        let value = match self.tree.get(key)? {
-            Some(value) => value,
-            None => return Ok(None),
-        };
-        let strap = deserialize::<Strap>(value.as_ref())?;
-        Ok(Some(strap))
+        read_record(&self.tree, &key)
     }
 
     fn record_new_asset_id(
@@ -383,7 +1217,7 @@ impl MetadataStorage for SledMetadataStorage {
         strap: &Strap,
     ) -> crate::Result<()> {
         let key = Self::strap_key(strap_id)?;
-        let bytes = SledSnapshotStorage::serialize_record(strap, "strap metadata")?;
+        let bytes = serialize(strap, RecordEncoding::default(), "strap metadata")?;
         self.tree
             .insert(key, bytes)
             .context("persist strap metadata")?;
@@ -395,6 +1229,9 @@ impl MetadataStorage for SledMetadataStorage {
         let mut asset_ids = Vec::new();
         for entry in self.tree.iter() {
             let (key, _) = entry.context("iterate strap metadata entries")?;
+            if key.as_ref() == SCHEMA_VERSION_KEY {
+                continue;
+            }
             let key_str = std::str::from_utf8(key.as_ref())
                 .context("metadata key is not valid UTF-8")?;
             let asset_id = AssetId::from_str(key_str)
@@ -408,6 +1245,9 @@ impl MetadataStorage for SledMetadataStorage {
         let mut straps = Vec::new();
         for entry in self.tree.iter() {
             let (key, value) = entry.context("iterate strap metadata entries")?;
+            if key.as_ref() == SCHEMA_VERSION_KEY {
+                continue;
+            }
             let key_str = std::str::from_utf8(key.as_ref())
                 .context("metadata key is not valid UTF-8")?;
             let asset_id = AssetId::from_str(key_str)
@@ -420,30 +1260,71 @@ impl MetadataStorage for SledMetadataStorage {
     }
 }
 
+fn serialize<T: Serialize>(
+    value: &T,
+    encoding: RecordEncoding,
+    label: &str,
+) -> crate::Result<Vec<u8>> {
+    encoding::encode(value, encoding).with_context(|| format!("serialize {label}"))
+}
+
 fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> crate::Result<T> {
-    serde_json::from_slice(bytes).context("deserialize sled record")
+    encoding::decode(bytes).context("deserialize sled record")
+}
+
+fn read_record<T: DeserializeOwned>(tree: &Tree, key: &[u8]) -> crate::Result<Option<T>> {
+    tree.get(key)?
+        .map(|value| deserialize(value.as_ref()))
+        .transpose()
+}
+
+// The last record under `prefix`, i.e. the latest one where keys end in a big-endian
+// height
+fn read_latest_record<T: DeserializeOwned>(
+    tree: &Tree,
+    prefix: &[u8],
+) -> crate::Result<Option<T>> {
+    tree.scan_prefix(prefix)
+        .next_back()
+        .transpose()?
+        .map(|(_, value)| deserialize(value.as_ref()))
+        .transpose()
 }
 
 #[cfg(test)]
 mod tests {
     #![allow(non_snake_case)]
     use super::{
+        RecordEncoding,
         SledMetadataStorage,
         SledSnapshotStorage,
+        SnapshotRecord,
     };
     use crate::{
         app::snapshot_storage::{
+            BlockChanges,
+            HistoryOrder,
             MetadataStorage,
+            PersistentSnapshotStorage,
+            RetentionPolicy,
             SnapshotStorage,
         },
         events::{
             Modifier,
+            Roll,
             Strap,
             StrapKind,
         },
         snapshot::{
             AccountSnapshot,
+            ActiveModifier,
+            GameWorkingState,
+            HistoricalSnapshot,
+            HouseLedgerEntry,
             OverviewSnapshot,
+            PlayerGameStats,
+            StrapSupplyChange,
+            StrapSupplyChangeKind,
         },
     };
     use fuel_core::types::fuel_tx::AssetId;
@@ -460,6 +1341,99 @@ mod tests {
             .expect("open sled db")
     }
 
+    mod conformance {
+        use super::*;
+
+        // Removed from disk once the last handle to it is dropped
+        fn temporary_storage() -> (SledSnapshotStorage, SledMetadataStorage) {
+            let db = sled::Config::default()
+                .temporary(true)
+                .open()
+                .expect("open sled db");
+            (
+                SledSnapshotStorage::new(&db).unwrap(),
+                SledMetadataStorage::new(&db).unwrap(),
+            )
+        }
+
+        crate::snapshot_storage_conformance_tests!(persistent temporary_storage);
+
+        mod json {
+            use super::*;
+
+            fn temporary_json_storage() -> (SledSnapshotStorage, SledMetadataStorage) {
+                let (snapshots, metadata) = temporary_storage();
+                (snapshots.with_encoding(RecordEncoding::Json), metadata)
+            }
+
+            crate::snapshot_storage_conformance_tests!(persistent temporary_json_storage);
+        }
+    }
+
+    #[test]
+    fn latest_snapshot__untagged_json_record__is_read_without_being_rewritten() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage").unwrap();
+        let db = sled_db(&temp_dir);
+        let storage = SledSnapshotStorage::new(&db).unwrap();
+        let snapshot = OverviewSnapshot {
+            game_id: 3,
+            ..Default::default()
+        };
+        let legacy = serde_json::to_vec(&SnapshotRecord {
+            snapshot: snapshot.clone(),
+            height: 7,
+        })
+        .unwrap();
+        storage
+            .overview_tree
+            .insert(7u32.to_be_bytes(), legacy.clone())
+            .unwrap();
+        storage.set_latest_height(7).unwrap();
+
+        // when
+        let latest = storage.latest_snapshot().unwrap();
+
+        // then
+        assert_eq!(latest, (snapshot, 7));
+        let stored = storage
+            .overview_tree
+            .get(7u32.to_be_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.as_ref(), legacy.as_slice());
+    }
+
+    #[test]
+    fn with_encoding__reopened_with_another_encoding__reads_records_as_written() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage").unwrap();
+        let db = sled_db(&temp_dir);
+        let account = Identity::Address(Address::from([5u8; 32]));
+        let account_snapshot = AccountSnapshot {
+            total_chip_bet: 30,
+            ..Default::default()
+        };
+        let mut json_storage = SledSnapshotStorage::new(&db)
+            .unwrap()
+            .with_encoding(RecordEncoding::Json);
+        json_storage
+            .update_account_snapshot(&account, 1, &account_snapshot, 9)
+            .unwrap();
+        let key = SledSnapshotStorage::account_key(&account, 1, 9);
+        let written = json_storage.account_tree.get(&key).unwrap().unwrap();
+        assert!(RecordEncoding::Json.is_encoding_of(&written));
+
+        // when
+        let storage = SledSnapshotStorage::new(&db).unwrap();
+        let read = storage.account_snapshot_at(&account, 1).unwrap();
+
+        // then
+        assert_eq!(read, Some((account_snapshot, 9)));
+        let stored = storage.account_tree.get(&key).unwrap().unwrap();
+        assert_eq!(stored, written);
+    }
+
     #[test]
     fn sut__when_updating_snapshots_then_latest_state_is_persisted() {
         // given
@@ -533,6 +1507,138 @@ mod tests {
         assert_eq!(account_snapshot.total_chip_won, 5);
     }
 
+    #[test]
+    fn sut__when_rolling_back_then_working_state_is_restored_to_height() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_working_state").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        let state_one = GameWorkingState {
+            historical_modifiers: vec![ActiveModifier::new(
+                1,
+                Modifier::Holy,
+                Roll::Four,
+            )],
+            modifier_triggered: vec![Modifier::Holy],
+            modifier_purchased: vec![],
+        };
+        let mut state_two = state_one.clone();
+        state_two.modifier_purchased.push(Modifier::Lucky);
+        storage.update_working_state(&state_one, 10).unwrap();
+        storage.update_working_state(&state_two, 20).unwrap();
+        assert_eq!(
+            storage.latest_working_state().unwrap(),
+            Some((state_two, 20))
+        );
+
+        // when
+        storage.roll_back_snapshots(15).unwrap();
+
+        // then
+        let reopened = SledSnapshotStorage::new(&db).unwrap();
+        assert_eq!(
+            reopened.latest_working_state().unwrap(),
+            Some((state_one, 10))
+        );
+    }
+
+    #[test]
+    fn sut__when_rolling_back_then_later_house_ledger_entries_are_removed() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_house_ledger").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        let owner = Identity::Address(Address::from([2u8; 32]));
+        let fund = HouseLedgerEntry::Fund {
+            amount: 1_000,
+            funder: owner,
+        };
+        let withdrawal = HouseLedgerEntry::Withdrawal {
+            amount: 400,
+            to: owner,
+        };
+        let failed = HouseLedgerEntry::FailedWithdrawal {
+            requested_amount: 900,
+            available_amount: 600,
+            to: owner,
+        };
+        storage.record_house_ledger_entry(&fund, 10).unwrap();
+        storage.record_house_ledger_entry(&withdrawal, 10).unwrap();
+        storage.record_house_ledger_entry(&failed, 20).unwrap();
+        assert_eq!(
+            storage.house_ledger().unwrap(),
+            vec![(fund.clone(), 10), (withdrawal.clone(), 10), (failed, 20)]
+        );
+
+        // when
+        storage.roll_back_snapshots(15).unwrap();
+
+        // then
+        assert_eq!(
+            storage.house_ledger().unwrap(),
+            vec![(fund, 10), (withdrawal, 10)]
+        );
+    }
+
+    #[test]
+    fn sut__when_rolling_back_then_later_strap_supply_changes_are_removed() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_strap_supply").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        let change = |level, kind| StrapSupplyChange {
+            strap: Strap::new(level, StrapKind::Coat, Modifier::Nothing),
+            kind,
+            amount: 1,
+        };
+        let escrowed = change(1, StrapSupplyChangeKind::Escrowed);
+        let minted = change(2, StrapSupplyChangeKind::Minted);
+        let consumed = change(1, StrapSupplyChangeKind::UpgradeConsumed);
+        storage.record_strap_supply_change(&escrowed, 10).unwrap();
+        storage.record_strap_supply_change(&minted, 20).unwrap();
+        storage.record_strap_supply_change(&consumed, 20).unwrap();
+        assert_eq!(
+            storage.strap_supply_changes().unwrap(),
+            vec![(escrowed.clone(), 10), (minted, 20), (consumed, 20)]
+        );
+
+        // when
+        storage.roll_back_snapshots(15).unwrap();
+
+        // then
+        assert_eq!(
+            storage.strap_supply_changes().unwrap(),
+            vec![(escrowed, 10)]
+        );
+    }
+
+    #[test]
+    fn sut__when_looking_up_block_timestamp_then_closest_earlier_block_is_returned() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_block_timestamps").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        storage.record_block_timestamp(10, 1_000).unwrap();
+        storage.record_block_timestamp(20, 1_010).unwrap();
+
+        // when
+        let before_first = storage.block_timestamp_at_or_before(9).unwrap();
+        let exact = storage.block_timestamp_at_or_before(20).unwrap();
+        let between = storage.block_timestamp_at_or_before(15).unwrap();
+        storage.roll_back_snapshots(15).unwrap();
+        let after_rollback = storage.block_timestamp_at_or_before(20).unwrap();
+
+        // then
+        assert_eq!(before_first, None);
+        assert_eq!(exact, Some((20, 1_010)));
+        assert_eq!(between, Some((10, 1_000)));
+        assert_eq!(after_rollback, Some((10, 1_000)));
+    }
+
     #[test]
     fn sut__when_pruning_from_height_then_entries_at_or_above_are_removed() {
         // given
@@ -584,6 +1690,186 @@ mod tests {
         assert!(latest_account.is_none());
     }
 
+    #[test]
+    fn sut__when_removing_historical_snapshots_from_game_then_later_games_are_removed() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_historical").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        for game_id in [1, 2, 3, 300] {
+            let snapshot = HistoricalSnapshot::new(game_id, Vec::new(), Vec::new());
+            storage
+                .write_historical_snapshot(game_id, &snapshot)
+                .unwrap();
+        }
+
+        // when
+        storage.remove_historical_snapshots_from(2).unwrap();
+
+        // then
+        assert_eq!(storage.historical_snapshots(1).unwrap().game_id, 1);
+        assert!(storage.historical_snapshots(2).is_err());
+        assert!(storage.historical_snapshots(3).is_err());
+        assert!(storage.historical_snapshots(300).is_err());
+    }
+
+    #[test]
+    fn sut__when_listing_historical_snapshot_range_then_games_are_in_requested_order() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_history_range").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        for game_id in [1, 2, 3, 255, 256, 300] {
+            let snapshot = HistoricalSnapshot::new(game_id, Vec::new(), Vec::new());
+            storage
+                .write_historical_snapshot(game_id, &snapshot)
+                .unwrap();
+        }
+        let game_ids = |snapshots: Vec<HistoricalSnapshot>| {
+            snapshots
+                .into_iter()
+                .map(|snapshot| snapshot.game_id)
+                .collect::<Vec<_>>()
+        };
+
+        // when
+        let ascending = storage
+            .historical_snapshot_range(Some(3), 3, HistoryOrder::Ascending)
+            .unwrap();
+        let descending = storage
+            .historical_snapshot_range(Some(255), 2, HistoryOrder::Descending)
+            .unwrap();
+        let newest = storage
+            .historical_snapshot_range(None, 2, HistoryOrder::Descending)
+            .unwrap();
+
+        // then
+        assert_eq!(game_ids(ascending), vec![3, 255, 256]);
+        assert_eq!(game_ids(descending), vec![255, 3]);
+        assert_eq!(game_ids(newest), vec![300, 256]);
+    }
+
+    #[test]
+    fn sut__when_committing_block_then_every_change_is_persisted() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_commit_block").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        let owner = Identity::Address(Address::from([2u8; 32]));
+        let earlier_fund = HouseLedgerEntry::Fund {
+            amount: 100,
+            funder: owner,
+        };
+        storage
+            .record_house_ledger_entry(&earlier_fund, 30)
+            .unwrap();
+
+        let snapshot = OverviewSnapshot {
+            game_id: 4,
+            pot_size: 700,
+            ..OverviewSnapshot::default()
+        };
+        let account_snapshot = AccountSnapshot {
+            total_chip_bet: 25,
+            ..AccountSnapshot::default()
+        };
+        let state = GameWorkingState {
+            modifier_triggered: vec![Modifier::Holy],
+            ..GameWorkingState::default()
+        };
+        let fund = HouseLedgerEntry::Fund {
+            amount: 1_000,
+            funder: owner,
+        };
+        let withdrawal = HouseLedgerEntry::Withdrawal {
+            amount: 400,
+            to: owner,
+        };
+        let historical = HistoricalSnapshot::new(3, vec![Roll::Six], Vec::new());
+        let stats = PlayerGameStats {
+            chips_wagered: 25,
+            ..PlayerGameStats::default()
+        };
+        let escrowed = StrapSupplyChange {
+            strap: Strap::new(1, StrapKind::Hat, Modifier::Nothing),
+            kind: StrapSupplyChangeKind::Escrowed,
+            amount: 2,
+        };
+        let changes = BlockChanges {
+            overview: Some((snapshot.clone(), 30)),
+            accounts: vec![(owner, 4, account_snapshot.clone(), 30)],
+            player_stats: vec![(owner, 4, stats.clone(), 30)],
+            working_state: Some((state.clone(), 30)),
+            house_ledger: vec![(fund.clone(), 30), (withdrawal.clone(), 30)],
+            strap_supply: vec![(escrowed.clone(), 30)],
+            block_timestamps: [(30, 1_700_000_030)].into(),
+            historical: [(3, historical.clone())].into(),
+            historical_accounts: Vec::new(),
+        };
+
+        // when
+        storage.commit_block(&changes).unwrap();
+
+        // then
+        assert_eq!(storage.latest_snapshot().unwrap(), (snapshot, 30));
+        assert_eq!(
+            storage.latest_account_snapshot(&owner).unwrap(),
+            Some((account_snapshot, 30))
+        );
+        assert_eq!(storage.latest_working_state().unwrap(), Some((state, 30)));
+        assert_eq!(
+            storage.house_ledger().unwrap(),
+            vec![(earlier_fund, 30), (fund, 30), (withdrawal, 30)]
+        );
+        assert_eq!(
+            storage.block_timestamp_at_or_before(30).unwrap(),
+            Some((30, 1_700_000_030))
+        );
+        assert_eq!(storage.historical_snapshots(3).unwrap(), historical);
+        assert_eq!(storage.player_stats_at(&owner, 4).unwrap(), Some(stats));
+        assert_eq!(
+            storage.strap_supply_changes().unwrap(),
+            vec![(escrowed, 30)]
+        );
+    }
+
+    #[test]
+    fn sut__when_listing_player_stats_in_games_then_only_the_range_is_returned() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage_player_stats").unwrap();
+        let db = sled_db(&temp_dir);
+
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        let player = Identity::Address(Address::from([5u8; 32]));
+        let stats = |chips_wagered| PlayerGameStats {
+            chips_wagered,
+            ..PlayerGameStats::default()
+        };
+        for (game_id, height) in [(1, 10), (2, 20), (256, 30), (300, 40)] {
+            storage
+                .update_player_stats(&player, game_id, &stats(game_id.into()), height)
+                .unwrap();
+        }
+
+        // when
+        let in_range = storage.player_stats_in_games(2, 256).unwrap();
+        storage.roll_back_snapshots(25).unwrap();
+        let after_rollback = storage.player_stats_in_games(0, u32::MAX).unwrap();
+
+        // then
+        assert_eq!(
+            in_range,
+            vec![(player, 2, stats(2)), (player, 256, stats(256))]
+        );
+        assert_eq!(
+            after_rollback,
+            vec![(player, 1, stats(1)), (player, 2, stats(2))]
+        );
+    }
+
     #[test]
     fn sut__when_recording_metadata_then_lookup_returns_value() {
         // given
@@ -648,4 +1934,106 @@ mod tests {
         // then
         assert_eq!(known, vec![(asset_id_a, strap_a), (asset_id_b, strap_b)]);
     }
+
+    #[test]
+    fn in_namespace__keeps_deployments_in_one_db_apart() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage").unwrap();
+        let db = sled_db(&temp_dir);
+        let mut first = SledSnapshotStorage::in_namespace(&db, "first").unwrap();
+        let second = SledSnapshotStorage::in_namespace(&db, "second").unwrap();
+        let unnamespaced = SledSnapshotStorage::new(&db).unwrap();
+        let mut first_metadata = SledMetadataStorage::in_namespace(&db, "first").unwrap();
+        let second_metadata = SledMetadataStorage::in_namespace(&db, "second").unwrap();
+        let snapshot = OverviewSnapshot {
+            game_id: 7,
+            ..OverviewSnapshot::default()
+        };
+        let strap = Strap::new(1, StrapKind::Hat, Modifier::Nothing);
+
+        // when
+        first.update_snapshot(&snapshot, 10).unwrap();
+        first_metadata
+            .record_new_asset_id(&AssetId::from([1u8; 32]), &strap)
+            .unwrap();
+
+        // then
+        assert_eq!(first.latest_snapshot().unwrap(), (snapshot, 10));
+        assert!(second.latest_snapshot().is_err());
+        assert!(unnamespaced.latest_snapshot().is_err());
+        assert!(
+            second_metadata
+                .all_known_strap_asset_ids()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn compact_overviews__keeps_reorg_window_and_last_overview_of_each_game() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage").unwrap();
+        let db = sled_db(&temp_dir);
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        for height in 1..=20 {
+            let snapshot = OverviewSnapshot {
+                game_id: match height {
+                    1..=5 => 1,
+                    6..=12 => 2,
+                    _ => 3,
+                },
+                ..OverviewSnapshot::default()
+            };
+            storage.update_snapshot(&snapshot, height).unwrap();
+        }
+        let policy = RetentionPolicy { reorg_window: 5 };
+
+        // when
+        let removed = storage.compact_overviews(policy).unwrap();
+        let removed_again = storage.compact_overviews(policy).unwrap();
+
+        // then
+        // Heights 5, 12 and 14 end games 1 and 2 and the part of game 3 before the window
+        assert_eq!(removed, 20 - 9);
+        assert_eq!(removed_again, 0);
+        let (latest, height) = storage.latest_snapshot().unwrap();
+        assert_eq!((latest.game_id, height), (3, 20));
+        storage.roll_back_snapshots(17).unwrap();
+        let (latest, height) = storage.latest_snapshot().unwrap();
+        assert_eq!((latest.game_id, height), (3, 17));
+        storage.roll_back_snapshots(15).unwrap();
+        let (latest, height) = storage.latest_snapshot().unwrap();
+        assert_eq!((latest.game_id, height), (3, 15));
+    }
+
+    #[test]
+    fn roll_back_snapshots__below_compacted_cutoff__errors_and_keeps_history() {
+        // given
+        let temp_dir = TempDir::new("sled_snapshot_storage").unwrap();
+        let db = sled_db(&temp_dir);
+        let mut storage = SledSnapshotStorage::new(&db).unwrap();
+        for height in 1..=20 {
+            let snapshot = OverviewSnapshot {
+                game_id: if height <= 12 { 2 } else { 3 },
+                ..OverviewSnapshot::default()
+            };
+            storage.update_snapshot(&snapshot, height).unwrap();
+        }
+        let historical = HistoricalSnapshot::new(2, Vec::new(), Vec::new());
+        storage.write_historical_snapshot(2, &historical).unwrap();
+        storage
+            .compact_overviews(RetentionPolicy { reorg_window: 5 })
+            .unwrap();
+
+        // when
+        let result = SledSnapshotStorage::new(&db).unwrap().roll_back(13);
+
+        // then
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("below 15 were compacted"), "{err}");
+        assert_eq!(storage.latest_snapshot().unwrap().1, 20);
+        assert_eq!(storage.historical_snapshots(2).unwrap(), historical);
+        storage.prune_from(0).unwrap();
+        storage.roll_back_snapshots(0).unwrap();
+    }
 }
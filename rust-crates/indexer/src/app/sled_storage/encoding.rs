@@ -0,0 +1,152 @@
+// Values carry a leading format tag. Records written before the tag existed are plain
+// JSON, which never starts with one of these bytes, so they still decode untagged.
+//
+// Both formats keep field names, so a field added under `#[serde(default)]` still
+// decodes from older records.
+use anyhow::{
+    Context,
+    bail,
+};
+use serde::{
+    Serialize,
+    de::DeserializeOwned,
+};
+
+const JSON_TAG: u8 = 0;
+const MESSAGEPACK_TAG: u8 = 1;
+
+/// How sled values are encoded when written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordEncoding {
+    Json,
+    /// MessagePack with structs as maps keyed by field name
+    #[default]
+    MessagePack,
+}
+
+impl RecordEncoding {
+    fn tag(self) -> u8 {
+        match self {
+            RecordEncoding::Json => JSON_TAG,
+            RecordEncoding::MessagePack => MESSAGEPACK_TAG,
+        }
+    }
+
+    /// Whether `bytes` were written with this encoding; untagged legacy JSON never is.
+    pub(super) fn is_encoding_of(self, bytes: &[u8]) -> bool {
+        bytes.first() == Some(&self.tag())
+    }
+}
+
+pub(super) fn encode<T: Serialize>(
+    value: &T,
+    encoding: RecordEncoding,
+) -> crate::Result<Vec<u8>> {
+    let mut tagged = vec![encoding.tag()];
+    match encoding {
+        RecordEncoding::Json => {
+            serde_json::to_writer(&mut tagged, value).context("encode json record")?
+        }
+        RecordEncoding::MessagePack => rmp_serde::encode::write_named(&mut tagged, value)
+            .context("encode messagepack record")?,
+    }
+    Ok(tagged)
+}
+
+pub(super) fn decode<T: DeserializeOwned>(bytes: &[u8]) -> crate::Result<T> {
+    match bytes.split_first() {
+        Some((&JSON_TAG, payload)) => {
+            serde_json::from_slice(payload).context("decode json record")
+        }
+        Some((&MESSAGEPACK_TAG, payload)) => {
+            rmp_serde::from_slice(payload).context("decode messagepack record")
+        }
+        Some((tag, _)) if tag.is_ascii_control() && !tag.is_ascii_whitespace() => {
+            bail!("unknown record format tag {tag}")
+        }
+        _ => serde_json::from_slice(bytes).context("decode untagged json record"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use crate::snapshot::OverviewSnapshot;
+
+    fn overview() -> OverviewSnapshot {
+        let mut snapshot = OverviewSnapshot::new();
+        snapshot.game_id = 7;
+        snapshot.pot_size = 1_000;
+        snapshot.next_roll_height = Some(42);
+        snapshot
+    }
+
+    #[test]
+    fn decode__round_trips_each_encoding() {
+        for encoding in [RecordEncoding::Json, RecordEncoding::MessagePack] {
+            // given
+            let bytes = encode(&overview(), encoding).unwrap();
+
+            // when
+            let decoded = decode::<OverviewSnapshot>(&bytes).unwrap();
+
+            // then
+            assert_eq!(decoded, overview());
+            assert!(encoding.is_encoding_of(&bytes));
+        }
+    }
+
+    #[test]
+    fn decode__untagged_json__reads_legacy_record() {
+        // given
+        let bytes = serde_json::to_vec(&overview()).unwrap();
+
+        // when
+        let decoded = decode::<OverviewSnapshot>(&bytes).unwrap();
+
+        // then
+        assert_eq!(decoded, overview());
+        assert!(!RecordEncoding::Json.is_encoding_of(&bytes));
+        assert!(!RecordEncoding::MessagePack.is_encoding_of(&bytes));
+    }
+
+    #[test]
+    fn decode__unknown_tag__errors() {
+        // given
+        let bytes = [2u8, 0, 0];
+
+        // when
+        let result = decode::<OverviewSnapshot>(&bytes);
+
+        // then
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("unknown record format tag 2"), "{err}");
+    }
+
+    #[test]
+    fn decode__messagepack_record_without_a_defaulted_field__reads_the_default() {
+        // given
+        let mut older = serde_json::to_value(overview()).unwrap();
+        older.as_object_mut().unwrap().remove("next_roll_time");
+        let mut bytes = vec![MESSAGEPACK_TAG];
+        rmp_serde::encode::write_named(&mut bytes, &older).unwrap();
+
+        // when
+        let decoded = decode::<OverviewSnapshot>(&bytes).unwrap();
+
+        // then
+        assert_eq!(decoded, overview());
+    }
+
+    #[test]
+    fn encode__messagepack__is_smaller_than_json() {
+        // when
+        let json = encode(&overview(), RecordEncoding::Json).unwrap();
+        let messagepack = encode(&overview(), RecordEncoding::MessagePack).unwrap();
+
+        // then
+        assert!(messagepack.len() < json.len());
+    }
+}
@@ -0,0 +1,398 @@
+// Upgrades trees written by older indexers to the current layout, so changing how
+// snapshots are stored never means reindexing from scratch.
+//
+// Version 0 is the layout indexers wrote before the schema was versioned, with untagged
+// JSON values. Records are decoded with the current snapshot types, so one they cannot
+// read fails the migration instead of being carried over, and fields added since then
+// are written out with their `#[serde(default)]` values.
+use super::{
+    SCHEMA_VERSION_KEY,
+    SnapshotRecord,
+    encoding::{
+        self,
+        RecordEncoding,
+    },
+    open_tree,
+};
+use crate::{
+    events::Strap,
+    snapshot::{
+        AccountSnapshot,
+        HistoricalSnapshot,
+        OverviewSnapshot,
+    },
+};
+use anyhow::{
+    Context,
+    bail,
+};
+use serde::{
+    Serialize,
+    de::DeserializeOwned,
+};
+use sled::{
+    Batch,
+    Db,
+    Tree,
+};
+
+/// Layout written by this indexer. Databases from before versioning are at version 0.
+pub const SCHEMA_VERSION: u32 = 1;
+
+struct Migration {
+    description: &'static str,
+    apply: fn(&Db, &str) -> crate::Result<()>,
+}
+
+// `MIGRATIONS[v]` upgrades version `v` to `v + 1`
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [Migration {
+    description: "re-encode every record as MessagePack and key account snapshots by height",
+    apply: reencode_records,
+}];
+
+// Every tree of a namespace; a namespace where all of them are empty is new
+const TREES: [&str; 11] = [
+    "snapshot_overview",
+    "snapshot_overview_meta",
+    "account_snapshots",
+    "historical_snapshots",
+    "historical_accounts",
+    "game_working_state",
+    "house_ledger",
+    "block_timestamps",
+    "player_stats",
+    "strap_supply",
+    "metadata",
+];
+
+/// Bring `namespace` up to `SCHEMA_VERSION`, one migration at a time, recording the
+/// version reached after each
+pub(super) fn migrate(db: &Db, namespace: &str) -> crate::Result<()> {
+    let metadata = open_tree(db, namespace, "metadata")?;
+    let version = match stored_version(&metadata)? {
+        Some(version) => version,
+        None if is_new(db, namespace)? => return store_version(&metadata, SCHEMA_VERSION),
+        None => 0,
+    };
+    if version > SCHEMA_VERSION {
+        bail!(
+            "sled storage{} is at schema version {version}, newer than the version \
+             {SCHEMA_VERSION} this indexer understands",
+            label(namespace)
+        );
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let from = from as u32;
+        tracing::info!(
+            "Migrating sled storage{} from schema version {from}: {}",
+            label(namespace),
+            migration.description
+        );
+        (migration.apply)(db, namespace).with_context(|| {
+            format!("migrate sled storage from schema version {from}")
+        })?;
+        store_version(&metadata, from + 1)?;
+    }
+    Ok(())
+}
+
+fn label(namespace: &str) -> String {
+    if namespace.is_empty() {
+        String::new()
+    } else {
+        format!(" {namespace}")
+    }
+}
+
+fn stored_version(metadata: &Tree) -> crate::Result<Option<u32>> {
+    let Some(bytes) = metadata
+        .get(SCHEMA_VERSION_KEY)
+        .context("read schema version")?
+    else {
+        return Ok(None);
+    };
+    let bytes: [u8; 4] = bytes
+        .as_ref()
+        .try_into()
+        .context("schema version should be 4 bytes")?;
+    Ok(Some(u32::from_be_bytes(bytes)))
+}
+
+fn store_version(metadata: &Tree, version: u32) -> crate::Result<()> {
+    metadata
+        .insert(SCHEMA_VERSION_KEY, version.to_be_bytes().as_slice())
+        .context("write schema version")?;
+    metadata.flush().context("flush schema version")?;
+    Ok(())
+}
+
+fn is_new(db: &Db, namespace: &str) -> crate::Result<bool> {
+    for name in TREES {
+        if !open_tree(db, namespace, name)?.is_empty() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Version 0 to 1. Records are rewritten here rather than as they are read, which keeps
+// reads free of writes. Trees added since version 0 start out empty there.
+//
+// Account snapshots were kept under `identity|game_id` alone, each write replacing the
+// last. Their one record moves to the key of the height it was written at, in the same
+// batch as its re-encode, so a record already in MessagePack has its new key too.
+fn reencode_records(db: &Db, namespace: &str) -> crate::Result<()> {
+    let tree = |name| open_tree(db, namespace, name);
+    reencode::<SnapshotRecord<OverviewSnapshot>>(
+        &tree("snapshot_overview")?,
+        "overview snapshots",
+    )?;
+    reencode_rekeyed(
+        &tree("account_snapshots")?,
+        "account snapshots",
+        |key, record: &SnapshotRecord<AccountSnapshot>| {
+            let mut key = key.to_vec();
+            key.push(b'|');
+            key.extend_from_slice(&record.height.to_be_bytes());
+            key
+        },
+    )?;
+    reencode::<HistoricalSnapshot>(
+        &tree("historical_snapshots")?,
+        "historical snapshots",
+    )?;
+    // The overview meta tree holds raw big-endian heights
+    reencode::<Strap>(&tree("metadata")?, "strap metadata")
+}
+
+// Rewrite every value of `tree` not yet in MessagePack, in one batch
+fn reencode<T: Serialize + DeserializeOwned>(
+    tree: &Tree,
+    label: &str,
+) -> crate::Result<()> {
+    reencode_rekeyed::<T>(tree, label, |key, _| key.to_vec())
+}
+
+// `reencode`, moving each rewritten record to the key `rekey` gives it
+fn reencode_rekeyed<T: Serialize + DeserializeOwned>(
+    tree: &Tree,
+    label: &str,
+    rekey: impl Fn(&[u8], &T) -> Vec<u8>,
+) -> crate::Result<()> {
+    let mut batch = Batch::default();
+    for entry in tree.iter() {
+        let (key, bytes) = entry.with_context(|| format!("iterate {label}"))?;
+        if key.as_ref() == SCHEMA_VERSION_KEY
+            || RecordEncoding::MessagePack.is_encoding_of(&bytes)
+        {
+            continue;
+        }
+        let record = encoding::decode::<T>(&bytes)
+            .with_context(|| format!("deserialize {label}"))?;
+        let bytes = encoding::encode(&record, RecordEncoding::MessagePack)
+            .with_context(|| format!("serialize {label}"))?;
+        let new_key = rekey(&key, &record);
+        if new_key != key.as_ref() {
+            batch.remove(key);
+        }
+        batch.insert(new_key, bytes);
+    }
+    tree.apply_batch(batch)
+        .with_context(|| format!("re-encode {label}"))?;
+    tree.flush().with_context(|| format!("flush {label}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+    use crate::{
+        app::{
+            sled_storage::{
+                SledMetadataStorage,
+                SledSnapshotStorage,
+            },
+            snapshot_storage::{
+                MetadataStorage,
+                SnapshotStorage,
+            },
+        },
+        events::{
+            Modifier,
+            Roll,
+            Strap,
+            StrapKind,
+        },
+        snapshot::{
+            AccountSnapshot,
+            HistoricalSnapshot,
+            OverviewSnapshot,
+        },
+    };
+    use fuel_core::types::fuel_tx::AssetId;
+    use fuels::types::{
+        Address,
+        ContractId,
+        Identity,
+    };
+    use serde::Deserialize;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+
+    // Trees of a database as an older indexer left them. Keys are UTF-8 text or hex,
+    // values JSON or hex.
+    #[derive(Deserialize)]
+    struct Fixture {
+        trees: BTreeMap<String, Vec<FixtureEntry>>,
+    }
+
+    #[derive(Deserialize)]
+    struct FixtureEntry {
+        key: Option<String>,
+        key_hex: Option<String>,
+        value: Option<Value>,
+        value_hex: Option<String>,
+    }
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn fixture_db(fixture: &str) -> Db {
+        let fixture: Fixture = serde_json::from_str(fixture).unwrap();
+        let db = sled::Config::default().temporary(true).open().unwrap();
+        for (name, entries) in fixture.trees {
+            let tree = db.open_tree(name).unwrap();
+            for entry in entries {
+                let key = match (entry.key, entry.key_hex) {
+                    (Some(key), None) => key.into_bytes(),
+                    (None, Some(hex)) => decode_hex(&hex),
+                    _ => panic!("fixture entry needs one of key or key_hex"),
+                };
+                let value = match (entry.value, entry.value_hex) {
+                    (Some(value), None) => serde_json::to_vec(&value).unwrap(),
+                    (None, Some(hex)) => decode_hex(&hex),
+                    _ => panic!("fixture entry needs one of value or value_hex"),
+                };
+                tree.insert(key, value).unwrap();
+            }
+        }
+        db
+    }
+
+    fn version(db: &Db, namespace: &str) -> Option<u32> {
+        stored_version(&open_tree(db, namespace, "metadata").unwrap()).unwrap()
+    }
+
+    fn address() -> Identity {
+        Identity::Address(Address::from([1; 32]))
+    }
+
+    fn contract() -> Identity {
+        Identity::ContractId(ContractId::from([2; 32]))
+    }
+
+    fn account(total_chip_bet: u64) -> AccountSnapshot {
+        AccountSnapshot {
+            total_chip_bet,
+            ..AccountSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn migrate__unversioned_fixture__reads_back_at_current_version() {
+        // given
+        let db = fixture_db(include_str!("fixtures/v0.json"));
+
+        // when
+        let snapshots = SledSnapshotStorage::new(&db).unwrap();
+
+        // then
+        assert_eq!(version(&db, ""), Some(SCHEMA_VERSION));
+        // Each account's one record, moved to the key of its height
+        assert_eq!(db.open_tree("account_snapshots").unwrap().len(), 2);
+        for name in [
+            "snapshot_overview",
+            "account_snapshots",
+            "historical_snapshots",
+        ] {
+            for value in db.open_tree(name).unwrap().iter().values() {
+                assert!(RecordEncoding::MessagePack.is_encoding_of(&value.unwrap()));
+            }
+        }
+        let expected_overview = OverviewSnapshot {
+            game_id: 2,
+            rolls: vec![Roll::Six],
+            pot_size: 130,
+            current_block_height: 9,
+            total_chip_bets: 7,
+            ..OverviewSnapshot::default()
+        };
+        assert_eq!(snapshots.latest_snapshot().unwrap(), (expected_overview, 9));
+        let mut first_account = account(30);
+        first_account.per_roll_bets[4].bets = serde_json::from_value(serde_json::json!(
+            [{"bet_roll_index": 0, "amount": 30, "kind": "Chip"}]
+        ))
+        .unwrap();
+        assert_eq!(
+            snapshots.account_snapshot_at(&address(), 1).unwrap(),
+            Some((first_account, 5))
+        );
+        assert_eq!(
+            snapshots.latest_account_snapshot(&contract()).unwrap(),
+            Some((account(7), 9))
+        );
+        assert_eq!(
+            snapshots.historical_snapshots(1).unwrap(),
+            HistoricalSnapshot::new(1, vec![Roll::Six, Roll::Seven], Vec::new())
+        );
+        let metadata = SledMetadataStorage::new(&db).unwrap();
+        assert_eq!(
+            metadata.all_known_straps().unwrap(),
+            vec![(
+                AssetId::from([3; 32]),
+                Strap::new(1, StrapKind::Hat, Modifier::Lucky)
+            )]
+        );
+    }
+
+    #[test]
+    fn migrate__new_namespace__starts_at_current_version() {
+        // given
+        let db = sled::Config::default().temporary(true).open().unwrap();
+
+        // when
+        let mut snapshots = SledSnapshotStorage::in_namespace(&db, "a").unwrap();
+        snapshots
+            .update_account_snapshot(&address(), 1, &account(1), 1)
+            .unwrap();
+        let reopened = SledSnapshotStorage::in_namespace(&db, "a").unwrap();
+
+        // then
+        assert_eq!(version(&db, "a"), Some(SCHEMA_VERSION));
+        assert_eq!(version(&db, ""), None);
+        assert_eq!(
+            reopened.account_snapshot_at(&address(), 1).unwrap(),
+            Some((account(1), 1))
+        );
+    }
+
+    #[test]
+    fn migrate__newer_schema_version__refuses_to_open() {
+        // given
+        let db = sled::Config::default().temporary(true).open().unwrap();
+        let metadata = open_tree(&db, "", "metadata").unwrap();
+        store_version(&metadata, SCHEMA_VERSION + 1).unwrap();
+
+        // when
+        let result = SledSnapshotStorage::new(&db);
+
+        // then
+        let message = result.err().unwrap().to_string();
+        assert!(message.contains("newer than the version"), "{message}");
+    }
+}
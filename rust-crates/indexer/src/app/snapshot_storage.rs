@@ -1,7 +1,11 @@
 use crate::snapshot::{
     AccountSnapshot,
+    GameWorkingState,
     HistoricalSnapshot,
+    HouseLedgerEntry,
     OverviewSnapshot,
+    PlayerGameStats,
+    StrapSupplyChange,
 };
 
 use crate::events::Strap;
@@ -9,9 +13,35 @@ use fuels::{
     prelude::*,
     types::Identity,
 };
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::Path,
+};
+
+/// Blocks of overview history kept at every height unless configured otherwise
+pub const DEFAULT_REORG_WINDOW: u32 = 1_000;
+
+/// Lookup of something storage never had, as opposed to storage failing. Backends return it
+/// inside their `anyhow` errors so readers can tell the two apart by downcasting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotFound(pub String);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
 
 pub trait SnapshotStorage {
-    /// retrieve latest snapshot along with its block height
+    /// retrieve latest snapshot along with its block height, or `NotFound` until one has
+    /// been written
     fn latest_snapshot(&self) -> crate::Result<(OverviewSnapshot, u32)>;
 
     /// retrieve latest account snapshot along with its block height
@@ -43,18 +73,231 @@ pub trait SnapshotStorage {
         height: u32,
     ) -> crate::Result<()>;
 
+    /// retrieve a player's stats for the given game id
+    fn player_stats_at(
+        &self,
+        player: &Identity,
+        game_id: u32,
+    ) -> crate::Result<Option<PlayerGameStats>>;
+
+    /// write or overwrite a player's stats for the given game id at given block height
+    fn update_player_stats(
+        &mut self,
+        player: &Identity,
+        game_id: u32,
+        stats: &PlayerGameStats,
+        height: u32,
+    ) -> crate::Result<()>;
+
+    /// retrieve every player's stats for games `from_game..=to_game`, along with the
+    /// game id, ordered by game id
+    fn player_stats_in_games(
+        &self,
+        from_game: u32,
+        to_game: u32,
+    ) -> crate::Result<Vec<(Identity, u32, PlayerGameStats)>>;
+
+    /// retrieve latest per-game working state along with its block height
+    fn latest_working_state(&self) -> crate::Result<Option<(GameWorkingState, u32)>>;
+
+    /// write or overwrite per-game working state at given block height
+    fn update_working_state(
+        &mut self,
+        state: &GameWorkingState,
+        height: u32,
+    ) -> crate::Result<()>;
+
+    /// append an entry to the house ledger at given block height
+    fn record_house_ledger_entry(
+        &mut self,
+        entry: &HouseLedgerEntry,
+        height: u32,
+    ) -> crate::Result<()>;
+
+    /// retrieve all house ledger entries along with their block heights, oldest first
+    fn house_ledger(&self) -> crate::Result<Vec<(HouseLedgerEntry, u32)>>;
+
+    /// append a strap supply change at given block height
+    fn record_strap_supply_change(
+        &mut self,
+        change: &StrapSupplyChange,
+        height: u32,
+    ) -> crate::Result<()>;
+
+    /// retrieve all strap supply changes along with their block heights, oldest first
+    fn strap_supply_changes(&self) -> crate::Result<Vec<(StrapSupplyChange, u32)>>;
+
+    /// write or overwrite the unix timestamp (seconds) of the block at given height
+    fn record_block_timestamp(
+        &mut self,
+        height: u32,
+        timestamp: u64,
+    ) -> crate::Result<()>;
+
+    /// retrieve the closest known block timestamp at or below given height, along with
+    /// the height it was recorded for
+    fn block_timestamp_at_or_before(
+        &self,
+        height: u32,
+    ) -> crate::Result<Option<(u32, u64)>>;
+
     /// roll back snapshots to given block height (deleting any snapshots above that height)
     fn roll_back_snapshots(&mut self, to_height: u32) -> crate::Result<()>;
 
-    /// retrieve historical snapshot for given game id
+    /// retrieve historical snapshot for given game id, or `NotFound` if the game has not
+    /// finished
     fn historical_snapshots(&self, game_id: u32) -> crate::Result<HistoricalSnapshot>;
 
-    /// write or overwrite historical snapshot for given game id
+    /// write or overwrite historical snapshot for given game id. Account updates recorded
+    /// for the game still apply on top of it
     fn write_historical_snapshot(
         &mut self,
         game_id: u32,
         snapshot: &HistoricalSnapshot,
     ) -> crate::Result<()>;
+
+    /// replace `account`'s entry in the results of finished game `game_id`, as of given
+    /// block height. Rolling back below that height restores the entry it replaced
+    fn update_historical_account(
+        &mut self,
+        game_id: u32,
+        account: &Identity,
+        account_snapshot: &AccountSnapshot,
+        height: u32,
+    ) -> crate::Result<()>;
+
+    /// retrieve up to `limit` historical snapshots in `order`, starting at game id `from`
+    /// (inclusive) or at the first game in that order
+    fn historical_snapshot_range(
+        &self,
+        from: Option<u32>,
+        limit: usize,
+        order: HistoryOrder,
+    ) -> crate::Result<Vec<HistoricalSnapshot>>;
+
+    /// remove historical snapshots, and their account updates, for the given game id and
+    /// every later game
+    fn remove_historical_snapshots_from(&mut self, game_id: u32) -> crate::Result<()>;
+
+    /// roll back to given block height, then remove the historical snapshots of the game
+    /// current at that height and every later game, or of every game if no snapshot is
+    /// left. Backends that can should apply both atomically
+    fn roll_back(&mut self, to_height: u32) -> crate::Result<()> {
+        self.roll_back_snapshots(to_height)?;
+        let current_game = match self.latest_snapshot() {
+            Ok((snapshot, _)) => snapshot.game_id,
+            Err(_) => 0,
+        };
+        self.remove_historical_snapshots_from(current_game)
+    }
+
+    /// remove everything recorded at or above `from_height`, the same way `roll_back` to
+    /// the height before does. From 0 that is everything, historical snapshots included
+    fn prune_from(&mut self, from_height: u32) -> crate::Result<()>;
+
+    /// write every change made while applying one event batch. Backends that can should
+    /// commit them atomically, so a crash never leaves a partially applied block
+    fn commit_block(&mut self, changes: &BlockChanges) -> crate::Result<()> {
+        for (game_id, snapshot) in &changes.historical {
+            self.write_historical_snapshot(*game_id, snapshot)?;
+        }
+        for (game_id, account, snapshot, height) in &changes.historical_accounts {
+            self.update_historical_account(*game_id, account, snapshot, *height)?;
+        }
+        for (account, game_id, snapshot, height) in &changes.accounts {
+            self.update_account_snapshot(account, *game_id, snapshot, *height)?;
+        }
+        for (player, game_id, stats, height) in &changes.player_stats {
+            self.update_player_stats(player, *game_id, stats, *height)?;
+        }
+        if let Some((state, height)) = &changes.working_state {
+            self.update_working_state(state, *height)?;
+        }
+        for (entry, height) in &changes.house_ledger {
+            self.record_house_ledger_entry(entry, *height)?;
+        }
+        for (change, height) in &changes.strap_supply {
+            self.record_strap_supply_change(change, *height)?;
+        }
+        for (height, timestamp) in &changes.block_timestamps {
+            self.record_block_timestamp(*height, *timestamp)?;
+        }
+        if let Some((snapshot, height)) = &changes.overview {
+            self.update_snapshot(snapshot, *height)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which per-height overview snapshots `PersistentSnapshotStorage::compact_overviews`
+/// keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Blocks below the latest snapshot that keep their overview. Once compacted, a
+    /// rollback deeper than this is refused, as the overview it needs is gone. At
+    /// least 1, or `prune_from` the latest height on restart is refused too.
+    pub reorg_window: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            reorg_window: DEFAULT_REORG_WINDOW,
+        }
+    }
+}
+
+/// A `SnapshotStorage` kept on disk that the indexer binary can run on. Clones share
+/// the same underlying storage.
+pub trait PersistentSnapshotStorage:
+    SnapshotStorage + Clone + Send + Sync + Sized + 'static
+{
+    type Metadata: MetadataStorage + Clone + Send + Sync + 'static;
+
+    /// Open the storage under the directory `path`, with one pair of storages per
+    /// namespace. The empty namespace is the layout of an indexer with one deployment.
+    fn open_namespaces(
+        path: &Path,
+        namespaces: &[String],
+    ) -> crate::Result<Vec<(Self, Self::Metadata)>>;
+
+    /// Drop overview snapshots older than the policy's reorg window, except the last
+    /// one of each game, and refuse later rollbacks below the window. Returns how many
+    /// were removed.
+    fn compact_overviews(&self, policy: RetentionPolicy) -> crate::Result<usize>;
+}
+
+/// Order to list historical games in, by game id
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryOrder {
+    #[serde(rename = "asc")]
+    Ascending,
+    #[default]
+    #[serde(rename = "desc")]
+    Descending,
+}
+
+/// Writes buffered while applying an event batch, see `WorkingSet`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BlockChanges {
+    pub overview: Option<(OverviewSnapshot, u32)>,
+    /// account, game id, snapshot and block height
+    pub accounts: Vec<(Identity, u32, AccountSnapshot, u32)>,
+    /// player, game id, stats and block height
+    pub player_stats: Vec<(Identity, u32, PlayerGameStats, u32)>,
+    pub working_state: Option<(GameWorkingState, u32)>,
+    pub house_ledger: Vec<(HouseLedgerEntry, u32)>,
+    pub strap_supply: Vec<(StrapSupplyChange, u32)>,
+    pub block_timestamps: BTreeMap<u32, u64>,
+    pub historical: BTreeMap<u32, HistoricalSnapshot>,
+    /// game id, account, snapshot and block height of updates to finished games
+    pub historical_accounts: Vec<(u32, Identity, AccountSnapshot, u32)>,
+}
+
+impl BlockChanges {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 pub trait MetadataStorage {
@@ -0,0 +1,1604 @@
+// SQLite-backed storage, with a relational schema that can be queried directly.
+use crate::{
+    app::{
+        identity::parse_identity,
+        snapshot_storage::{
+            BlockChanges,
+            HistoryOrder,
+            MetadataStorage,
+            NotFound,
+            PersistentSnapshotStorage,
+            RetentionPolicy,
+            SnapshotStorage,
+        },
+    },
+    events::Strap,
+    snapshot::{
+        AccountBetKind,
+        AccountSnapshot,
+        GameWorkingState,
+        HistoricalSnapshot,
+        HouseLedgerEntry,
+        OverviewSnapshot,
+        PlayerGameStats,
+        StrapSupplyChange,
+    },
+};
+use anyhow::{
+    Context,
+    anyhow,
+    bail,
+};
+use fuel_core::types::fuel_tx::AssetId;
+use fuels::types::Identity;
+use rusqlite::{
+    Connection,
+    OpenFlags,
+    OptionalExtension,
+    ToSql,
+    params,
+    types::{
+        FromSql,
+        FromSqlResult,
+        ToSqlOutput,
+        ValueRef,
+    },
+};
+use serde::{
+    Serialize,
+    de::DeserializeOwned,
+};
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+/// Database file of the empty namespace, i.e. of an indexer with one deployment
+pub const DEFAULT_DATABASE_FILE: &str = "indexer.sqlite3";
+
+// Snapshots are kept whole in the JSON `snapshot`/`state`/`entry` columns so they read
+// back exactly; the other columns break them down for SQL. Identities are written as
+// `address:0x...` or `contract:0x...`, enum variants by name. SQLite integers are
+// signed, so amounts and timestamps above `i64::MAX` read as negative from plain SQL,
+// i.e. as the value minus 2^64.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS overview_snapshots (
+    height INTEGER PRIMARY KEY,
+    game_id INTEGER NOT NULL,
+    pot_size INTEGER NOT NULL,
+    chips_owed INTEGER NOT NULL,
+    total_chip_bets INTEGER NOT NULL,
+    snapshot TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS overview_snapshots_game_id ON overview_snapshots (game_id);
+
+-- Finished games
+CREATE TABLE IF NOT EXISTS games (
+    game_id INTEGER PRIMARY KEY,
+    roll_count INTEGER NOT NULL,
+    player_count INTEGER NOT NULL,
+    snapshot TEXT NOT NULL
+);
+
+-- Results of finished games changed after they ended, e.g. by claims. They apply on
+-- top of `games.snapshot` in height order; `player_count` is as of the game's end.
+CREATE TABLE IF NOT EXISTS historical_accounts (
+    game_id INTEGER NOT NULL,
+    identity TEXT NOT NULL,
+    height INTEGER NOT NULL,
+    snapshot TEXT NOT NULL,
+    PRIMARY KEY (game_id, identity, height)
+);
+
+-- Rolls of finished games, `roll_index` counting from 0 like `bets.bet_roll_index`
+CREATE TABLE IF NOT EXISTS rolls (
+    game_id INTEGER NOT NULL,
+    roll_index INTEGER NOT NULL,
+    roll TEXT NOT NULL,
+    PRIMARY KEY (game_id, roll_index)
+);
+
+-- One row per height an account changed at in a game, the highest being its current
+-- state, so a rollback only has to drop the rows above the height it returns to. Bets
+-- and claims are written out again with every row.
+CREATE TABLE IF NOT EXISTS account_snapshots (
+    identity TEXT NOT NULL,
+    game_id INTEGER NOT NULL,
+    total_chip_bet INTEGER NOT NULL,
+    total_chip_won INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    snapshot TEXT NOT NULL,
+    PRIMARY KEY (identity, game_id, height)
+);
+
+-- Strap columns are unset for chip bets
+CREATE TABLE IF NOT EXISTS bets (
+    identity TEXT NOT NULL,
+    game_id INTEGER NOT NULL,
+    roll TEXT NOT NULL,
+    bet_roll_index INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    strap_level INTEGER,
+    strap_kind TEXT,
+    strap_modifier TEXT,
+    height INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS bets_account ON bets (identity, game_id, height);
+
+CREATE TABLE IF NOT EXISTS claims (
+    identity TEXT NOT NULL,
+    game_id INTEGER NOT NULL,
+    chips INTEGER NOT NULL,
+    straps TEXT NOT NULL,
+    height INTEGER NOT NULL,
+    PRIMARY KEY (identity, game_id, height)
+);
+
+-- Versioned by height like `account_snapshots`
+CREATE TABLE IF NOT EXISTS player_stats (
+    game_id INTEGER NOT NULL,
+    identity TEXT NOT NULL,
+    chips_wagered INTEGER NOT NULL,
+    chips_won INTEGER NOT NULL,
+    hit INTEGER NOT NULL,
+    highest_strap_level INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    PRIMARY KEY (game_id, identity, height)
+);
+
+CREATE TABLE IF NOT EXISTS working_states (
+    height INTEGER PRIMARY KEY,
+    state TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS house_ledger (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    entry TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS strap_supply_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    strap_level INTEGER NOT NULL,
+    strap_kind TEXT NOT NULL,
+    strap_modifier TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    height INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS block_timestamps (
+    height INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL
+);
+
+-- Lowest height whose overview survived compaction, rollbacks below it are refused
+CREATE TABLE IF NOT EXISTS compaction (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    compacted_below INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS straps (
+    asset_id TEXT PRIMARY KEY,
+    strap_level INTEGER NOT NULL,
+    strap_kind TEXT NOT NULL,
+    strap_modifier TEXT NOT NULL
+);
+";
+
+// Every table with a `height` column, which rollbacks and pruning cut back
+const HEIGHT_TABLES: [&str; 10] = [
+    "overview_snapshots",
+    "account_snapshots",
+    "historical_accounts",
+    "bets",
+    "claims",
+    "player_stats",
+    "working_states",
+    "house_ledger",
+    "strap_supply_changes",
+    "block_timestamps",
+];
+
+#[derive(Clone)]
+pub struct SqliteSnapshotStorage {
+    connections: Connections,
+}
+
+#[derive(Clone)]
+pub struct SqliteMetadataStorage {
+    connections: Connections,
+}
+
+// The writer and a read-only connection onto the same database, so readers such as the
+// query API don't wait behind the event loop's writes
+#[derive(Clone)]
+struct Connections {
+    writer: Arc<Mutex<Connection>>,
+    reader: Arc<Mutex<Connection>>,
+}
+
+impl Connections {
+    // One read transaction per call, so reads spanning several tables see one commit
+    fn read<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        let mut connection = self.reader.lock().unwrap();
+        let transaction = connection
+            .transaction()
+            .context("begin sqlite read transaction")?;
+        f(&transaction)
+    }
+
+    // Writes that touch more than one row go through a transaction, so readers never
+    // see them half done
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        let mut connection = self.writer.lock().unwrap();
+        let transaction = connection
+            .transaction()
+            .context("begin sqlite transaction")?;
+        let result = f(&transaction)?;
+        transaction.commit().context("commit sqlite transaction")?;
+        Ok(result)
+    }
+}
+
+impl SqliteSnapshotStorage {
+    /// Open or create the database file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> crate::Result<(Self, SqliteMetadataStorage)> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)
+            .with_context(|| format!("open sqlite database {}", path.display()))?;
+        // Lets analysts, and our own reader, read while the indexer writes
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .context("enable sqlite write-ahead log")?;
+        let writer = Self::with_schema(connection)?;
+        let reader = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("open sqlite database {} to read", path.display()))?;
+        Ok(Self::with_connections(Connections {
+            writer: Arc::new(Mutex::new(writer)),
+            reader: Arc::new(Mutex::new(reader)),
+        }))
+    }
+
+    /// An in-memory database has no file to open a second connection onto, so its
+    /// readers share the writer's connection
+    pub fn open_in_memory() -> crate::Result<(Self, SqliteMetadataStorage)> {
+        let connection =
+            Connection::open_in_memory().context("open in-memory sqlite database")?;
+        let connection = Arc::new(Mutex::new(Self::with_schema(connection)?));
+        Ok(Self::with_connections(Connections {
+            writer: connection.clone(),
+            reader: connection,
+        }))
+    }
+
+    fn with_schema(connection: Connection) -> crate::Result<Connection> {
+        connection
+            .execute_batch(SCHEMA)
+            .context("create sqlite schema")?;
+        Ok(connection)
+    }
+
+    fn with_connections(connections: Connections) -> (Self, SqliteMetadataStorage) {
+        let metadata = SqliteMetadataStorage {
+            connections: connections.clone(),
+        };
+        (Self { connections }, metadata)
+    }
+
+    fn read<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        self.connections.read(f)
+    }
+
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        self.connections.write(f)
+    }
+}
+
+/// SQLite integers are signed, so `u64` amounts and timestamps are stored as the `i64`
+/// with the same bits. Every value up to `i64::MAX` reads the same from plain SQL, larger
+/// ones read as negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SqlU64(u64);
+
+impl ToSql for SqlU64 {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0.cast_signed()))
+    }
+}
+
+impl FromSql for SqlU64 {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(|value| Self(value.cast_unsigned()))
+    }
+}
+
+fn write_overview(
+    connection: &Connection,
+    snapshot: &OverviewSnapshot,
+    height: u32,
+) -> crate::Result<()> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO overview_snapshots
+                (height, game_id, pot_size, chips_owed, total_chip_bets, snapshot)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                height,
+                snapshot.game_id,
+                SqlU64(snapshot.pot_size),
+                SqlU64(snapshot.chips_owed),
+                SqlU64(snapshot.total_chip_bets),
+                to_json(snapshot)?,
+            ],
+        )
+        .context("persist overview snapshot")?;
+    Ok(())
+}
+
+fn write_account(
+    connection: &Connection,
+    account: &Identity,
+    game_id: u32,
+    snapshot: &AccountSnapshot,
+    height: u32,
+) -> crate::Result<()> {
+    let identity = identity_key(account);
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO account_snapshots
+                (identity, game_id, total_chip_bet, total_chip_won, height, snapshot)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                identity,
+                game_id,
+                SqlU64(snapshot.total_chip_bet),
+                SqlU64(snapshot.total_chip_won),
+                height,
+                to_json(snapshot)?,
+            ],
+        )
+        .context("persist account snapshot")?;
+
+    connection.execute(
+        "DELETE FROM bets WHERE identity = ?1 AND game_id = ?2 AND height = ?3",
+        params![identity, game_id, height],
+    )?;
+    let mut insert_bet = connection.prepare_cached(
+        "INSERT INTO bets (identity, game_id, roll, bet_roll_index, amount, strap_level,
+            strap_kind, strap_modifier, height)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?;
+    for roll_bets in &snapshot.per_roll_bets {
+        let roll = variant_name(&roll_bets.roll)?;
+        for bet in &roll_bets.bets {
+            let (strap_level, strap_kind, strap_modifier) = match &bet.kind {
+                AccountBetKind::Chip => (None, None, None),
+                AccountBetKind::Strap(strap) => {
+                    let (level, kind, modifier) = strap_columns(strap)?;
+                    (Some(level), Some(kind), Some(modifier))
+                }
+            };
+            insert_bet.execute(params![
+                identity,
+                game_id,
+                roll,
+                bet.bet_roll_index,
+                SqlU64(bet.amount),
+                strap_level,
+                strap_kind,
+                strap_modifier,
+                height,
+            ])?;
+        }
+    }
+
+    connection.execute(
+        "DELETE FROM claims WHERE identity = ?1 AND game_id = ?2 AND height = ?3",
+        params![identity, game_id, height],
+    )?;
+    if let Some((chips, straps)) = &snapshot.claimed_rewards {
+        connection.execute(
+            "INSERT INTO claims (identity, game_id, chips, straps, height)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![identity, game_id, SqlU64(*chips), to_json(straps)?, height],
+        )?;
+    }
+    Ok(())
+}
+
+fn write_player_stats(
+    connection: &Connection,
+    player: &Identity,
+    game_id: u32,
+    stats: &PlayerGameStats,
+    height: u32,
+) -> crate::Result<()> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO player_stats
+                (game_id, identity, chips_wagered, chips_won, hit, highest_strap_level,
+                 height)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                game_id,
+                identity_key(player),
+                SqlU64(stats.chips_wagered),
+                SqlU64(stats.chips_won),
+                stats.hit,
+                stats.highest_strap_level,
+                height,
+            ],
+        )
+        .context("persist player stats")?;
+    Ok(())
+}
+
+fn write_working_state(
+    connection: &Connection,
+    state: &GameWorkingState,
+    height: u32,
+) -> crate::Result<()> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO working_states (height, state) VALUES (?1, ?2)",
+            params![height, to_json(state)?],
+        )
+        .context("persist working state")?;
+    Ok(())
+}
+
+fn write_house_ledger_entry(
+    connection: &Connection,
+    entry: &HouseLedgerEntry,
+    height: u32,
+) -> crate::Result<()> {
+    let (kind, amount) = match entry {
+        HouseLedgerEntry::Fund { amount, .. } => ("fund", amount),
+        HouseLedgerEntry::Withdrawal { amount, .. } => ("withdrawal", amount),
+        HouseLedgerEntry::FailedWithdrawal {
+            requested_amount, ..
+        } => ("failed_withdrawal", requested_amount),
+    };
+    connection
+        .execute(
+            "INSERT INTO house_ledger (kind, amount, height, entry)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![kind, SqlU64(*amount), height, to_json(entry)?],
+        )
+        .context("persist house ledger entry")?;
+    Ok(())
+}
+
+fn write_strap_supply_change(
+    connection: &Connection,
+    change: &StrapSupplyChange,
+    height: u32,
+) -> crate::Result<()> {
+    let (level, kind, modifier) = strap_columns(&change.strap)?;
+    connection
+        .execute(
+            "INSERT INTO strap_supply_changes
+                (strap_level, strap_kind, strap_modifier, kind, amount, height)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                level,
+                kind,
+                modifier,
+                variant_name(&change.kind)?,
+                SqlU64(change.amount),
+                height,
+            ],
+        )
+        .context("persist strap supply change")?;
+    Ok(())
+}
+
+fn write_block_timestamp(
+    connection: &Connection,
+    height: u32,
+    timestamp: u64,
+) -> crate::Result<()> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO block_timestamps (height, timestamp) VALUES (?1, ?2)",
+            params![height, SqlU64(timestamp)],
+        )
+        .context("persist block timestamp")?;
+    Ok(())
+}
+
+fn write_historical(
+    connection: &Connection,
+    game_id: u32,
+    snapshot: &HistoricalSnapshot,
+) -> crate::Result<()> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO games (game_id, roll_count, player_count, snapshot)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                game_id,
+                snapshot.rolls.len(),
+                snapshot.accounts.len(),
+                to_json(snapshot)?,
+            ],
+        )
+        .context("persist historical snapshot")?;
+    connection.execute("DELETE FROM rolls WHERE game_id = ?1", params![game_id])?;
+    let mut insert_roll = connection.prepare_cached(
+        "INSERT INTO rolls (game_id, roll_index, roll) VALUES (?1, ?2, ?3)",
+    )?;
+    for (roll_index, roll) in snapshot.rolls.iter().enumerate() {
+        insert_roll.execute(params![game_id, roll_index, variant_name(roll)?])?;
+    }
+    Ok(())
+}
+
+fn write_historical_account(
+    connection: &Connection,
+    game_id: u32,
+    account: &Identity,
+    snapshot: &AccountSnapshot,
+    height: u32,
+) -> crate::Result<()> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO historical_accounts (game_id, identity, height, snapshot)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![game_id, identity_key(account), height, to_json(snapshot)?],
+        )
+        .context("persist historical account")?;
+    Ok(())
+}
+
+fn with_account_updates(
+    connection: &Connection,
+    mut snapshot: HistoricalSnapshot,
+) -> crate::Result<HistoricalSnapshot> {
+    let mut statement = connection.prepare_cached(
+        "SELECT identity, snapshot FROM historical_accounts WHERE game_id = ?1
+         ORDER BY height",
+    )?;
+    let rows = statement
+        .query_map(params![snapshot.game_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (identity, account_snapshot) in rows {
+        snapshot.upsert_account(
+            parse_identity(&identity, None)?,
+            from_json(&account_snapshot)?,
+        );
+    }
+    Ok(snapshot)
+}
+
+fn ensure_not_compacted(connection: &Connection, to_height: u32) -> crate::Result<()> {
+    let compacted_below: Option<u32> = connection
+        .query_row("SELECT compacted_below FROM compaction", [], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    if let Some(compacted_below) = compacted_below
+        && to_height < compacted_below
+    {
+        bail!(
+            "cannot roll back to height {to_height}, overview snapshots below \
+             {compacted_below} were compacted"
+        );
+    }
+    Ok(())
+}
+
+fn remove_games_from(connection: &Connection, game_id: u32) -> crate::Result<()> {
+    connection.execute("DELETE FROM games WHERE game_id >= ?1", params![game_id])?;
+    connection.execute("DELETE FROM rolls WHERE game_id >= ?1", params![game_id])?;
+    connection.execute(
+        "DELETE FROM historical_accounts WHERE game_id >= ?1",
+        params![game_id],
+    )?;
+    Ok(())
+}
+
+// Remove every row recorded at or above `from_height`
+fn delete_from_height(connection: &Connection, from_height: u32) -> crate::Result<()> {
+    for table in HEIGHT_TABLES {
+        connection
+            .execute(
+                &format!("DELETE FROM {table} WHERE height >= ?1"),
+                params![from_height],
+            )
+            .with_context(|| format!("remove {table} from height {from_height}"))?;
+    }
+    Ok(())
+}
+
+fn latest_overview(
+    connection: &Connection,
+) -> crate::Result<Option<(OverviewSnapshot, u32)>> {
+    let row = connection
+        .query_row(
+            "SELECT snapshot, height FROM overview_snapshots ORDER BY height DESC LIMIT 1",
+            [],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?)),
+        )
+        .optional()?;
+    row.map(|(snapshot, height)| Ok((from_json(&snapshot)?, height)))
+        .transpose()
+}
+
+impl PersistentSnapshotStorage for SqliteSnapshotStorage {
+    type Metadata = SqliteMetadataStorage;
+
+    // One database file per namespace, so each can be queried or attached on its own
+    fn open_namespaces(
+        path: &Path,
+        namespaces: &[String],
+    ) -> crate::Result<Vec<(Self, SqliteMetadataStorage)>> {
+        namespaces
+            .iter()
+            .map(|namespace| {
+                let file = if namespace.is_empty() {
+                    DEFAULT_DATABASE_FILE.to_string()
+                } else {
+                    format!("{namespace}.sqlite3")
+                };
+                Self::open(path.join(file))
+            })
+            .collect()
+    }
+
+    fn compact_overviews(&self, policy: RetentionPolicy) -> crate::Result<usize> {
+        self.write(|connection| {
+            let latest: Option<u32> = connection.query_row(
+                "SELECT MAX(height) FROM overview_snapshots",
+                [],
+                |row| row.get(0),
+            )?;
+            let Some(latest) = latest else {
+                return Ok(0);
+            };
+            let cutoff = latest.saturating_sub(policy.reorg_window);
+            connection
+                .execute(
+                    "INSERT INTO compaction (id, compacted_below) VALUES (0, ?1)
+                     ON CONFLICT (id) DO UPDATE
+                     SET compacted_below = MAX(compacted_below, excluded.compacted_below)",
+                    params![cutoff],
+                )
+                .context("record compaction cutoff")?;
+            let removed = connection
+                .execute(
+                    "DELETE FROM overview_snapshots
+                     WHERE height < ?1 AND height NOT IN (
+                        SELECT MAX(height) FROM overview_snapshots
+                        WHERE height < ?1
+                        GROUP BY game_id
+                     )",
+                    params![cutoff],
+                )
+                .context("remove compacted overview snapshots")?;
+            Ok(removed)
+        })
+    }
+}
+
+impl SnapshotStorage for SqliteSnapshotStorage {
+    fn latest_snapshot(&self) -> crate::Result<(OverviewSnapshot, u32)> {
+        self.read(latest_overview)?
+            .ok_or_else(|| NotFound("No snapshot found".to_string()).into())
+    }
+
+    fn latest_account_snapshot(
+        &self,
+        account: &Identity,
+    ) -> crate::Result<Option<(AccountSnapshot, u32)>> {
+        let Some((overview, _)) = self.read(latest_overview)? else {
+            return Ok(None);
+        };
+        self.account_snapshot_at(account, overview.game_id)
+    }
+
+    fn account_snapshot_at(
+        &self,
+        account: &Identity,
+        game_id: u32,
+    ) -> crate::Result<Option<(AccountSnapshot, u32)>> {
+        let row = self.read(|connection| {
+            let row = connection
+                .query_row(
+                    "SELECT snapshot, height FROM account_snapshots
+                     WHERE identity = ?1 AND game_id = ?2
+                     ORDER BY height DESC LIMIT 1",
+                    params![identity_key(account), game_id],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?)),
+                )
+                .optional()?;
+            Ok(row)
+        })?;
+        row.map(|(snapshot, height)| Ok((from_json(&snapshot)?, height)))
+            .transpose()
+    }
+
+    fn update_snapshot(
+        &mut self,
+        snapshot: &OverviewSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.write(|connection| write_overview(connection, snapshot, height))
+    }
+
+    fn update_account_snapshot(
+        &mut self,
+        account: &Identity,
+        game_id: u32,
+        account_snapshot: &AccountSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.write(|connection| {
+            write_account(connection, account, game_id, account_snapshot, height)
+        })
+    }
+
+    fn player_stats_at(
+        &self,
+        player: &Identity,
+        game_id: u32,
+    ) -> crate::Result<Option<PlayerGameStats>> {
+        self.read(|connection| {
+            let stats = connection
+                .query_row(
+                    "SELECT chips_wagered, chips_won, hit, highest_strap_level
+                     FROM player_stats WHERE game_id = ?1 AND identity = ?2
+                     ORDER BY height DESC LIMIT 1",
+                    params![game_id, identity_key(player)],
+                    |row| {
+                        Ok(PlayerGameStats {
+                            chips_wagered: row.get::<_, SqlU64>(0)?.0,
+                            chips_won: row.get::<_, SqlU64>(1)?.0,
+                            hit: row.get(2)?,
+                            highest_strap_level: row.get(3)?,
+                        })
+                    },
+                )
+                .optional()?;
+            Ok(stats)
+        })
+    }
+
+    fn update_player_stats(
+        &mut self,
+        player: &Identity,
+        game_id: u32,
+        stats: &PlayerGameStats,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.write(|connection| {
+            write_player_stats(connection, player, game_id, stats, height)
+        })
+    }
+
+    fn player_stats_in_games(
+        &self,
+        from_game: u32,
+        to_game: u32,
+    ) -> crate::Result<Vec<(Identity, u32, PlayerGameStats)>> {
+        let rows = self.read(|connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT identity, game_id, chips_wagered, chips_won, hit,
+                    highest_strap_level
+                 FROM player_stats AS stats WHERE game_id BETWEEN ?1 AND ?2
+                    AND height = (
+                        SELECT MAX(height) FROM player_stats
+                        WHERE game_id = stats.game_id AND identity = stats.identity
+                    )
+                 ORDER BY game_id, identity",
+            )?;
+            let rows = statement
+                .query_map(params![from_game, to_game], |row| {
+                    let stats = PlayerGameStats {
+                        chips_wagered: row.get::<_, SqlU64>(2)?.0,
+                        chips_won: row.get::<_, SqlU64>(3)?.0,
+                        hit: row.get(4)?,
+                        highest_strap_level: row.get(5)?,
+                    };
+                    Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?, stats))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })?;
+        rows.into_iter()
+            .map(|(identity, game_id, stats)| {
+                Ok((parse_identity(&identity, None)?, game_id, stats))
+            })
+            .collect()
+    }
+
+    fn latest_working_state(&self) -> crate::Result<Option<(GameWorkingState, u32)>> {
+        let row = self.read(|connection| {
+            let row = connection
+                .query_row(
+                    "SELECT state, height FROM working_states ORDER BY height DESC LIMIT 1",
+                    [],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?)),
+                )
+                .optional()?;
+            Ok(row)
+        })?;
+        row.map(|(state, height)| Ok((from_json(&state)?, height)))
+            .transpose()
+    }
+
+    fn update_working_state(
+        &mut self,
+        state: &GameWorkingState,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.write(|connection| write_working_state(connection, state, height))
+    }
+
+    fn record_house_ledger_entry(
+        &mut self,
+        entry: &HouseLedgerEntry,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.write(|connection| write_house_ledger_entry(connection, entry, height))
+    }
+
+    fn house_ledger(&self) -> crate::Result<Vec<(HouseLedgerEntry, u32)>> {
+        let rows = self.read(|connection| {
+            let mut statement = connection
+                .prepare_cached("SELECT entry, height FROM house_ledger ORDER BY id")?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })?;
+        rows.into_iter()
+            .map(|(entry, height)| Ok((from_json(&entry)?, height)))
+            .collect()
+    }
+
+    fn record_strap_supply_change(
+        &mut self,
+        change: &StrapSupplyChange,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.write(|connection| write_strap_supply_change(connection, change, height))
+    }
+
+    fn strap_supply_changes(&self) -> crate::Result<Vec<(StrapSupplyChange, u32)>> {
+        let rows = self.read(|connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT strap_level, strap_kind, strap_modifier, kind, amount, height
+                 FROM strap_supply_changes ORDER BY id",
+            )?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, u8>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, SqlU64>(4)?.0,
+                        row.get::<_, u32>(5)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })?;
+        rows.into_iter()
+            .map(|(level, strap_kind, modifier, kind, amount, height)| {
+                let change = StrapSupplyChange {
+                    strap: strap_from_columns(level, strap_kind, modifier)?,
+                    kind: from_variant_name(kind)?,
+                    amount,
+                };
+                Ok((change, height))
+            })
+            .collect()
+    }
+
+    fn record_block_timestamp(
+        &mut self,
+        height: u32,
+        timestamp: u64,
+    ) -> crate::Result<()> {
+        self.write(|connection| write_block_timestamp(connection, height, timestamp))
+    }
+
+    fn block_timestamp_at_or_before(
+        &self,
+        height: u32,
+    ) -> crate::Result<Option<(u32, u64)>> {
+        self.read(|connection| {
+            let row = connection
+                .query_row(
+                    "SELECT height, timestamp FROM block_timestamps
+                     WHERE height <= ?1 ORDER BY height DESC LIMIT 1",
+                    params![height],
+                    |row| Ok((row.get(0)?, row.get::<_, SqlU64>(1)?.0)),
+                )
+                .optional()?;
+            Ok(row)
+        })
+    }
+
+    fn roll_back_snapshots(&mut self, to_height: u32) -> crate::Result<()> {
+        let Some(first_orphaned) = to_height.checked_add(1) else {
+            return Ok(());
+        };
+        // Historical snapshots are keyed by game id rather than height, so callers
+        // invalidate them with `remove_historical_snapshots_from` or `roll_back`.
+        self.write(|connection| {
+            ensure_not_compacted(connection, to_height)?;
+            delete_from_height(connection, first_orphaned)
+        })
+    }
+
+    fn roll_back(&mut self, to_height: u32) -> crate::Result<()> {
+        self.write(|connection| {
+            ensure_not_compacted(connection, to_height)?;
+            if let Some(first_orphaned) = to_height.checked_add(1) {
+                delete_from_height(connection, first_orphaned)?;
+            }
+            let current_game = latest_overview(connection)?
+                .map(|(snapshot, _)| snapshot.game_id)
+                .unwrap_or_default();
+            remove_games_from(connection, current_game)
+        })
+    }
+
+    fn prune_from(&mut self, from_height: u32) -> crate::Result<()> {
+        if let Some(rollback_to) = from_height.checked_sub(1) {
+            return self.roll_back(rollback_to);
+        }
+        // Starting over from genesis, so nothing is compacted any more
+        self.write(|connection| {
+            connection.execute("DELETE FROM compaction", [])?;
+            delete_from_height(connection, from_height)?;
+            remove_games_from(connection, 0)
+        })
+    }
+
+    fn historical_snapshots(&self, game_id: u32) -> crate::Result<HistoricalSnapshot> {
+        self.read(|connection| {
+            let snapshot = connection
+                .query_row(
+                    "SELECT snapshot FROM games WHERE game_id = ?1",
+                    params![game_id],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?;
+            let snapshot = snapshot.ok_or_else(|| {
+                NotFound(format!("No historical snapshot found for game {game_id}"))
+            })?;
+            with_account_updates(connection, from_json(&snapshot)?)
+        })
+    }
+
+    fn write_historical_snapshot(
+        &mut self,
+        game_id: u32,
+        snapshot: &HistoricalSnapshot,
+    ) -> crate::Result<()> {
+        self.write(|connection| write_historical(connection, game_id, snapshot))
+    }
+
+    fn update_historical_account(
+        &mut self,
+        game_id: u32,
+        account: &Identity,
+        account_snapshot: &AccountSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.write(|connection| {
+            write_historical_account(
+                connection,
+                game_id,
+                account,
+                account_snapshot,
+                height,
+            )
+        })
+    }
+
+    fn historical_snapshot_range(
+        &self,
+        from: Option<u32>,
+        limit: usize,
+        order: HistoryOrder,
+    ) -> crate::Result<Vec<HistoricalSnapshot>> {
+        let (bound, direction) = match order {
+            HistoryOrder::Ascending => (">=", "ASC"),
+            HistoryOrder::Descending => ("<=", "DESC"),
+        };
+        let filter = match from {
+            Some(_) => format!("WHERE game_id {bound} ?1"),
+            None => "WHERE ?1 IS NULL".to_string(),
+        };
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        self.read(|connection| {
+            let mut statement = connection.prepare_cached(&format!(
+                "SELECT snapshot FROM games {filter} ORDER BY game_id {direction} LIMIT ?2"
+            ))?;
+            let rows = statement
+                .query_map(params![from, limit], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows.iter()
+                .map(|snapshot| with_account_updates(connection, from_json(snapshot)?))
+                .collect()
+        })
+    }
+
+    fn remove_historical_snapshots_from(&mut self, game_id: u32) -> crate::Result<()> {
+        self.write(|connection| remove_games_from(connection, game_id))
+    }
+
+    fn commit_block(&mut self, changes: &BlockChanges) -> crate::Result<()> {
+        self.write(|connection| {
+            for (game_id, snapshot) in &changes.historical {
+                write_historical(connection, *game_id, snapshot)?;
+            }
+            for (game_id, account, snapshot, height) in &changes.historical_accounts {
+                write_historical_account(
+                    connection, *game_id, account, snapshot, *height,
+                )?;
+            }
+            for (account, game_id, snapshot, height) in &changes.accounts {
+                write_account(connection, account, *game_id, snapshot, *height)?;
+            }
+            for (player, game_id, stats, height) in &changes.player_stats {
+                write_player_stats(connection, player, *game_id, stats, *height)?;
+            }
+            if let Some((state, height)) = &changes.working_state {
+                write_working_state(connection, state, *height)?;
+            }
+            for (entry, height) in &changes.house_ledger {
+                write_house_ledger_entry(connection, entry, *height)?;
+            }
+            for (change, height) in &changes.strap_supply {
+                write_strap_supply_change(connection, change, *height)?;
+            }
+            for (height, timestamp) in &changes.block_timestamps {
+                write_block_timestamp(connection, *height, *timestamp)?;
+            }
+            if let Some((snapshot, height)) = &changes.overview {
+                write_overview(connection, snapshot, *height)?;
+            }
+            Ok(())
+        })
+        .context("commit block changes")
+    }
+}
+
+impl MetadataStorage for SqliteMetadataStorage {
+    fn strap_asset_id(&self, strap_id: &AssetId) -> crate::Result<Option<Strap>> {
+        let row = self.connections.read(|connection| {
+            let row = connection
+                .query_row(
+                    "SELECT strap_level, strap_kind, strap_modifier FROM straps
+                     WHERE asset_id = ?1",
+                    params![asset_id_key(strap_id)],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()?;
+            Ok(row)
+        })?;
+        row.map(|(level, kind, modifier)| strap_from_columns(level, kind, modifier))
+            .transpose()
+    }
+
+    fn record_new_asset_id(
+        &mut self,
+        strap_id: &AssetId,
+        strap: &Strap,
+    ) -> crate::Result<()> {
+        let (level, kind, modifier) = strap_columns(strap)?;
+        self.connections.write(|connection| {
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO straps
+                        (asset_id, strap_level, strap_kind, strap_modifier)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![asset_id_key(strap_id), level, kind, modifier],
+                )
+                .context("persist strap metadata")?;
+            Ok(())
+        })
+    }
+
+    fn all_known_strap_asset_ids(&self) -> crate::Result<Vec<AssetId>> {
+        Ok(self
+            .all_known_straps()?
+            .into_iter()
+            .map(|(asset_id, _)| asset_id)
+            .collect())
+    }
+
+    fn all_known_straps(&self) -> crate::Result<Vec<(AssetId, Strap)>> {
+        let rows = self.connections.read(|connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT asset_id, strap_level, strap_kind, strap_modifier FROM straps
+                 ORDER BY asset_id",
+            )?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })?;
+        rows.into_iter()
+            .map(|(asset_id, level, kind, modifier)| {
+                let asset_id = AssetId::from_str(&asset_id)
+                    .map_err(|e| anyhow!("invalid strap asset id {asset_id}: {e}"))?;
+                Ok((asset_id, strap_from_columns(level, kind, modifier)?))
+            })
+            .collect()
+    }
+}
+
+fn identity_key(identity: &Identity) -> String {
+    match identity {
+        Identity::Address(address) => format!("address:{address:#x}"),
+        Identity::ContractId(contract_id) => format!("contract:{contract_id:#x}"),
+    }
+}
+
+fn asset_id_key(asset_id: &AssetId) -> String {
+    format!("{asset_id:#x}")
+}
+
+fn strap_columns(strap: &Strap) -> crate::Result<(u8, String, String)> {
+    Ok((
+        strap.level,
+        variant_name(&strap.kind)?,
+        variant_name(&strap.modifier)?,
+    ))
+}
+
+fn strap_from_columns(level: u8, kind: String, modifier: String) -> crate::Result<Strap> {
+    Ok(Strap::new(
+        level,
+        from_variant_name(kind)?,
+        from_variant_name(modifier)?,
+    ))
+}
+
+// Unit enum variants by their serde name, e.g. `Roll::Six` as `Six`
+fn variant_name<T: Serialize>(value: &T) -> crate::Result<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(name) => Ok(name),
+        other => Err(anyhow!("expected a unit variant, got {other}")),
+    }
+}
+
+fn from_variant_name<T: DeserializeOwned>(name: String) -> crate::Result<T> {
+    serde_json::from_value(serde_json::Value::String(name))
+        .context("deserialize sqlite enum column")
+}
+
+fn to_json<T: Serialize>(value: &T) -> crate::Result<String> {
+    serde_json::to_string(value).context("serialize sqlite record")
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> crate::Result<T> {
+    serde_json::from_str(json).context("deserialize sqlite record")
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+    use crate::{
+        events::{
+            Modifier,
+            Roll,
+            StrapKind,
+        },
+        snapshot::{
+            AccountBetPlacement,
+            StrapSupplyChangeKind,
+        },
+    };
+    use fuels::types::{
+        Address,
+        ContractId,
+    };
+    use tempdir::TempDir;
+
+    fn storage() -> (SqliteSnapshotStorage, SqliteMetadataStorage) {
+        SqliteSnapshotStorage::open_in_memory().unwrap()
+    }
+
+    mod conformance {
+        crate::snapshot_storage_conformance_tests!(persistent super::storage);
+    }
+
+    fn overview(game_id: u32) -> OverviewSnapshot {
+        OverviewSnapshot {
+            game_id,
+            ..OverviewSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn commit_block__round_trips_and_fills_relational_tables() {
+        // given
+        let (mut storage, _) = storage();
+        let player = Identity::Address(Address::from([1u8; 32]));
+        let hat = Strap::new(1, StrapKind::Hat, Modifier::Lucky);
+        let mut account = AccountSnapshot {
+            total_chip_bet: 30,
+            claimed_rewards: Some((50, vec![(hat.clone(), 1)])),
+            ..AccountSnapshot::default()
+        };
+        account.per_roll_bets[4].bets = vec![
+            AccountBetPlacement {
+                bet_roll_index: 0,
+                amount: 30,
+                kind: AccountBetKind::Chip,
+            },
+            AccountBetPlacement {
+                bet_roll_index: 1,
+                amount: 1,
+                kind: AccountBetKind::Strap(hat.clone()),
+            },
+        ];
+        let historical = HistoricalSnapshot::new(1, vec![Roll::Six, Roll::Seven], vec![]);
+        let stats = PlayerGameStats {
+            chips_wagered: 30,
+            chips_won: 50,
+            hit: true,
+            highest_strap_level: 1,
+        };
+        let supply = StrapSupplyChange {
+            strap: hat.clone(),
+            kind: StrapSupplyChangeKind::Minted,
+            amount: 1,
+        };
+        let ledger_entry = HouseLedgerEntry::Fund {
+            amount: 500,
+            funder: player,
+        };
+        let changes = BlockChanges {
+            overview: Some((overview(2), 10)),
+            accounts: vec![(player, 1, account.clone(), 10)],
+            player_stats: vec![(player, 1, stats.clone(), 10)],
+            working_state: Some((GameWorkingState::default(), 10)),
+            house_ledger: vec![(ledger_entry.clone(), 10)],
+            strap_supply: vec![(supply.clone(), 10)],
+            block_timestamps: [(10, 1_000)].into(),
+            historical: [(1, historical.clone())].into(),
+            historical_accounts: Vec::new(),
+        };
+
+        // when
+        storage.commit_block(&changes).unwrap();
+
+        // then
+        assert_eq!(storage.latest_snapshot().unwrap(), (overview(2), 10));
+        assert_eq!(
+            storage.account_snapshot_at(&player, 1).unwrap(),
+            Some((account, 10))
+        );
+        assert_eq!(storage.player_stats_at(&player, 1).unwrap(), Some(stats));
+        assert_eq!(storage.house_ledger().unwrap(), vec![(ledger_entry, 10)]);
+        assert_eq!(storage.strap_supply_changes().unwrap(), vec![(supply, 10)]);
+        assert_eq!(
+            storage.block_timestamp_at_or_before(15).unwrap(),
+            Some((10, 1_000))
+        );
+        assert_eq!(storage.historical_snapshots(1).unwrap(), historical);
+
+        let connection = storage.connections.writer.lock().unwrap();
+        let bets: Vec<(String, String, u64, Option<String>)> = connection
+            .prepare(
+                "SELECT identity, roll, amount, strap_kind FROM bets ORDER BY amount",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let identity = identity_key(&player);
+        let expected_bets = vec![
+            (
+                identity.clone(),
+                "Six".to_string(),
+                1,
+                Some("Hat".to_string()),
+            ),
+            (identity.clone(), "Six".to_string(), 30, None),
+        ];
+        assert_eq!(bets, expected_bets);
+        let claimed_chips: u64 = connection
+            .query_row("SELECT chips FROM claims WHERE game_id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(claimed_chips, 50);
+        let rolls: Vec<String> = connection
+            .prepare("SELECT roll FROM rolls WHERE game_id = 1 ORDER BY roll_index")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(rolls, vec!["Six", "Seven"]);
+    }
+
+    #[test]
+    fn commit_block__u64_max_amounts__round_trip() {
+        // given
+        let (mut storage, _) = storage();
+        let player = Identity::Address(Address::from([1u8; 32]));
+        let mut account = AccountSnapshot {
+            total_chip_bet: u64::MAX,
+            total_chip_won: u64::MAX,
+            claimed_rewards: Some((u64::MAX, Vec::new())),
+            ..AccountSnapshot::default()
+        };
+        account.per_roll_bets[0].bets = vec![AccountBetPlacement {
+            bet_roll_index: 0,
+            amount: u64::MAX,
+            kind: AccountBetKind::Chip,
+        }];
+        let snapshot = OverviewSnapshot {
+            pot_size: u64::MAX,
+            chips_owed: u64::MAX,
+            total_chip_bets: u64::MAX,
+            ..OverviewSnapshot::default()
+        };
+        let stats = PlayerGameStats {
+            chips_wagered: u64::MAX,
+            chips_won: u64::MAX,
+            ..PlayerGameStats::default()
+        };
+        let supply = StrapSupplyChange {
+            strap: Strap::new(1, StrapKind::Hat, Modifier::Lucky),
+            kind: StrapSupplyChangeKind::Minted,
+            amount: u64::MAX,
+        };
+        let ledger_entry = HouseLedgerEntry::Fund {
+            amount: u64::MAX,
+            funder: player,
+        };
+        let changes = BlockChanges {
+            overview: Some((snapshot.clone(), 10)),
+            accounts: vec![(player, 0, account.clone(), 10)],
+            player_stats: vec![(player, 0, stats.clone(), 10)],
+            house_ledger: vec![(ledger_entry, 10)],
+            strap_supply: vec![(supply.clone(), 10)],
+            block_timestamps: [(10, u64::MAX)].into(),
+            ..BlockChanges::default()
+        };
+
+        // when
+        storage.commit_block(&changes).unwrap();
+
+        // then
+        assert_eq!(storage.latest_snapshot().unwrap(), (snapshot, 10));
+        assert_eq!(
+            storage.latest_account_snapshot(&player).unwrap(),
+            Some((account, 10))
+        );
+        assert_eq!(storage.player_stats_at(&player, 0).unwrap(), Some(stats));
+        assert_eq!(
+            storage.player_stats_in_games(0, 0).unwrap()[0].2.chips_won,
+            u64::MAX
+        );
+        assert_eq!(storage.house_ledger().unwrap(), changes.house_ledger);
+        assert_eq!(storage.strap_supply_changes().unwrap(), vec![(supply, 10)]);
+        assert_eq!(
+            storage.block_timestamp_at_or_before(10).unwrap(),
+            Some((10, u64::MAX))
+        );
+    }
+
+    #[test]
+    fn open__reads_do_not_wait_for_open_write_transaction() {
+        // given
+        let temp_dir = TempDir::new("sqlite_snapshot_storage").unwrap();
+        let (mut storage, metadata) =
+            SqliteSnapshotStorage::open(temp_dir.path().join(DEFAULT_DATABASE_FILE))
+                .unwrap();
+        storage.update_snapshot(&overview(1), 10).unwrap();
+        let mut writer = storage.connections.writer.lock().unwrap();
+        let pending = writer.transaction().unwrap();
+        write_overview(&pending, &overview(2), 20).unwrap();
+
+        // when
+        let latest = storage.latest_snapshot().unwrap();
+
+        // then
+        assert_eq!(latest, (overview(1), 10));
+        assert!(metadata.all_known_straps().unwrap().is_empty());
+    }
+
+    #[test]
+    fn roll_back_snapshots__removes_newer_entries_and_restores_latest() {
+        // given
+        let (mut storage, _) = storage();
+        let player = Identity::ContractId(ContractId::from([2u8; 32]));
+        for height in [5, 10] {
+            storage.update_snapshot(&overview(height), height).unwrap();
+            storage
+                .update_account_snapshot(&player, 0, &AccountSnapshot::default(), height)
+                .unwrap();
+            storage
+                .record_block_timestamp(height, u64::from(height))
+                .unwrap();
+        }
+
+        // when
+        storage.roll_back_snapshots(7).unwrap();
+
+        // then
+        assert_eq!(storage.latest_snapshot().unwrap(), (overview(5), 5));
+        assert_eq!(
+            storage.block_timestamp_at_or_before(10).unwrap(),
+            Some((5, 5))
+        );
+        assert_eq!(storage.latest_account_snapshot(&player).unwrap(), None);
+        assert_eq!(
+            storage.account_snapshot_at(&player, 0).unwrap(),
+            Some((AccountSnapshot::default(), 5))
+        );
+        storage.prune_from(0).unwrap();
+        assert!(storage.latest_snapshot().is_err());
+    }
+
+    #[test]
+    fn roll_back__restores_the_account_bets_and_stats_of_the_target_height() {
+        // given
+        let (mut storage, _) = storage();
+        let player = Identity::Address(Address::from([1u8; 32]));
+        let bet = |amount| AccountBetPlacement {
+            bet_roll_index: 0,
+            amount,
+            kind: AccountBetKind::Chip,
+        };
+        let mut first = AccountSnapshot {
+            total_chip_bet: 10,
+            ..AccountSnapshot::default()
+        };
+        first.per_roll_bets[0].bets = vec![bet(10)];
+        let mut second = first.clone();
+        second.total_chip_bet = 30;
+        second.per_roll_bets[0].bets.push(bet(20));
+        let stats = |chips_wagered| PlayerGameStats {
+            chips_wagered,
+            ..PlayerGameStats::default()
+        };
+        for (height, account, chips_wagered) in [(5, &first, 10), (8, &second, 30)] {
+            storage.update_snapshot(&overview(1), height).unwrap();
+            storage
+                .update_account_snapshot(&player, 1, account, height)
+                .unwrap();
+            storage
+                .update_player_stats(&player, 1, &stats(chips_wagered), height)
+                .unwrap();
+        }
+
+        // when
+        let stats_before = storage.player_stats_in_games(1, 1).unwrap();
+        storage.roll_back(6).unwrap();
+
+        // then
+        assert_eq!(stats_before, vec![(player, 1, stats(30))]);
+        assert_eq!(
+            storage.latest_account_snapshot(&player).unwrap(),
+            Some((first, 5))
+        );
+        assert_eq!(
+            storage.player_stats_at(&player, 1).unwrap(),
+            Some(stats(10))
+        );
+        assert_eq!(
+            storage.player_stats_in_games(1, 1).unwrap(),
+            vec![(player, 1, stats(10))]
+        );
+        let connection = storage.connections.writer.lock().unwrap();
+        let bet_amounts: Vec<u64> = connection
+            .prepare("SELECT amount FROM bets ORDER BY amount")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(bet_amounts, vec![10]);
+    }
+
+    #[test]
+    fn historical_snapshot_range__pages_in_either_order() {
+        // given
+        let (mut storage, _) = storage();
+        for game_id in 1..=4 {
+            let snapshot = HistoricalSnapshot::new(game_id, vec![], vec![]);
+            storage
+                .write_historical_snapshot(game_id, &snapshot)
+                .unwrap();
+        }
+        let game_ids = |snapshots: Vec<HistoricalSnapshot>| {
+            snapshots
+                .into_iter()
+                .map(|snapshot| snapshot.game_id)
+                .collect::<Vec<_>>()
+        };
+
+        // when
+        let newest = storage
+            .historical_snapshot_range(None, 2, HistoryOrder::Descending)
+            .unwrap();
+        let from_two = storage
+            .historical_snapshot_range(Some(2), 10, HistoryOrder::Ascending)
+            .unwrap();
+        storage.remove_historical_snapshots_from(3).unwrap();
+        let remaining = storage
+            .historical_snapshot_range(Some(4), 10, HistoryOrder::Descending)
+            .unwrap();
+
+        // then
+        assert_eq!(game_ids(newest), vec![4, 3]);
+        assert_eq!(game_ids(from_two), vec![2, 3, 4]);
+        assert_eq!(game_ids(remaining), vec![2, 1]);
+        assert!(storage.historical_snapshots(3).is_err());
+    }
+
+    #[test]
+    fn metadata__records_and_lists_known_straps() {
+        // given
+        let (_, mut metadata) = storage();
+        let asset_id_a = AssetId::from([1u8; 32]);
+        let asset_id_b = AssetId::from([2u8; 32]);
+        let strap_a = Strap::new(1, StrapKind::Shirt, Modifier::Nothing);
+        let strap_b = Strap::new(3, StrapKind::Ring, Modifier::Holy);
+
+        // when
+        metadata.record_new_asset_id(&asset_id_b, &strap_b).unwrap();
+        metadata.record_new_asset_id(&asset_id_a, &strap_a).unwrap();
+
+        // then
+        assert_eq!(
+            metadata.strap_asset_id(&asset_id_b).unwrap(),
+            Some(strap_b.clone())
+        );
+        assert_eq!(
+            metadata.all_known_straps().unwrap(),
+            vec![(asset_id_a, strap_a), (asset_id_b, strap_b)]
+        );
+    }
+}
@@ -0,0 +1,753 @@
+// Behaviour every `SnapshotStorage` and `MetadataStorage` backend must share, and what
+// `PersistentSnapshotStorage` adds on top.
+//
+// Each check takes a fresh pair of storages and panics on the first difference from the
+// expected behaviour. `snapshot_storage_conformance_tests!` turns all of them into tests.
+#![allow(non_snake_case)]
+use crate::{
+    app::snapshot_storage::{
+        BlockChanges,
+        HistoryOrder,
+        MetadataStorage,
+        NotFound,
+        PersistentSnapshotStorage,
+        RetentionPolicy,
+        SnapshotStorage,
+    },
+    events::{
+        Modifier,
+        Roll,
+        Strap,
+        StrapKind,
+    },
+    snapshot::{
+        AccountSnapshot,
+        GameWorkingState,
+        HistoricalSnapshot,
+        HouseLedgerEntry,
+        OverviewSnapshot,
+        PlayerGameStats,
+        StrapSupplyChange,
+        StrapSupplyChangeKind,
+    },
+};
+use fuel_core::types::fuel_tx::AssetId;
+use fuels::types::{
+    Address,
+    ContractId,
+    Identity,
+};
+
+/// Generate a `#[test]` per conformance check, each run against the storages returned
+/// by calling `$new_storage`, e.g.
+/// `snapshot_storage_conformance_tests!(|| (MySnapshots::new(), MyMetadata::new()));`
+/// Prefix it with `persistent` to also check `PersistentSnapshotStorage` methods.
+#[macro_export]
+macro_rules! snapshot_storage_conformance_tests {
+    (persistent $new_storage:expr) => {
+        $crate::snapshot_storage_conformance_tests!($new_storage);
+        $crate::snapshot_storage_conformance_tests!(
+            @checks $new_storage;
+            compact_overviews__keeps_the_window_and_refuses_deeper_rollbacks,
+            compact_overviews__smallest_window__lets_a_restart_prune_the_latest_block,
+        );
+    };
+    ($new_storage:expr) => {
+        $crate::snapshot_storage_conformance_tests!(
+            @checks $new_storage;
+            latest_snapshot__follows_the_last_written_height,
+            latest_account_snapshot__follows_the_latest_game_of_any_clone,
+            block_timestamp_at_or_before__includes_the_given_height,
+            roll_back_snapshots__keeps_the_target_height_and_drops_the_rest,
+            roll_back_snapshots__below_every_snapshot__leaves_nothing_latest,
+            account_snapshot_at__is_scoped_per_game_and_identity,
+            player_stats_in_games__covers_an_inclusive_range,
+            write_historical_snapshot__overwrites_and_pages,
+            remove_historical_snapshots_from__drops_that_game_and_later,
+            update_historical_account__is_rolled_back_with_its_block,
+            roll_back__reopens_the_game_current_at_the_target_height,
+            prune_from__drops_that_height_and_later,
+            commit_block__matches_individual_writes,
+            metadata__enumerates_every_recorded_strap,
+        );
+    };
+    (@checks $new_storage:expr; $($check:ident),* $(,)?) => {
+        $(
+            #[test]
+            #[allow(non_snake_case)]
+            fn $check() {
+                $crate::app::storage_conformance::$check($new_storage());
+            }
+        )*
+    };
+}
+
+fn overview(game_id: u32, height: u32) -> OverviewSnapshot {
+    OverviewSnapshot {
+        game_id,
+        current_block_height: height,
+        ..OverviewSnapshot::default()
+    }
+}
+
+fn account(total_chip_bet: u64) -> AccountSnapshot {
+    AccountSnapshot {
+        total_chip_bet,
+        ..AccountSnapshot::default()
+    }
+}
+
+fn stats(chips_wagered: u64) -> PlayerGameStats {
+    PlayerGameStats {
+        chips_wagered,
+        ..PlayerGameStats::default()
+    }
+}
+
+fn address(byte: u8) -> Identity {
+    Identity::Address(Address::from([byte; 32]))
+}
+
+fn historical(game_id: u32, rolls: Vec<Roll>) -> HistoricalSnapshot {
+    HistoricalSnapshot::new(game_id, rolls, Vec::new())
+}
+
+fn game_ids(snapshots: Vec<HistoricalSnapshot>) -> Vec<u32> {
+    snapshots
+        .into_iter()
+        .map(|snapshot| snapshot.game_id)
+        .collect()
+}
+
+fn is_not_found<T>(result: crate::Result<T>) -> bool {
+    result.is_err_and(|e| e.downcast_ref::<NotFound>().is_some())
+}
+
+// Writes one of everything that is recorded per height
+fn write_block<S: SnapshotStorage>(snapshots: &mut S, game_id: u32, height: u32) {
+    let player = address(1);
+    let amount = u64::from(height);
+    snapshots
+        .update_account_snapshot(&player, game_id, &account(amount), height)
+        .unwrap();
+    snapshots
+        .update_player_stats(&player, game_id, &stats(amount), height)
+        .unwrap();
+    snapshots
+        .update_working_state(&GameWorkingState::default(), height)
+        .unwrap();
+    snapshots
+        .record_house_ledger_entry(
+            &HouseLedgerEntry::Fund {
+                amount,
+                funder: player,
+            },
+            height,
+        )
+        .unwrap();
+    snapshots
+        .record_strap_supply_change(
+            &StrapSupplyChange {
+                strap: Strap::new(1, StrapKind::Hat, Modifier::Nothing),
+                kind: StrapSupplyChangeKind::Minted,
+                amount,
+            },
+            height,
+        )
+        .unwrap();
+    snapshots
+        .record_block_timestamp(height, u64::from(height) * 10)
+        .unwrap();
+    snapshots
+        .update_snapshot(&overview(game_id, height), height)
+        .unwrap();
+}
+
+pub fn latest_snapshot__follows_the_last_written_height<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    assert!(is_not_found(snapshots.latest_snapshot()));
+    assert_eq!(snapshots.latest_working_state().unwrap(), None);
+
+    snapshots.update_snapshot(&overview(1, 5), 5).unwrap();
+    snapshots.update_snapshot(&overview(1, 8), 8).unwrap();
+    let state = GameWorkingState {
+        modifier_triggered: vec![Modifier::Lucky],
+        ..GameWorkingState::default()
+    };
+    snapshots
+        .update_working_state(&GameWorkingState::default(), 5)
+        .unwrap();
+    snapshots.update_working_state(&state, 8).unwrap();
+
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(1, 8), 8));
+    assert_eq!(snapshots.latest_working_state().unwrap(), Some((state, 8)));
+}
+
+pub fn latest_account_snapshot__follows_the_latest_game_of_any_clone<
+    S: SnapshotStorage + Clone,
+    M: MetadataStorage,
+>(
+    (snapshots, _): (S, M),
+) {
+    let player = address(1);
+    let mut writer = snapshots.clone();
+
+    writer.update_snapshot(&overview(2, 5), 5).unwrap();
+    writer
+        .update_account_snapshot(&player, 2, &account(7), 5)
+        .unwrap();
+
+    assert_eq!(
+        snapshots.latest_account_snapshot(&player).unwrap(),
+        Some((account(7), 5))
+    );
+}
+
+pub fn block_timestamp_at_or_before__includes_the_given_height<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    snapshots.record_block_timestamp(10, 100).unwrap();
+    snapshots.record_block_timestamp(20, 200).unwrap();
+
+    assert_eq!(snapshots.block_timestamp_at_or_before(9).unwrap(), None);
+    assert_eq!(
+        snapshots.block_timestamp_at_or_before(10).unwrap(),
+        Some((10, 100))
+    );
+    assert_eq!(
+        snapshots.block_timestamp_at_or_before(19).unwrap(),
+        Some((10, 100))
+    );
+    assert_eq!(
+        snapshots.block_timestamp_at_or_before(u32::MAX).unwrap(),
+        Some((20, 200))
+    );
+}
+
+pub fn roll_back_snapshots__keeps_the_target_height_and_drops_the_rest<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    let player = address(1);
+    write_block(&mut snapshots, 1, 5);
+    write_block(&mut snapshots, 1, 10);
+    write_block(&mut snapshots, 2, 15);
+    snapshots
+        .write_historical_snapshot(1, &historical(1, vec![Roll::Seven]))
+        .unwrap();
+
+    snapshots.roll_back_snapshots(10).unwrap();
+
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(1, 10), 10));
+    assert_eq!(
+        snapshots.latest_account_snapshot(&player).unwrap(),
+        Some((account(10), 10))
+    );
+    assert_eq!(snapshots.account_snapshot_at(&player, 2).unwrap(), None);
+    assert_eq!(snapshots.player_stats_at(&player, 2).unwrap(), None);
+    assert_eq!(
+        snapshots.player_stats_at(&player, 1).unwrap(),
+        Some(stats(10))
+    );
+    assert_eq!(
+        snapshots
+            .latest_working_state()
+            .unwrap()
+            .map(|(_, height)| height),
+        Some(10)
+    );
+    let ledger_heights: Vec<_> = snapshots
+        .house_ledger()
+        .unwrap()
+        .into_iter()
+        .map(|(_, height)| height)
+        .collect();
+    assert_eq!(ledger_heights, vec![5, 10]);
+    let supply_heights: Vec<_> = snapshots
+        .strap_supply_changes()
+        .unwrap()
+        .into_iter()
+        .map(|(_, height)| height)
+        .collect();
+    assert_eq!(supply_heights, vec![5, 10]);
+    assert_eq!(
+        snapshots.block_timestamp_at_or_before(15).unwrap(),
+        Some((10, 100))
+    );
+    // Finished games are keyed by game id, not height, so rollbacks leave them be
+    assert_eq!(
+        snapshots.historical_snapshots(1).unwrap(),
+        historical(1, vec![Roll::Seven])
+    );
+
+    snapshots.roll_back_snapshots(7).unwrap();
+
+    // The account and stats changed at 10, so their writes at 5 are current again
+    assert_eq!(
+        snapshots.latest_account_snapshot(&player).unwrap(),
+        Some((account(5), 5))
+    );
+    assert_eq!(
+        snapshots.player_stats_at(&player, 1).unwrap(),
+        Some(stats(5))
+    );
+    assert_eq!(
+        snapshots.player_stats_in_games(1, 2).unwrap(),
+        vec![(player, 1, stats(5))]
+    );
+}
+
+pub fn roll_back_snapshots__below_every_snapshot__leaves_nothing_latest<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    let player = address(1);
+    write_block(&mut snapshots, 1, 5);
+
+    snapshots.roll_back_snapshots(4).unwrap();
+
+    assert!(is_not_found(snapshots.latest_snapshot()));
+    assert_eq!(snapshots.latest_account_snapshot(&player).unwrap(), None);
+    assert_eq!(snapshots.account_snapshot_at(&player, 1).unwrap(), None);
+    assert_eq!(snapshots.latest_working_state().unwrap(), None);
+    assert!(snapshots.house_ledger().unwrap().is_empty());
+    assert!(snapshots.strap_supply_changes().unwrap().is_empty());
+    assert_eq!(snapshots.block_timestamp_at_or_before(5).unwrap(), None);
+}
+
+pub fn account_snapshot_at__is_scoped_per_game_and_identity<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    let player = address(1);
+    // Same bytes, different kind of identity
+    let contract = Identity::ContractId(ContractId::from([1; 32]));
+    snapshots.update_snapshot(&overview(1, 5), 5).unwrap();
+    snapshots
+        .update_account_snapshot(&player, 1, &account(10), 5)
+        .unwrap();
+    snapshots
+        .update_account_snapshot(&player, 1, &account(15), 6)
+        .unwrap();
+    assert_eq!(
+        snapshots.latest_account_snapshot(&player).unwrap(),
+        Some((account(15), 6))
+    );
+
+    snapshots.update_snapshot(&overview(2, 7), 7).unwrap();
+    snapshots
+        .update_account_snapshot(&player, 2, &account(20), 7)
+        .unwrap();
+
+    assert_eq!(
+        snapshots.latest_account_snapshot(&player).unwrap(),
+        Some((account(20), 7))
+    );
+    assert_eq!(
+        snapshots.account_snapshot_at(&player, 1).unwrap(),
+        Some((account(15), 6))
+    );
+    assert_eq!(snapshots.account_snapshot_at(&player, 3).unwrap(), None);
+    assert_eq!(snapshots.latest_account_snapshot(&contract).unwrap(), None);
+    assert_eq!(snapshots.account_snapshot_at(&contract, 1).unwrap(), None);
+}
+
+pub fn player_stats_in_games__covers_an_inclusive_range<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    for game_id in 1..=4 {
+        for player in [address(1), address(2)] {
+            snapshots
+                .update_player_stats(
+                    &player,
+                    game_id,
+                    &stats(u64::from(game_id)),
+                    game_id,
+                )
+                .unwrap();
+        }
+    }
+    snapshots
+        .update_player_stats(&address(1), 2, &stats(50), 9)
+        .unwrap();
+
+    let mut in_range = snapshots.player_stats_in_games(2, 3).unwrap();
+    in_range.sort_by_key(|(player, game_id, _)| (*game_id, format!("{player:?}")));
+
+    let expected = vec![
+        (address(1), 2, stats(50)),
+        (address(2), 2, stats(2)),
+        (address(1), 3, stats(3)),
+        (address(2), 3, stats(3)),
+    ];
+    assert_eq!(in_range, expected);
+    assert!(snapshots.player_stats_in_games(3, 2).unwrap().is_empty());
+    assert!(snapshots.player_stats_in_games(5, 9).unwrap().is_empty());
+}
+
+pub fn write_historical_snapshot__overwrites_and_pages<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    assert!(is_not_found(snapshots.historical_snapshots(1)));
+    for game_id in 1..=5 {
+        snapshots
+            .write_historical_snapshot(game_id, &historical(game_id, Vec::new()))
+            .unwrap();
+    }
+    let replacement = historical(3, vec![Roll::Two, Roll::Seven]);
+
+    snapshots
+        .write_historical_snapshot(3, &replacement)
+        .unwrap();
+
+    assert_eq!(snapshots.historical_snapshots(3).unwrap(), replacement);
+    let page = |from, limit, order| {
+        game_ids(
+            snapshots
+                .historical_snapshot_range(from, limit, order)
+                .unwrap(),
+        )
+    };
+    assert_eq!(page(None, 2, HistoryOrder::Descending), vec![5, 4]);
+    assert_eq!(page(None, 2, HistoryOrder::Ascending), vec![1, 2]);
+    assert_eq!(page(Some(3), 10, HistoryOrder::Descending), vec![3, 2, 1]);
+    assert_eq!(page(Some(3), 2, HistoryOrder::Ascending), vec![3, 4]);
+    assert_eq!(page(Some(9), 2, HistoryOrder::Ascending), Vec::<u32>::new());
+    assert_eq!(page(None, 0, HistoryOrder::Descending), Vec::<u32>::new());
+}
+
+pub fn remove_historical_snapshots_from__drops_that_game_and_later<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    for game_id in 1..=4 {
+        snapshots
+            .write_historical_snapshot(game_id, &historical(game_id, Vec::new()))
+            .unwrap();
+    }
+
+    snapshots.remove_historical_snapshots_from(3).unwrap();
+
+    assert!(is_not_found(snapshots.historical_snapshots(3)));
+    assert!(is_not_found(snapshots.historical_snapshots(4)));
+    let remaining = snapshots
+        .historical_snapshot_range(None, 10, HistoryOrder::Ascending)
+        .unwrap();
+    assert_eq!(game_ids(remaining), vec![1, 2]);
+}
+
+pub fn update_historical_account__is_rolled_back_with_its_block<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    let (player, newcomer) = (address(1), address(2));
+    let mut finished = historical(1, Vec::new());
+    finished.upsert_account(player, account(5));
+    snapshots.write_historical_snapshot(1, &finished).unwrap();
+    snapshots
+        .write_historical_snapshot(2, &historical(2, Vec::new()))
+        .unwrap();
+    snapshots
+        .update_historical_account(1, &player, &account(6), 20)
+        .unwrap();
+    snapshots
+        .update_historical_account(1, &newcomer, &account(7), 21)
+        .unwrap();
+    snapshots
+        .update_historical_account(2, &player, &account(8), 21)
+        .unwrap();
+
+    let mut updated = finished.clone();
+    updated.upsert_account(player, account(6));
+    updated.upsert_account(newcomer, account(7));
+    assert_eq!(snapshots.historical_snapshots(1).unwrap(), updated);
+    let paged = snapshots
+        .historical_snapshot_range(None, 1, HistoryOrder::Ascending)
+        .unwrap();
+    assert_eq!(paged, vec![updated]);
+
+    snapshots.roll_back_snapshots(20).unwrap();
+
+    let mut kept = finished.clone();
+    kept.upsert_account(player, account(6));
+    assert_eq!(snapshots.historical_snapshots(1).unwrap(), kept);
+    assert_eq!(
+        snapshots.historical_snapshots(2).unwrap(),
+        historical(2, Vec::new())
+    );
+
+    snapshots.roll_back_snapshots(19).unwrap();
+    snapshots.remove_historical_snapshots_from(2).unwrap();
+    snapshots
+        .update_historical_account(2, &player, &account(9), 22)
+        .unwrap();
+    snapshots
+        .write_historical_snapshot(2, &historical(2, Vec::new()))
+        .unwrap();
+
+    assert_eq!(snapshots.historical_snapshots(1).unwrap(), finished);
+    let mut replayed = historical(2, Vec::new());
+    replayed.upsert_account(player, account(9));
+    assert_eq!(snapshots.historical_snapshots(2).unwrap(), replayed);
+}
+
+pub fn roll_back__reopens_the_game_current_at_the_target_height<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    snapshots.update_snapshot(&overview(2, 10), 10).unwrap();
+    snapshots.update_snapshot(&overview(3, 20), 20).unwrap();
+    for game_id in 1..=3 {
+        snapshots
+            .write_historical_snapshot(game_id, &historical(game_id, Vec::new()))
+            .unwrap();
+    }
+    snapshots
+        .update_historical_account(1, &address(1), &account(1), 20)
+        .unwrap();
+
+    snapshots.roll_back(15).unwrap();
+
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(2, 10), 10));
+    let remaining = snapshots
+        .historical_snapshot_range(None, 10, HistoryOrder::Ascending)
+        .unwrap();
+    assert_eq!(remaining, vec![historical(1, Vec::new())]);
+
+    snapshots.roll_back(5).unwrap();
+
+    assert!(snapshots.latest_snapshot().is_err());
+    assert!(is_not_found(snapshots.historical_snapshots(1)));
+}
+
+pub fn commit_block__matches_individual_writes<S: SnapshotStorage, M: MetadataStorage>(
+    (mut snapshots, _): (S, M),
+) {
+    let player = address(1);
+    let changes = BlockChanges {
+        overview: Some((overview(2, 12), 12)),
+        accounts: vec![(player, 2, account(7), 12)],
+        player_stats: vec![(player, 1, stats(7), 12)],
+        working_state: Some((GameWorkingState::default(), 12)),
+        house_ledger: vec![(
+            HouseLedgerEntry::Withdrawal {
+                amount: 3,
+                to: player,
+            },
+            12,
+        )],
+        strap_supply: vec![(
+            StrapSupplyChange {
+                strap: Strap::new(2, StrapKind::Coat, Modifier::Holy),
+                kind: StrapSupplyChangeKind::Escrowed,
+                amount: 1,
+            },
+            12,
+        )],
+        block_timestamps: [(11, 110), (12, 120)].into(),
+        historical: [(1, historical(1, vec![Roll::Four]))].into(),
+        historical_accounts: vec![(1, player, account(8), 12)],
+    };
+
+    snapshots.commit_block(&changes).unwrap();
+
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(2, 12), 12));
+    assert_eq!(
+        snapshots.latest_account_snapshot(&player).unwrap(),
+        Some((account(7), 12))
+    );
+    assert_eq!(
+        snapshots.player_stats_at(&player, 1).unwrap(),
+        Some(stats(7))
+    );
+    assert_eq!(
+        snapshots.latest_working_state().unwrap(),
+        Some((GameWorkingState::default(), 12))
+    );
+    assert_eq!(snapshots.house_ledger().unwrap(), changes.house_ledger);
+    assert_eq!(
+        snapshots.strap_supply_changes().unwrap(),
+        changes.strap_supply
+    );
+    assert_eq!(
+        snapshots.block_timestamp_at_or_before(11).unwrap(),
+        Some((11, 110))
+    );
+    let mut finished = historical(1, vec![Roll::Four]);
+    finished.upsert_account(player, account(8));
+    assert_eq!(snapshots.historical_snapshots(1).unwrap(), finished);
+}
+
+pub fn metadata__enumerates_every_recorded_strap<
+    S: SnapshotStorage,
+    M: MetadataStorage,
+>(
+    (_, mut metadata): (S, M),
+) {
+    let asset_id_a = AssetId::from([1u8; 32]);
+    let asset_id_b = AssetId::from([2u8; 32]);
+    let strap_a = Strap::new(1, StrapKind::Shirt, Modifier::Nothing);
+    let strap_b = Strap::new(4, StrapKind::Ring, Modifier::Burnt);
+    let relabelled = Strap::new(2, StrapKind::Shirt, Modifier::Lucky);
+    assert!(metadata.all_known_straps().unwrap().is_empty());
+
+    metadata.record_new_asset_id(&asset_id_b, &strap_b).unwrap();
+    metadata.record_new_asset_id(&asset_id_a, &strap_a).unwrap();
+    metadata
+        .record_new_asset_id(&asset_id_a, &relabelled)
+        .unwrap();
+
+    assert_eq!(
+        metadata.strap_asset_id(&asset_id_a).unwrap(),
+        Some(relabelled.clone())
+    );
+    assert_eq!(
+        metadata.strap_asset_id(&AssetId::from([3u8; 32])).unwrap(),
+        None
+    );
+    let mut asset_ids = metadata.all_known_strap_asset_ids().unwrap();
+    asset_ids.sort();
+    assert_eq!(asset_ids, vec![asset_id_a, asset_id_b]);
+    let mut straps = metadata.all_known_straps().unwrap();
+    straps.sort_by_key(|(asset_id, _)| *asset_id);
+    assert_eq!(
+        straps,
+        vec![(asset_id_a, relabelled), (asset_id_b, strap_b)]
+    );
+}
+
+pub fn prune_from__drops_that_height_and_later<S: SnapshotStorage, M: MetadataStorage>(
+    (mut snapshots, _): (S, M),
+) {
+    let player = address(1);
+    write_block(&mut snapshots, 1, 5);
+    write_block(&mut snapshots, 1, 8);
+    snapshots.update_snapshot(&overview(2, 9), 9).unwrap();
+    for game_id in [0, 1] {
+        snapshots
+            .write_historical_snapshot(game_id, &historical(game_id, Vec::new()))
+            .unwrap();
+    }
+
+    snapshots.prune_from(8).unwrap();
+
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(1, 5), 5));
+    // Game 1 ended in a pruned block, so it is open again
+    assert!(is_not_found(snapshots.historical_snapshots(1)));
+    assert_eq!(
+        snapshots.latest_account_snapshot(&player).unwrap(),
+        Some((account(5), 5))
+    );
+    assert_eq!(
+        snapshots.player_stats_at(&player, 1).unwrap(),
+        Some(stats(5))
+    );
+    assert_eq!(
+        snapshots.latest_working_state().unwrap(),
+        Some((GameWorkingState::default(), 5))
+    );
+    assert_eq!(snapshots.house_ledger().unwrap().len(), 1);
+    assert_eq!(
+        snapshots.block_timestamp_at_or_before(8).unwrap(),
+        Some((5, 50))
+    );
+
+    snapshots.prune_from(0).unwrap();
+
+    assert!(is_not_found(snapshots.latest_snapshot()));
+    assert_eq!(snapshots.account_snapshot_at(&player, 1).unwrap(), None);
+    assert_eq!(snapshots.player_stats_at(&player, 1).unwrap(), None);
+    assert_eq!(snapshots.latest_working_state().unwrap(), None);
+    assert!(snapshots.house_ledger().unwrap().is_empty());
+    assert!(snapshots.strap_supply_changes().unwrap().is_empty());
+    assert_eq!(snapshots.block_timestamp_at_or_before(8).unwrap(), None);
+    assert!(is_not_found(snapshots.historical_snapshots(0)));
+}
+
+pub fn compact_overviews__keeps_the_window_and_refuses_deeper_rollbacks<
+    S: PersistentSnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    for height in 1..=20 {
+        let game_id = if height <= 12 { 2 } else { 3 };
+        snapshots
+            .update_snapshot(&overview(game_id, height), height)
+            .unwrap();
+    }
+    snapshots
+        .write_historical_snapshot(2, &historical(2, Vec::new()))
+        .unwrap();
+    let policy = RetentionPolicy { reorg_window: 5 };
+
+    // Below height 15 only 12 and 14, the last of games 2 and 3 there, are kept
+    assert_eq!(snapshots.compact_overviews(policy).unwrap(), 12);
+    assert_eq!(snapshots.compact_overviews(policy).unwrap(), 0);
+
+    assert!(snapshots.roll_back(13).is_err());
+    assert!(snapshots.prune_from(14).is_err());
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(3, 20), 20));
+    assert_eq!(
+        snapshots.historical_snapshots(2).unwrap(),
+        historical(2, Vec::new())
+    );
+
+    snapshots.roll_back(15).unwrap();
+
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(3, 15), 15));
+    assert_eq!(
+        snapshots.historical_snapshots(2).unwrap(),
+        historical(2, Vec::new())
+    );
+
+    // Starting over from genesis forgets the compacted range
+    snapshots.prune_from(0).unwrap();
+    snapshots.roll_back(0).unwrap();
+}
+
+pub fn compact_overviews__smallest_window__lets_a_restart_prune_the_latest_block<
+    S: PersistentSnapshotStorage,
+    M: MetadataStorage,
+>(
+    (mut snapshots, _): (S, M),
+) {
+    for height in 1..=10 {
+        snapshots
+            .update_snapshot(&overview(1, height), height)
+            .unwrap();
+    }
+    snapshots
+        .compact_overviews(RetentionPolicy { reorg_window: 1 })
+        .unwrap();
+
+    // A restart resumes from the latest height, indexing that block again
+    snapshots.prune_from(10).unwrap();
+
+    assert_eq!(snapshots.latest_snapshot().unwrap(), (overview(1, 9), 9));
+}
@@ -0,0 +1,359 @@
+// Strap supply per kind, level and modifier, built from recorded `StrapSupplyChange`s.
+use crate::{
+    events::{
+        Modifier,
+        Roll,
+        Strap,
+        StrapKind,
+    },
+    snapshot::{
+        AccountBetKind,
+        AccountSnapshot,
+        ActiveModifier,
+        StrapSupplyChange,
+        StrapSupplyChangeKind,
+    },
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrapSupply {
+    pub minted: u64,
+    pub escrowed: u64,
+    pub upgrades_consumed: u64,
+    pub upgrades_produced: u64,
+    /// Minted straps not escrowed in bets, i.e. held by players
+    pub circulating: u64,
+}
+
+impl StrapSupply {
+    fn apply(&mut self, change: &StrapSupplyChange) {
+        let counter = match change.kind {
+            StrapSupplyChangeKind::Minted => &mut self.minted,
+            StrapSupplyChangeKind::Escrowed => &mut self.escrowed,
+            StrapSupplyChangeKind::UpgradeConsumed => &mut self.upgrades_consumed,
+            StrapSupplyChangeKind::UpgradeProduced => &mut self.upgrades_produced,
+        };
+        *counter = counter.saturating_add(change.amount);
+        self.circulating = self.minted.saturating_sub(self.escrowed);
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.minted = self.minted.saturating_add(other.minted);
+        self.escrowed = self.escrowed.saturating_add(other.escrowed);
+        self.upgrades_consumed = self
+            .upgrades_consumed
+            .saturating_add(other.upgrades_consumed);
+        self.upgrades_produced = self
+            .upgrades_produced
+            .saturating_add(other.upgrades_produced);
+        self.circulating = self.minted.saturating_sub(self.escrowed);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrapSupplyEntry {
+    pub strap: Strap,
+    pub supply: StrapSupply,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrapLevelSupply {
+    pub level: u8,
+    /// Summed over every modifier
+    pub supply: StrapSupply,
+}
+
+/// Level histogram for one `StrapKind`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrapKindSupply {
+    pub kind: StrapKind,
+    pub total: StrapSupply,
+    /// Lowest level first
+    pub levels: Vec<StrapLevelSupply>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrapSupplyReport {
+    /// Ordered by kind, level and modifier
+    pub straps: Vec<StrapSupplyEntry>,
+    pub kinds: Vec<StrapKindSupply>,
+}
+
+/// Sum supply changes per strap, and per level of each kind
+pub fn strap_supply<'a>(
+    changes: impl IntoIterator<Item = &'a StrapSupplyChange>,
+) -> StrapSupplyReport {
+    let mut straps: BTreeMap<(StrapKind, u8, Modifier), StrapSupply> = BTreeMap::new();
+    for change in changes {
+        let Strap {
+            level,
+            kind,
+            modifier,
+        } = change.strap;
+        straps
+            .entry((kind, level, modifier))
+            .or_default()
+            .apply(change);
+    }
+
+    let mut kinds: BTreeMap<StrapKind, BTreeMap<u8, StrapSupply>> = BTreeMap::new();
+    for ((kind, level, _), supply) in &straps {
+        kinds
+            .entry(*kind)
+            .or_default()
+            .entry(*level)
+            .or_default()
+            .add(supply);
+    }
+
+    StrapSupplyReport {
+        straps: straps
+            .into_iter()
+            .map(|((kind, level, modifier), supply)| StrapSupplyEntry {
+                strap: Strap {
+                    level,
+                    kind,
+                    modifier,
+                },
+                supply,
+            })
+            .collect(),
+        kinds: kinds
+            .into_iter()
+            .map(|(kind, levels)| {
+                let mut total = StrapSupply::default();
+                let levels = levels
+                    .into_iter()
+                    .map(|(level, supply)| {
+                        total.add(&supply);
+                        StrapLevelSupply { level, supply }
+                    })
+                    .collect();
+                StrapKindSupply {
+                    kind,
+                    total,
+                    levels,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Strap bets a claim upgrades, as the strap given up, the strap paid out and the amount.
+/// Mirrors the contract's `claim_rewards`: a strap bet wins if its roll comes up at or
+/// after the roll it was placed on, and pays out the next level of the same kind.
+pub fn strap_bet_upgrades(
+    account: &AccountSnapshot,
+    rolls: &[Roll],
+    modifiers: &[ActiveModifier],
+    enabled_modifiers: &[(Roll, Modifier)],
+) -> Vec<(Strap, Strap, u64)> {
+    let mut upgrades = Vec::new();
+    for roll_bets in &account.per_roll_bets {
+        for bet in &roll_bets.bets {
+            let AccountBetKind::Strap(strap) = &bet.kind else {
+                continue;
+            };
+            let won = rolls
+                .iter()
+                .skip(bet.bet_roll_index as usize)
+                .any(|roll| *roll == roll_bets.roll);
+            if !won {
+                continue;
+            }
+            let modifier = modifier_for_roll(
+                modifiers,
+                roll_bets.roll,
+                bet.bet_roll_index,
+                enabled_modifiers,
+            )
+            .unwrap_or(strap.modifier);
+            let upgraded = Strap {
+                level: strap.level.saturating_add(1),
+                kind: strap.kind,
+                modifier,
+            };
+            upgrades.push((strap.clone(), upgraded, bet.amount));
+        }
+    }
+    upgrades
+}
+
+// The first modifier active on `roll` by the time the bet was placed applies, but only
+// if the claimer enabled it
+fn modifier_for_roll(
+    modifiers: &[ActiveModifier],
+    roll: Roll,
+    bet_roll_index: u32,
+    enabled_modifiers: &[(Roll, Modifier)],
+) -> Option<Modifier> {
+    let active = modifiers.iter().find(|active| {
+        active.modifier_roll == roll && active.roll_index <= bet_roll_index
+    })?;
+    enabled_modifiers
+        .contains(&(roll, active.modifier))
+        .then_some(active.modifier)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+    use super::*;
+    use crate::snapshot::AccountBetPlacement;
+
+    fn strap(level: u8, kind: StrapKind, modifier: Modifier) -> Strap {
+        Strap {
+            level,
+            kind,
+            modifier,
+        }
+    }
+
+    fn change(
+        strap: Strap,
+        kind: StrapSupplyChangeKind,
+        amount: u64,
+    ) -> StrapSupplyChange {
+        StrapSupplyChange {
+            strap,
+            kind,
+            amount,
+        }
+    }
+
+    fn strap_bet(strap: Strap, bet_roll_index: u32, amount: u64) -> AccountBetPlacement {
+        AccountBetPlacement {
+            bet_roll_index,
+            amount,
+            kind: AccountBetKind::Strap(strap),
+        }
+    }
+
+    fn account_with_bets(bets: Vec<(Roll, AccountBetPlacement)>) -> AccountSnapshot {
+        let mut account = AccountSnapshot::default();
+        for (roll, bet) in bets {
+            account
+                .per_roll_bets
+                .iter_mut()
+                .find(|roll_bets| roll_bets.roll == roll)
+                .unwrap()
+                .bets
+                .push(bet);
+        }
+        account
+    }
+
+    #[test]
+    fn strap_supply__sums_changes_per_strap_and_per_kind_level() {
+        // given
+        let plain_shirt = strap(1, StrapKind::Shirt, Modifier::Nothing);
+        let lucky_shirt = strap(1, StrapKind::Shirt, Modifier::Lucky);
+        let shirt_2 = strap(2, StrapKind::Shirt, Modifier::Nothing);
+        let hat = strap(1, StrapKind::Hat, Modifier::Nothing);
+        let changes = vec![
+            change(plain_shirt.clone(), StrapSupplyChangeKind::Minted, 5),
+            change(lucky_shirt.clone(), StrapSupplyChangeKind::Minted, 2),
+            change(plain_shirt.clone(), StrapSupplyChangeKind::Escrowed, 3),
+            change(
+                plain_shirt.clone(),
+                StrapSupplyChangeKind::UpgradeConsumed,
+                1,
+            ),
+            change(shirt_2.clone(), StrapSupplyChangeKind::UpgradeProduced, 1),
+            change(shirt_2.clone(), StrapSupplyChangeKind::Minted, 1),
+            change(hat.clone(), StrapSupplyChangeKind::Minted, 4),
+        ];
+
+        // when
+        let report = strap_supply(&changes);
+
+        // then
+        let straps: Vec<_> = report
+            .straps
+            .iter()
+            .map(|entry| (entry.strap.clone(), entry.supply.circulating))
+            .collect();
+        assert_eq!(
+            straps,
+            vec![(plain_shirt, 2), (lucky_shirt, 2), (shirt_2, 1), (hat, 4),]
+        );
+        let shirts = &report.kinds[0];
+        assert_eq!(shirts.kind, StrapKind::Shirt);
+        let expected_levels = vec![
+            StrapLevelSupply {
+                level: 1,
+                supply: StrapSupply {
+                    minted: 7,
+                    escrowed: 3,
+                    upgrades_consumed: 1,
+                    upgrades_produced: 0,
+                    circulating: 4,
+                },
+            },
+            StrapLevelSupply {
+                level: 2,
+                supply: StrapSupply {
+                    minted: 1,
+                    escrowed: 0,
+                    upgrades_consumed: 0,
+                    upgrades_produced: 1,
+                    circulating: 1,
+                },
+            },
+        ];
+        assert_eq!(shirts.levels, expected_levels);
+        assert_eq!(shirts.total.minted, 8);
+        assert_eq!(report.kinds[1].kind, StrapKind::Hat);
+    }
+
+    #[test]
+    fn strap_bet_upgrades__pays_next_level_for_bets_hit_after_placement() {
+        // given
+        let shirt = strap(1, StrapKind::Shirt, Modifier::Nothing);
+        let hat = strap(3, StrapKind::Hat, Modifier::Burnt);
+        let account = account_with_bets(vec![
+            (Roll::Six, strap_bet(shirt.clone(), 1, 2)),
+            // Four only came up before this bet was placed
+            (Roll::Four, strap_bet(hat.clone(), 1, 1)),
+            (Roll::Eight, strap_bet(hat.clone(), 0, 1)),
+        ]);
+        let rolls = [Roll::Four, Roll::Six, Roll::Eight, Roll::Seven];
+        let modifiers = [
+            ActiveModifier::new(0, Modifier::Lucky, Roll::Six),
+            ActiveModifier::new(2, Modifier::Holy, Roll::Eight),
+        ];
+        let enabled = [(Roll::Six, Modifier::Lucky), (Roll::Eight, Modifier::Holy)];
+
+        // when
+        let upgrades = strap_bet_upgrades(&account, &rolls, &modifiers, &enabled);
+
+        // then
+        let expected = vec![
+            (shirt, strap(2, StrapKind::Shirt, Modifier::Lucky), 2),
+            // Holy was activated after the bet was placed
+            (hat.clone(), strap(4, StrapKind::Hat, Modifier::Burnt), 1),
+        ];
+        assert_eq!(upgrades, expected);
+    }
+
+    #[test]
+    fn strap_bet_upgrades__keeps_modifier_if_active_modifier_not_enabled() {
+        // given
+        let shirt = strap(1, StrapKind::Shirt, Modifier::Burnt);
+        let account =
+            account_with_bets(vec![(Roll::Six, strap_bet(shirt.clone(), 0, 1))]);
+        let modifiers = [ActiveModifier::new(0, Modifier::Lucky, Roll::Six)];
+
+        // when
+        let upgrades = strap_bet_upgrades(&account, &[Roll::Six], &modifiers, &[]);
+
+        // then
+        let expected = vec![(shirt, strap(2, StrapKind::Shirt, Modifier::Burnt), 1)];
+        assert_eq!(upgrades, expected);
+    }
+}
@@ -2,7 +2,10 @@
 
 use super::*;
 use crate::{
-    app::query_api::Query,
+    app::query_api::{
+        Query,
+        QueryError,
+    },
     events::{
         ContractEvent,
         Event,
@@ -19,10 +22,15 @@ use crate::{
     app::{
         in_memory_metadata_storage::InMemoryMetadataStorage,
         in_memory_snapshot_storage::InMemorySnapshotStorage,
+        sled_storage::{
+            SledMetadataStorage,
+            SledSnapshotStorage,
+        },
     },
     events::{
         ClaimRewardsEvent,
         FundPotEvent,
+        InsufficientHouseWithdrawalEvent,
         Modifier,
         ModifierTriggeredEvent,
         NewGameEvent,
@@ -32,39 +40,61 @@ use crate::{
         Roll,
         Strap,
         StrapKind,
+        WithdrawHousePotEvent,
     },
 };
 use std::future::pending;
+use tempdir::TempDir;
 use tokio::sync::{
     mpsc,
     oneshot,
 };
 
 pub struct FakeEventSource {
-    recv: mpsc::Receiver<(Vec<Event>, u32)>,
+    recv: mpsc::Receiver<EventBatch>,
 }
 
 impl FakeEventSource {
-    pub fn new_with_sender() -> (Self, mpsc::Sender<(Vec<Event>, u32)>) {
+    pub fn new_with_sender() -> (Self, FakeEventSender) {
         let (send, recv) = mpsc::channel(10);
         let recv = FakeEventSource { recv };
-        (recv, send)
+        (recv, FakeEventSender { send })
     }
 }
 
 impl EventSource for FakeEventSource {
-    async fn next_event_batch(&mut self) -> Result<Option<(Vec<Event>, u32)>> {
+    async fn next_event_batch(&mut self) -> Result<Option<EventBatch>> {
         match self.recv.recv().await {
-            Some((events, height)) => Ok(Some((events, height))),
+            Some(batch) => Ok(Some(batch)),
             None => Err(anyhow::anyhow!("No more events")),
         }
     }
 }
 
+pub struct FakeEventSender {
+    send: mpsc::Sender<EventBatch>,
+}
+
+impl FakeEventSender {
+    pub async fn send(
+        &self,
+        (events, height): (Vec<Event>, u32),
+    ) -> std::result::Result<(), mpsc::error::SendError<EventBatch>> {
+        self.send.send(EventBatch::Events { events, height }).await
+    }
+
+    pub async fn rollback(
+        &self,
+        to_height: u32,
+    ) -> std::result::Result<(), mpsc::error::SendError<EventBatch>> {
+        self.send.send(EventBatch::Rollback { to_height }).await
+    }
+}
+
 pub struct PendingEventSource;
 
 impl EventSource for PendingEventSource {
-    async fn next_event_batch(&mut self) -> Result<Option<(Vec<Event>, u32)>> {
+    async fn next_event_batch(&mut self) -> Result<Option<EventBatch>> {
         pending().await
     }
 }
@@ -248,6 +278,95 @@ async fn run__empty_event_batch__updates_block_height() {
     assert_eq!(expected, actual);
 }
 
+#[tokio::test]
+async fn run__blockchain_event__records_timestamp_without_writing_overview() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let existing_snapshot = OverviewSnapshot {
+        next_roll_height: Some(120),
+        ..OverviewSnapshot::default()
+    };
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(existing_snapshot.clone(), 100);
+    let snapshot_copy = snapshot_storage.snapshot();
+    let timestamps_copy = snapshot_storage.block_timestamps();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+    event_sender
+        .send((vec![Event::block_event(100, 1_000)], 100))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // when
+    event_sender
+        .send((vec![Event::block_event(110, 1_020)], 110))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let (actual, height) = snapshot_copy.lock().unwrap().clone().unwrap();
+    assert_eq!(height, 100);
+    assert_eq!(actual, existing_snapshot);
+    let timestamps = timestamps_copy.lock().unwrap().clone();
+    assert_eq!(
+        timestamps.into_iter().collect::<Vec<_>>(),
+        vec![(100, 1_000), (110, 1_020)]
+    );
+}
+
+#[tokio::test]
+async fn run__roll_event__estimates_time_of_the_next_roll() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let existing_snapshot = OverviewSnapshot {
+        game_id: 1,
+        next_roll_height: Some(105),
+        ..OverviewSnapshot::default()
+    };
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(existing_snapshot, 100);
+    let snapshot_copy = snapshot_storage.snapshot();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+    for (height, timestamp) in [(100, 1_000), (110, 1_020)] {
+        event_sender
+            .send((vec![Event::block_event(height, timestamp)], height))
+            .await
+            .unwrap();
+        app.run(pending()).await.unwrap();
+    }
+
+    // when
+    let roll = Event::roll_event(1, 1, Roll::Five, 0, 0, 100, 120);
+    event_sender.send((vec![roll], 110)).await.unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let (actual, height) = snapshot_copy.lock().unwrap().clone().unwrap();
+    assert_eq!(height, 110);
+    assert_eq!(actual.next_roll_height, Some(120));
+    // blocks averaged two seconds apart, and the next roll is ten blocks away
+    assert_eq!(actual.next_roll_time, Some(1_040));
+}
+
 #[tokio::test]
 async fn run__new_game_event__resets_overview_snapshot() {
     // given
@@ -394,41 +513,650 @@ async fn run__multiple_new_game_events__persists_historical_snapshots() {
         .expect("update snapshot");
 
     event_sender
-        .send((vec![Event::ContractEvent(second_new_game)], 230))
+        .send((vec![Event::ContractEvent(second_new_game)], 230))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    let historical = historical_copy.lock().unwrap();
+    let stored_first = historical
+        .get(&first_snapshot.game_id)
+        .expect("missing first historical snapshot");
+    assert_eq!(stored_first.rolls, first_snapshot.rolls);
+    assert!(stored_first.modifiers.is_empty());
+    assert_eq!(stored_first.strap_rewards, first_snapshot.rewards);
+
+    let stored_second = historical
+        .get(&2)
+        .expect("missing second historical snapshot");
+    assert_eq!(stored_second.rolls, mid_snapshot.rolls);
+    assert!(stored_second.modifiers.is_empty());
+    assert_eq!(stored_second.strap_rewards, mid_snapshot.rewards);
+}
+
+#[tokio::test]
+async fn run__new_game_event__captures_triggered_modifiers_in_history() {
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+
+    let existing_snapshot = OverviewSnapshot {
+        game_id: 5,
+        modifier_shop: vec![
+            (Roll::Three, Roll::Four, Modifier::Holy, false, false, 30).into(),
+        ],
+        rolls: vec![Roll::Two],
+        ..OverviewSnapshot::default()
+    };
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(existing_snapshot.clone(), 300);
+    let historical_copy = snapshot_storage.historical_snapshots();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+    app.roll_frequency = Some(10);
+
+    let expected_index = 321;
+    let modifier_event = ContractEvent::ModifierTriggered(ModifierTriggeredEvent {
+        game_id: existing_snapshot.game_id,
+        roll_index: expected_index,
+        trigger_roll: Roll::Two,
+        modifier_roll: Roll::Four,
+        modifier: Modifier::Holy,
+    });
+
+    event_sender
+        .send((vec![Event::ContractEvent(modifier_event)], 305))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    let new_game_event = ContractEvent::NewGame(NewGameEvent {
+        game_id: 6,
+        new_straps: vec![],
+        new_modifiers: vec![],
+        pot_size: 0,
+        chips_owed_total: 0,
+    });
+
+    event_sender
+        .send((vec![Event::ContractEvent(new_game_event)], 310))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    let historical = historical_copy.lock().unwrap();
+    let stored = historical
+        .get(&existing_snapshot.game_id)
+        .expect("expected historical snapshot");
+    assert_eq!(stored.rolls, existing_snapshot.rolls);
+    assert_eq!(stored.strap_rewards, existing_snapshot.rewards);
+    let active_modifier = ActiveModifier::new(expected_index, Modifier::Holy, Roll::Four);
+    assert_eq!(stored.modifiers, vec![active_modifier]);
+
+    // ensure modifiers for new game start fresh
+    let next_entry = historical.get(&6);
+    assert!(next_entry.is_none());
+}
+
+#[tokio::test]
+async fn run__restart_mid_game__keeps_triggered_modifiers_in_history() {
+    // given
+    let existing_snapshot = OverviewSnapshot {
+        game_id: 5,
+        modifier_shop: vec![
+            (Roll::Three, Roll::Four, Modifier::Holy, false, false, 30).into(),
+        ],
+        rolls: vec![Roll::Two],
+        ..OverviewSnapshot::default()
+    };
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(existing_snapshot.clone(), 300);
+    let historical_copy = snapshot_storage.historical_snapshots();
+
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let mut app = App::new(
+        event_source,
+        PendingQueryApi,
+        snapshot_storage.clone(),
+        InMemoryMetadataStorage::default(),
+        zero_contract_id(),
+    );
+    let modifier_event = ContractEvent::ModifierTriggered(ModifierTriggeredEvent {
+        game_id: existing_snapshot.game_id,
+        roll_index: 1,
+        trigger_roll: Roll::Two,
+        modifier_roll: Roll::Four,
+        modifier: Modifier::Holy,
+    });
+    event_sender
+        .send((vec![Event::ContractEvent(modifier_event)], 305))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+    drop(app);
+
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let mut restarted_app = App::new(
+        event_source,
+        PendingQueryApi,
+        snapshot_storage,
+        InMemoryMetadataStorage::default(),
+        zero_contract_id(),
+    );
+
+    // when
+    let new_game_event = ContractEvent::NewGame(NewGameEvent {
+        game_id: 6,
+        new_straps: vec![],
+        new_modifiers: vec![],
+        pot_size: 0,
+        chips_owed_total: 0,
+    });
+    event_sender
+        .send((vec![Event::ContractEvent(new_game_event)], 310))
+        .await
+        .unwrap();
+    restarted_app.run(pending()).await.unwrap();
+
+    // then
+    let historical = historical_copy.lock().unwrap();
+    let stored = historical
+        .get(&existing_snapshot.game_id)
+        .expect("expected historical snapshot");
+    let active_modifier = ActiveModifier::new(1, Modifier::Holy, Roll::Four);
+    assert_eq!(stored.modifiers, vec![active_modifier]);
+    assert_eq!(restarted_app.working_state, GameWorkingState::default());
+}
+
+fn arb_block_batches() -> Vec<(Vec<Event>, u32)> {
+    let alice = Identity::Address(Address::from([5u8; 32]));
+    let bob = Identity::Address(Address::from([6u8; 32]));
+    let chip_bet = |player, roll, amount| {
+        Event::ContractEvent(ContractEvent::PlaceChipBet(PlaceChipBetEvent {
+            game_id: 1,
+            bet_roll_index: 0,
+            player,
+            roll,
+            amount,
+        }))
+    };
+    let fund_pot = Event::ContractEvent(ContractEvent::FundPot(FundPotEvent {
+        chips_amount: 1_000,
+        funder: bob,
+    }));
+    let claim = Event::ContractEvent(ContractEvent::ClaimRewards(ClaimRewardsEvent {
+        game_id: 1,
+        player: alice,
+        enabled_modifiers: vec![],
+        total_chips_winnings: 300,
+        total_strap_winnings: vec![],
+    }));
+    vec![
+        (
+            vec![arb_init_event(), Event::block_event(100, 1_700_000_000)],
+            100,
+        ),
+        (
+            vec![
+                Event::new_game_event(1, vec![], vec![], 500, 0),
+                Event::block_event(101, 1_700_000_001),
+            ],
+            101,
+        ),
+        (
+            vec![
+                chip_bet(alice, Roll::Six, 50),
+                chip_bet(bob, Roll::Eight, 20),
+                chip_bet(alice, Roll::Six, 10),
+                Event::block_event(105, 1_700_000_005),
+            ],
+            105,
+        ),
+        (
+            vec![
+                fund_pot,
+                Event::roll_event(1, 1, Roll::Six, 80, 300, 1_580, 120),
+                Event::block_event(110, 1_700_000_010),
+            ],
+            110,
+        ),
+        (
+            vec![
+                Event::new_game_event(2, vec![], vec![], 1_280, 300),
+                Event::block_event(120, 1_700_000_020),
+            ],
+            120,
+        ),
+        (vec![claim, Event::block_event(125, 1_700_000_025)], 125),
+    ]
+}
+
+fn sled_db(temp_dir: &TempDir) -> sled::Db {
+    sled::Config::default()
+        .path(temp_dir.path())
+        .open()
+        .expect("open sled db")
+}
+
+type IndexedState = (
+    (OverviewSnapshot, u32),
+    Vec<Option<(AccountSnapshot, u32)>>,
+    Option<HistoricalSnapshot>,
+    Vec<(HouseLedgerEntry, u32)>,
+    Option<(GameWorkingState, u32)>,
+);
+
+fn indexed_state(db: &sled::Db) -> IndexedState {
+    let snapshots = SledSnapshotStorage::new(db).unwrap();
+    let accounts = [[5u8; 32], [6u8; 32]]
+        .into_iter()
+        .map(|address| {
+            let identity = Identity::Address(Address::from(address));
+            snapshots.account_snapshot_at(&identity, 1).unwrap()
+        })
+        .collect();
+    (
+        snapshots.latest_snapshot().unwrap(),
+        accounts,
+        snapshots.historical_snapshots(1).ok(),
+        snapshots.house_ledger().unwrap(),
+        snapshots.latest_working_state().unwrap(),
+    )
+}
+
+// Resume the way `main` does: drop the last committed block and index again from there
+async fn index_from_last_commit(db: &sled::Db, batches: &[(Vec<Event>, u32)]) {
+    let mut snapshots = SledSnapshotStorage::new(db).unwrap();
+    let metadata = SledMetadataStorage::new(db).unwrap();
+    let resume_height = snapshots
+        .latest_snapshot()
+        .map(|(_, height)| height)
+        .unwrap_or_default();
+    snapshots.prune_from(resume_height).unwrap();
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let mut app = App::new(
+        event_source,
+        PendingQueryApi,
+        snapshots,
+        metadata,
+        zero_contract_id(),
+    );
+    for batch in batches
+        .iter()
+        .filter(|(_, height)| *height >= resume_height)
+    {
+        event_sender.send(batch.clone()).await.unwrap();
+        app.run(pending()).await.unwrap();
+    }
+}
+
+// Index every batch before `crash_at`, then stop halfway through that batch's events,
+// as if the process died
+async fn index_until_crash(
+    db: &sled::Db,
+    batches: &[(Vec<Event>, u32)],
+    crash_at: usize,
+) {
+    let snapshots = SledSnapshotStorage::new(db).unwrap();
+    let metadata = SledMetadataStorage::new(db).unwrap();
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let mut app = App::new(
+        event_source,
+        PendingQueryApi,
+        snapshots,
+        metadata,
+        zero_contract_id(),
+    );
+    for batch in &batches[..crash_at] {
+        event_sender.send(batch.clone()).await.unwrap();
+        app.run(pending()).await.unwrap();
+    }
+    let (events, height) = batches[crash_at].clone();
+    let applied = events.len().div_ceil(2);
+    for event in events.into_iter().take(applied) {
+        app.handle_event(event, height).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn run__crash_mid_block_then_restart__matches_uninterrupted_run() {
+    // given
+    let batches = arb_block_batches();
+    let uninterrupted_dir = TempDir::new("uninterrupted_run").unwrap();
+    let uninterrupted_db = sled_db(&uninterrupted_dir);
+    index_from_last_commit(&uninterrupted_db, &batches).await;
+    let expected = indexed_state(&uninterrupted_db);
+
+    for crash_at in 0..batches.len() {
+        let temp_dir = TempDir::new("crashed_run").unwrap();
+        let db = sled_db(&temp_dir);
+
+        // when
+        index_until_crash(&db, &batches, crash_at).await;
+
+        // then
+        let snapshots = SledSnapshotStorage::new(&db).unwrap();
+        let committed_height = snapshots.latest_snapshot().ok().map(|(_, h)| h);
+        let previous_height = crash_at.checked_sub(1).map(|i| batches[i].1);
+        assert_eq!(
+            committed_height, previous_height,
+            "crashed in batch {crash_at}"
+        );
+
+        index_from_last_commit(&db, &batches).await;
+        let actual = indexed_state(&db);
+        assert_eq!(actual, expected, "crashed in batch {crash_at}");
+    }
+}
+
+#[tokio::test]
+async fn run__new_game_event__captures_player_accounts_in_history() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::default(), 300);
+    let historical_copy = snapshot_storage.historical_snapshots();
+    let storage_clone = snapshot_storage.clone();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+
+    let player = Identity::Address(Address::from([4u8; 32]));
+    let chip_event = ContractEvent::PlaceChipBet(PlaceChipBetEvent {
+        game_id: 0,
+        bet_roll_index: 0,
+        player,
+        roll: Roll::Six,
+        amount: 150,
+    });
+    event_sender
+        .send((vec![Event::ContractEvent(chip_event)], 305))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // when
+    let new_game_event = ContractEvent::NewGame(NewGameEvent {
+        game_id: 1,
+        new_straps: vec![],
+        new_modifiers: vec![],
+        pot_size: 0,
+        chips_owed_total: 0,
+    });
+    event_sender
+        .send((vec![Event::ContractEvent(new_game_event)], 310))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let accounts = historical_copy
+        .lock()
+        .unwrap()
+        .get(&0)
+        .unwrap()
+        .accounts
+        .clone();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].identity, player);
+    assert_eq!(accounts[0].snapshot.total_chip_bet, 150);
+    assert_eq!(accounts[0].snapshot.claimed_rewards, None);
+
+    // and when the player claims for the finished game
+    let claim_event = ContractEvent::ClaimRewards(ClaimRewardsEvent {
+        game_id: 0,
+        player,
+        enabled_modifiers: vec![],
+        total_chips_winnings: 900,
+        total_strap_winnings: vec![],
+    });
+    event_sender
+        .send((vec![Event::ContractEvent(claim_event)], 315))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then the historical entry is updated in place
+    let accounts = SnapshotStorage::historical_snapshots(&storage_clone, 0)
+        .unwrap()
+        .accounts;
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].snapshot.total_chip_bet, 150);
+    assert_eq!(accounts[0].snapshot.total_chip_won, 900);
+    assert_eq!(
+        accounts[0].snapshot.claimed_rewards,
+        Some((900, Vec::new()))
+    );
+}
+
+#[tokio::test]
+async fn run__claim_for_finished_game_then_rollback__restores_historical_entry() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::default(), 300);
+    let storage_clone = snapshot_storage.clone();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+
+    let player = Identity::Address(Address::from([4u8; 32]));
+    let latecomer = Identity::Address(Address::from([5u8; 32]));
+    let chip_event = ContractEvent::PlaceChipBet(PlaceChipBetEvent {
+        game_id: 0,
+        bet_roll_index: 0,
+        player,
+        roll: Roll::Six,
+        amount: 150,
+    });
+    let new_game_event = ContractEvent::NewGame(NewGameEvent {
+        game_id: 1,
+        new_straps: vec![],
+        new_modifiers: vec![],
+        pot_size: 0,
+        chips_owed_total: 0,
+    });
+    event_sender
+        .send((vec![Event::ContractEvent(chip_event)], 305))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+    event_sender
+        .send((vec![Event::ContractEvent(new_game_event)], 310))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+    let claim = |player| {
+        Event::ContractEvent(ContractEvent::ClaimRewards(ClaimRewardsEvent {
+            game_id: 0,
+            player,
+            enabled_modifiers: vec![],
+            total_chips_winnings: 900,
+            total_strap_winnings: vec![],
+        }))
+    };
+    event_sender
+        .send((vec![claim(player), claim(latecomer)], 315))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+    let claimed = SnapshotStorage::historical_snapshots(&storage_clone, 0).unwrap();
+    assert_eq!(claimed.accounts.len(), 2);
+
+    // when
+    event_sender.rollback(312).await.unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let accounts = SnapshotStorage::historical_snapshots(&storage_clone, 0)
+        .unwrap()
+        .accounts;
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].identity, player);
+    assert_eq!(accounts[0].snapshot.total_chip_bet, 150);
+    assert_eq!(accounts[0].snapshot.total_chip_won, 0);
+    assert_eq!(accounts[0].snapshot.claimed_rewards, None);
+}
+
+#[tokio::test]
+async fn run__rollback__restores_overview_snapshot_at_height() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let existing_snapshot = OverviewSnapshot {
+        game_id: 1,
+        ..OverviewSnapshot::default()
+    };
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(existing_snapshot.clone(), 105);
+    let snapshot_copy = snapshot_storage.snapshot();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+
+    let first_roll = Event::roll_event(1, 1, Roll::Five, 0, 0, 100, 120);
+    let orphaned_roll = Event::roll_event(1, 2, Roll::Nine, 0, 0, 100, 130);
+    event_sender.send((vec![first_roll], 110)).await.unwrap();
+    app.run(pending()).await.unwrap();
+    event_sender.send((vec![orphaned_roll], 120)).await.unwrap();
+    app.run(pending()).await.unwrap();
+
+    // when
+    event_sender.rollback(115).await.unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let (actual, height) = snapshot_copy.lock().unwrap().clone().unwrap();
+    let mut expected = existing_snapshot;
+    expected.rolls = vec![Roll::Five];
+    expected.pot_size = 100;
+    expected.current_block_height = 110;
+    expected.next_roll_height = Some(120);
+    assert_eq!(height, 110);
+    assert_eq!(expected, actual);
+}
+
+#[tokio::test]
+async fn run__rollback_between_bets__keeps_the_earlier_bet_in_account_and_table() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::default(), 300);
+    let storage_clone = snapshot_storage.clone();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+
+    let player = Identity::Address(Address::from([6u8; 32]));
+    let bet = |roll, amount| {
+        Event::ContractEvent(ContractEvent::PlaceChipBet(PlaceChipBetEvent {
+            game_id: 0,
+            bet_roll_index: 0,
+            player,
+            roll,
+            amount,
+        }))
+    };
+    event_sender
+        .send((vec![bet(Roll::Six, 150)], 305))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+    event_sender
+        .send((vec![bet(Roll::Eight, 20)], 310))
         .await
         .unwrap();
     app.run(pending()).await.unwrap();
+    let table_bets = |storage: &InMemorySnapshotStorage| {
+        SnapshotStorage::latest_snapshot(storage)
+            .unwrap()
+            .0
+            .table_bets
+    };
+    let account_bets = |storage: &InMemorySnapshotStorage| {
+        let (account, _) = SnapshotStorage::latest_account_snapshot(storage, &player)
+            .unwrap()
+            .unwrap();
+        let bets = vec![crate::snapshot::TableAccountBets {
+            identity: player,
+            per_roll_bets: account.per_roll_bets,
+        }];
+        (account.total_chip_bet, bets)
+    };
 
-    let historical = historical_copy.lock().unwrap();
-    let stored_first = historical
-        .get(&first_snapshot.game_id)
-        .expect("missing first historical snapshot");
-    assert_eq!(stored_first.rolls, first_snapshot.rolls);
-    assert!(stored_first.modifiers.is_empty());
-    assert_eq!(stored_first.strap_rewards, first_snapshot.rewards);
+    // when
+    event_sender.rollback(307).await.unwrap();
+    app.run(pending()).await.unwrap();
+    let (total_after_rollback, bets_after_rollback) = account_bets(&storage_clone);
+    let table_after_rollback = table_bets(&storage_clone);
+    event_sender
+        .send((vec![bet(Roll::Eight, 40)], 311))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
 
-    let stored_second = historical
-        .get(&2)
-        .expect("missing second historical snapshot");
-    assert_eq!(stored_second.rolls, mid_snapshot.rolls);
-    assert!(stored_second.modifiers.is_empty());
-    assert_eq!(stored_second.strap_rewards, mid_snapshot.rewards);
+    // then
+    assert_eq!(total_after_rollback, 150);
+    assert_eq!(bets_after_rollback, table_after_rollback);
+    let (total, bets) = account_bets(&storage_clone);
+    assert_eq!(total, 190);
+    assert_eq!(bets, table_bets(&storage_clone));
+    let amounts: Vec<_> = bets[0]
+        .per_roll_bets
+        .iter()
+        .flat_map(|roll_bets| roll_bets.bets.iter().map(|bet| bet.amount))
+        .collect();
+    assert_eq!(amounts, vec![150, 40]);
 }
 
 #[tokio::test]
-async fn run__new_game_event__captures_triggered_modifiers_in_history() {
+async fn run__rollback_across_new_game__invalidates_historical_snapshot() {
+    // given
     let (event_source, event_sender) = FakeEventSource::new_with_sender();
-
     let existing_snapshot = OverviewSnapshot {
         game_id: 5,
+        rolls: vec![Roll::Two],
         modifier_shop: vec![
-            (Roll::Three, Roll::Four, Modifier::Holy, false, false, 30).into(),
+            (Roll::Two, Roll::Four, Modifier::Holy, false, false, 30).into(),
         ],
-        rolls: vec![Roll::Two],
         ..OverviewSnapshot::default()
     };
     let snapshot_storage =
         InMemorySnapshotStorage::new_with_snapshot(existing_snapshot.clone(), 300);
+    let snapshot_copy = snapshot_storage.snapshot();
     let historical_copy = snapshot_storage.historical_snapshots();
 
     let metadata_storage = InMemoryMetadataStorage::default();
@@ -440,49 +1168,62 @@ async fn run__new_game_event__captures_triggered_modifiers_in_history() {
         metadata_storage,
         zero_contract_id(),
     );
-    app.roll_frequency = Some(10);
 
-    let expected_index = 321;
-    let modifier_event = ContractEvent::ModifierTriggered(ModifierTriggeredEvent {
-        game_id: existing_snapshot.game_id,
-        roll_index: expected_index,
+    let kept_modifier = ContractEvent::ModifierTriggered(ModifierTriggeredEvent {
+        game_id: 5,
+        roll_index: 1,
         trigger_roll: Roll::Two,
         modifier_roll: Roll::Four,
         modifier: Modifier::Holy,
     });
-
     event_sender
-        .send((vec![Event::ContractEvent(modifier_event)], 305))
+        .send((vec![Event::ContractEvent(kept_modifier)], 305))
         .await
         .unwrap();
     app.run(pending()).await.unwrap();
 
-    let new_game_event = ContractEvent::NewGame(NewGameEvent {
+    let orphaned_roll = Event::roll_event(5, 2, Roll::Seven, 0, 0, 0, 320);
+    let orphaned_modifier = ContractEvent::ModifierTriggered(ModifierTriggeredEvent {
+        game_id: 5,
+        roll_index: 2,
+        trigger_roll: Roll::Seven,
+        modifier_roll: Roll::Six,
+        modifier: Modifier::Burnt,
+    });
+    let orphaned_new_game = ContractEvent::NewGame(NewGameEvent {
         game_id: 6,
         new_straps: vec![],
         new_modifiers: vec![],
         pot_size: 0,
         chips_owed_total: 0,
     });
-
     event_sender
-        .send((vec![Event::ContractEvent(new_game_event)], 310))
+        .send((
+            vec![
+                orphaned_roll,
+                Event::ContractEvent(orphaned_modifier),
+                Event::ContractEvent(orphaned_new_game),
+            ],
+            310,
+        ))
         .await
         .unwrap();
     app.run(pending()).await.unwrap();
+    assert!(historical_copy.lock().unwrap().contains_key(&5));
 
-    let historical = historical_copy.lock().unwrap();
-    let stored = historical
-        .get(&existing_snapshot.game_id)
-        .expect("expected historical snapshot");
-    assert_eq!(stored.rolls, existing_snapshot.rolls);
-    assert_eq!(stored.strap_rewards, existing_snapshot.rewards);
-    let active_modifier = ActiveModifier::new(expected_index, Modifier::Holy, Roll::Four);
-    assert_eq!(stored.modifiers, vec![active_modifier]);
+    // when
+    event_sender.rollback(307).await.unwrap();
+    app.run(pending()).await.unwrap();
 
-    // ensure modifiers for new game start fresh
-    let next_entry = historical.get(&6);
-    assert!(next_entry.is_none());
+    // then
+    let (actual, height) = snapshot_copy.lock().unwrap().clone().unwrap();
+    assert_eq!(height, 305);
+    assert_eq!(actual.game_id, 5);
+    assert!(historical_copy.lock().unwrap().is_empty());
+    let expected_modifiers = vec![ActiveModifier::new(1, Modifier::Holy, Roll::Four)];
+    assert_eq!(app.working_state.historical_modifiers, expected_modifiers);
+    assert_eq!(app.working_state.modifier_triggered, vec![Modifier::Holy]);
+    assert!(app.working_state.modifier_purchased.is_empty());
 }
 
 #[tokio::test]
@@ -666,10 +1407,11 @@ async fn run__place_chip_bet_event__updates_account_snapshot() {
     let key = InMemorySnapshotStorage::identity_key(&player);
     let account_guard = accounts_map.lock().unwrap();
     let game_id = 0;
-    let (account_snapshot, _) = account_guard
+    let account_snapshot = account_guard
         .get(&key)
         .unwrap()
         .get(&game_id)
+        .and_then(|versions| versions.values().next_back())
         .cloned()
         .unwrap();
     assert_eq!(account_snapshot.total_chip_bet, 150);
@@ -789,10 +1531,11 @@ async fn run__place_strap_bet_event__updates_account_snapshot() {
     let key = InMemorySnapshotStorage::identity_key(&player);
     let game_id = 0;
     let account_guard = accounts_map.lock().unwrap();
-    let (account_snapshot, _) = account_guard
+    let account_snapshot = account_guard
         .get(&key)
         .unwrap()
         .get(&game_id)
+        .and_then(|versions| versions.values().next_back())
         .cloned()
         .unwrap();
     assert_eq!(account_snapshot.total_chip_bet, 0);
@@ -892,10 +1635,11 @@ async fn run__claim_rewards_event__updates_account_snapshot() {
     let game_id = 0;
     let key = InMemorySnapshotStorage::identity_key(&player);
     let account_guard = accounts_map.lock().unwrap();
-    let (account_snapshot, _) = account_guard
+    let account_snapshot = account_guard
         .get(&key)
         .unwrap()
         .get(&game_id)
+        .and_then(|versions| versions.values().next_back())
         .cloned()
         .unwrap();
     assert_eq!(account_snapshot.total_chip_bet, 0);
@@ -958,10 +1702,11 @@ async fn run__claim_rewards_event__records_strap_winnings_in_account_snapshot()
     let key = InMemorySnapshotStorage::identity_key(&player);
     let game_id = 0;
     let account_guard = accounts_map.lock().unwrap();
-    let (account_snapshot, _) = account_guard
+    let account_snapshot = account_guard
         .get(&key)
         .unwrap()
         .get(&game_id)
+        .and_then(|versions| versions.values().next_back())
         .cloned()
         .unwrap();
     assert_eq!(account_snapshot.total_chip_bet, 0);
@@ -973,6 +1718,188 @@ async fn run__claim_rewards_event__records_strap_winnings_in_account_snapshot()
     );
 }
 
+#[tokio::test]
+async fn run__bets_then_claim__accumulate_player_game_stats() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::default(), 300);
+    let player_stats = snapshot_storage.player_stats();
+    let mut app = App::new(
+        event_source,
+        PendingQueryApi,
+        snapshot_storage,
+        InMemoryMetadataStorage::default(),
+        zero_contract_id(),
+    );
+    let player = Identity::Address(Address::from([3u8; 32]));
+    let strap = Strap::new(2, StrapKind::Coat, Modifier::Nothing);
+    let bets = vec![
+        Event::ContractEvent(ContractEvent::PlaceChipBet(PlaceChipBetEvent {
+            game_id: 0,
+            bet_roll_index: 0,
+            player,
+            roll: Roll::Six,
+            amount: 150,
+        })),
+        Event::ContractEvent(ContractEvent::PlaceStrapBet(PlaceStrapBetEvent {
+            game_id: 0,
+            bet_roll_index: 0,
+            player,
+            roll: Roll::Eight,
+            strap: Strap::new(1, StrapKind::Hat, Modifier::Nothing),
+            amount: 1,
+        })),
+    ];
+    let claim = Event::ContractEvent(ContractEvent::ClaimRewards(ClaimRewardsEvent {
+        game_id: 0,
+        player,
+        enabled_modifiers: vec![],
+        total_chips_winnings: 400,
+        total_strap_winnings: vec![(strap, 1)],
+    }));
+
+    // when
+    event_sender.send((bets, 305)).await.unwrap();
+    app.run(pending()).await.unwrap();
+    event_sender.send((vec![claim], 320)).await.unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let key = (0, InMemorySnapshotStorage::identity_key(&player));
+    let (_, versions) = player_stats.lock().unwrap()[&key].clone();
+    let (height, actual) = versions.last_key_value().unwrap();
+    let expected = PlayerGameStats {
+        chips_wagered: 150,
+        chips_won: 400,
+        hit: true,
+        highest_strap_level: 2,
+    };
+    assert_eq!(*actual, expected);
+    assert_eq!(*height, 320);
+}
+
+#[tokio::test]
+async fn run__strap_bet_then_claim__records_escrow_mint_and_upgrade() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::default(), 300);
+    let strap_supply = snapshot_storage.strap_supply();
+    let historical = snapshot_storage.historical_snapshots();
+    let mut app = App::new(
+        event_source,
+        PendingQueryApi,
+        snapshot_storage,
+        InMemoryMetadataStorage::default(),
+        zero_contract_id(),
+    );
+    let player = Identity::Address(Address::from([3u8; 32]));
+    let hat = Strap::new(1, StrapKind::Hat, Modifier::Nothing);
+    let upgraded_hat = Strap::new(2, StrapKind::Hat, Modifier::Nothing);
+    let bet = Event::ContractEvent(ContractEvent::PlaceStrapBet(PlaceStrapBetEvent {
+        game_id: 0,
+        bet_roll_index: 0,
+        player,
+        roll: Roll::Eight,
+        strap: hat.clone(),
+        amount: 1,
+    }));
+    let claim = Event::ContractEvent(ContractEvent::ClaimRewards(ClaimRewardsEvent {
+        game_id: 0,
+        player,
+        enabled_modifiers: vec![],
+        total_chips_winnings: 0,
+        total_strap_winnings: vec![(upgraded_hat.clone(), 1)],
+    }));
+    // The contract cleared the bets on the first claim, so a second one pays nothing
+    let repeated_claim =
+        Event::ContractEvent(ContractEvent::ClaimRewards(ClaimRewardsEvent {
+            game_id: 0,
+            player,
+            enabled_modifiers: vec![],
+            total_chips_winnings: 0,
+            total_strap_winnings: vec![],
+        }));
+
+    // when
+    event_sender.send((vec![bet], 305)).await.unwrap();
+    app.run(pending()).await.unwrap();
+    let finished =
+        HistoricalSnapshot::new(0, vec![Roll::Six, Roll::Eight, Roll::Seven], vec![]);
+    historical.lock().unwrap().insert(0, finished);
+    event_sender.send((vec![claim], 320)).await.unwrap();
+    app.run(pending()).await.unwrap();
+    event_sender
+        .send((vec![repeated_claim], 330))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let change = |strap: &Strap, kind| StrapSupplyChange {
+        strap: strap.clone(),
+        kind,
+        amount: 1,
+    };
+    let expected = vec![
+        (change(&hat, StrapSupplyChangeKind::Escrowed), 305),
+        (change(&upgraded_hat, StrapSupplyChangeKind::Minted), 320),
+        (change(&hat, StrapSupplyChangeKind::UpgradeConsumed), 320),
+        (
+            change(&upgraded_hat, StrapSupplyChangeKind::UpgradeProduced),
+            320,
+        ),
+    ];
+    assert_eq!(*strap_supply.lock().unwrap(), expected);
+}
+
+#[tokio::test]
+async fn run__event_batch__records_metrics() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(OverviewSnapshot::default(), 600);
+    let metrics = IndexerMetrics::new();
+    let mut app = App::new(
+        event_source,
+        PendingQueryApi,
+        snapshot_storage,
+        InMemoryMetadataStorage::default(),
+        zero_contract_id(),
+    )
+    .with_metrics(metrics.clone());
+    let funder = Identity::Address(Address::from([3u8; 32]));
+    let fund = |chips_amount| {
+        Event::ContractEvent(ContractEvent::FundPot(FundPotEvent {
+            chips_amount,
+            funder,
+        }))
+    };
+
+    // when
+    event_sender
+        .send((vec![fund(100), fund(250)], 605))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let body = metrics.encode().unwrap();
+    let expected_lines = [
+        "strapped_indexer_last_indexed_height 605",
+        "strapped_indexer_pot_size 350",
+        "strapped_indexer_events_processed_total{event=\"FundPot\"} 2",
+        "strapped_indexer_storage_commit_seconds_count 1",
+    ];
+    for line in expected_lines {
+        assert!(
+            body.lines().any(|actual| actual == line),
+            "{line} in {body}"
+        );
+    }
+}
+
 #[tokio::test]
 async fn handle_claim_rewards_event__records_strap_asset_ids() {
     // given
@@ -1056,6 +1983,135 @@ async fn run__fund_pot_event__increases_pot() {
     assert_eq!(expected, actual);
 }
 
+#[tokio::test]
+async fn run__withdraw_house_pot_event__reduces_pot_and_records_ledger() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+
+    let existing_snapshot = OverviewSnapshot {
+        pot_size: 1_000,
+        ..OverviewSnapshot::default()
+    };
+
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(existing_snapshot.clone(), 610);
+    let snapshot_copy = snapshot_storage.snapshot();
+    let ledger_copy = snapshot_storage.house_ledger();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+
+    let owner = Identity::Address(Address::from([3u8; 32]));
+    let withdraw_event = ContractEvent::WithdrawHousePot(WithdrawHousePotEvent {
+        amount: 400,
+        to: owner,
+    });
+
+    // when
+    event_sender
+        .send((vec![Event::ContractEvent(withdraw_event)], 615))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let (actual, _) = snapshot_copy.lock().unwrap().clone().unwrap();
+    let mut expected = existing_snapshot;
+    expected.pot_size = 600;
+    expected.current_block_height = 615;
+    assert_eq!(expected, actual);
+
+    let expected_ledger = vec![(
+        HouseLedgerEntry::Withdrawal {
+            amount: 400,
+            to: owner,
+        },
+        615,
+    )];
+    assert_eq!(*ledger_copy.lock().unwrap(), expected_ledger);
+}
+
+#[tokio::test]
+async fn run__insufficient_house_withdrawal_event__records_failed_withdrawal() {
+    // given
+    let (event_source, event_sender) = FakeEventSource::new_with_sender();
+
+    let existing_snapshot = OverviewSnapshot {
+        pot_size: 500,
+        ..OverviewSnapshot::default()
+    };
+
+    let snapshot_storage =
+        InMemorySnapshotStorage::new_with_snapshot(existing_snapshot.clone(), 610);
+    let snapshot_copy = snapshot_storage.snapshot();
+    let ledger_copy = snapshot_storage.house_ledger();
+
+    let metadata_storage = InMemoryMetadataStorage::default();
+    let query_api = PendingQueryApi;
+    let mut app = App::new(
+        event_source,
+        query_api,
+        snapshot_storage,
+        metadata_storage,
+        zero_contract_id(),
+    );
+
+    let owner = Identity::Address(Address::from([3u8; 32]));
+    let fund_event = ContractEvent::FundPot(FundPotEvent {
+        chips_amount: 100,
+        funder: owner,
+    });
+    let failed_event =
+        ContractEvent::InsufficientHouseWithdrawal(InsufficientHouseWithdrawalEvent {
+            requested_amount: 900,
+            available_amount: 600,
+            to: owner,
+        });
+
+    // when
+    event_sender
+        .send((
+            vec![
+                Event::ContractEvent(fund_event),
+                Event::ContractEvent(failed_event),
+            ],
+            615,
+        ))
+        .await
+        .unwrap();
+    app.run(pending()).await.unwrap();
+
+    // then
+    let (actual, _) = snapshot_copy.lock().unwrap().clone().unwrap();
+    assert_eq!(actual.pot_size, 600);
+
+    let expected_ledger = vec![
+        (
+            HouseLedgerEntry::Fund {
+                amount: 100,
+                funder: owner,
+            },
+            615,
+        ),
+        (
+            HouseLedgerEntry::FailedWithdrawal {
+                requested_amount: 900,
+                available_amount: 600,
+                to: owner,
+            },
+            615,
+        ),
+    ];
+    assert_eq!(*ledger_copy.lock().unwrap(), expected_ledger);
+}
+
 #[tokio::test]
 async fn run__purchase_modifier_event__marks_shop_entry() {
     // given
@@ -1110,6 +2166,7 @@ fn arb_snapshot() -> OverviewSnapshot {
         chips_owed: 123,
         current_block_height: 123,
         next_roll_height: Some(333),
+        next_roll_time: Some(1_700_000_333),
         roll_frequency: Some(10),
         first_roll_height: Some(123),
         rewards: vec![(
@@ -1165,7 +2222,7 @@ async fn run__latest_snapshot_query__returns_latest_snapshot() {
     app.run(pending()).await.unwrap();
 
     // then
-    let response = one_recv.await.unwrap();
+    let response = one_recv.await.unwrap().unwrap();
     assert_eq!(response, (snapshot, height));
 }
 
@@ -1210,7 +2267,7 @@ async fn run__latest_account_snapshot_query__returns_latest_account_snapshot() {
     app.run(pending()).await.unwrap();
 
     // then
-    let response = one_recv.await.unwrap();
+    let response = one_recv.await.unwrap().unwrap();
     assert_eq!(response, Some((expected_snapshot, expected_height)));
 }
 
@@ -1248,7 +2305,7 @@ async fn run__all_known_straps_query__returns_known_pairs() {
     app.run(pending()).await.unwrap();
 
     // then
-    let mut straps = one_recv.await.unwrap();
+    let mut straps = one_recv.await.unwrap().unwrap();
     straps.sort_by_key(|(asset_id, _)| *asset_id);
     assert_eq!(straps, vec![(asset_id_a, strap_a), (asset_id_b, strap_b)]);
 }
@@ -1284,8 +2341,45 @@ async fn run__historical_snapshot_query__returns_historical_snapshot() {
     app.run(pending()).await.unwrap();
 
     // then
-    let response = one_recv.await.unwrap();
-    assert_eq!(response, Some(expected_snapshot));
+    let response = one_recv.await.unwrap().unwrap();
+    assert_eq!(response, expected_snapshot);
+}
+
+#[tokio::test]
+async fn run__historical_snapshot_query__unknown_game__responds_not_found_and_keeps_running()
+ {
+    // given
+    let (query_api, sender) = FakeQueryApi::new_with_sender();
+    let mut app = App::new(
+        PendingEventSource,
+        query_api,
+        InMemorySnapshotStorage::new(),
+        InMemoryMetadataStorage::default(),
+        zero_contract_id(),
+    );
+
+    // when
+    let (one_send, one_recv) = oneshot::channel();
+    sender
+        .send(Query::historical_snapshot(99_999, one_send))
+        .await
+        .unwrap();
+    let state = app.run(pending()).await.unwrap();
+    let (dropped_send, dropped_recv) = oneshot::channel();
+    drop(dropped_recv);
+    sender
+        .send(Query::LatestSnapshot(dropped_send))
+        .await
+        .unwrap();
+    let after_dropped_client = app.run(pending()).await.unwrap();
+
+    // then
+    assert!(matches!(state, RunState::Continue));
+    assert!(matches!(
+        one_recv.await.unwrap(),
+        Err(QueryError::NotFound(_))
+    ));
+    assert!(matches!(after_dropped_client, RunState::Continue));
 }
 
 #[tokio::test]
@@ -1323,6 +2417,6 @@ async fn run__historical_account_snapshot_query__returns_historical_account_snap
     app.run(pending()).await.unwrap();
 
     // then
-    let response = one_recv.await.unwrap();
+    let response = one_recv.await.unwrap().unwrap();
     assert_eq!(response, Some((expected_snapshot, expected_height)));
 }
@@ -0,0 +1,365 @@
+// In-memory overlay over `SnapshotStorage` that buffers one event batch worth of writes.
+use crate::{
+    app::snapshot_storage::{
+        BlockChanges,
+        HistoryOrder,
+        SnapshotStorage,
+    },
+    snapshot::{
+        AccountSnapshot,
+        GameWorkingState,
+        HistoricalSnapshot,
+        HouseLedgerEntry,
+        OverviewSnapshot,
+        PlayerGameStats,
+        StrapSupplyChange,
+    },
+};
+use fuels::types::Identity;
+use std::collections::BTreeMap;
+
+/// Reads see buffered writes first and fall through to the wrapped storage. Nothing
+/// reaches the wrapped storage until `commit`, which hands it all at once.
+pub struct WorkingSet<Snapshots> {
+    inner: Snapshots,
+    pending: BlockChanges,
+}
+
+impl<Snapshots: SnapshotStorage> WorkingSet<Snapshots> {
+    pub fn new(inner: Snapshots) -> Self {
+        Self {
+            inner,
+            pending: BlockChanges::default(),
+        }
+    }
+
+    /// Write everything buffered since the last commit to the wrapped storage, returning
+    /// what was written
+    pub fn commit(&mut self) -> crate::Result<BlockChanges> {
+        let changes = std::mem::take(&mut self.pending);
+        if !changes.is_empty() {
+            self.inner.commit_block(&changes)?;
+        }
+        Ok(changes)
+    }
+
+    /// Drop everything buffered since the last commit
+    pub fn discard(&mut self) {
+        self.pending = BlockChanges::default();
+    }
+
+    fn with_pending_accounts(
+        &self,
+        mut snapshot: HistoricalSnapshot,
+    ) -> HistoricalSnapshot {
+        let game_id = snapshot.game_id;
+        for (_, account, account_snapshot, _) in self
+            .pending
+            .historical_accounts
+            .iter()
+            .filter(|(update_game_id, ..)| *update_game_id == game_id)
+        {
+            snapshot.upsert_account(*account, account_snapshot.clone());
+        }
+        snapshot
+    }
+}
+
+impl<Snapshots: SnapshotStorage> SnapshotStorage for WorkingSet<Snapshots> {
+    fn latest_snapshot(&self) -> crate::Result<(OverviewSnapshot, u32)> {
+        match &self.pending.overview {
+            Some(latest) => Ok(latest.clone()),
+            None => self.inner.latest_snapshot(),
+        }
+    }
+
+    fn latest_account_snapshot(
+        &self,
+        account: &Identity,
+    ) -> crate::Result<Option<(AccountSnapshot, u32)>> {
+        match &self.pending.overview {
+            Some((snapshot, _)) => self.account_snapshot_at(account, snapshot.game_id),
+            None => self.inner.latest_account_snapshot(account),
+        }
+    }
+
+    fn account_snapshot_at(
+        &self,
+        account: &Identity,
+        game_id: u32,
+    ) -> crate::Result<Option<(AccountSnapshot, u32)>> {
+        let pending = self
+            .pending
+            .accounts
+            .iter()
+            .find(|(identity, id, _, _)| identity == account && *id == game_id);
+        match pending {
+            Some((_, _, snapshot, height)) => Ok(Some((snapshot.clone(), *height))),
+            None => self.inner.account_snapshot_at(account, game_id),
+        }
+    }
+
+    fn update_snapshot(
+        &mut self,
+        snapshot: &OverviewSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.pending.overview = Some((snapshot.clone(), height));
+        Ok(())
+    }
+
+    fn update_account_snapshot(
+        &mut self,
+        account: &Identity,
+        game_id: u32,
+        account_snapshot: &AccountSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        let accounts = &mut self.pending.accounts;
+        match accounts
+            .iter_mut()
+            .find(|(identity, id, _, _)| identity == account && *id == game_id)
+        {
+            Some(entry) => {
+                entry.2 = account_snapshot.clone();
+                entry.3 = height;
+            }
+            None => accounts.push((*account, game_id, account_snapshot.clone(), height)),
+        }
+        Ok(())
+    }
+
+    fn player_stats_at(
+        &self,
+        player: &Identity,
+        game_id: u32,
+    ) -> crate::Result<Option<PlayerGameStats>> {
+        let pending = self
+            .pending
+            .player_stats
+            .iter()
+            .find(|(identity, id, _, _)| identity == player && *id == game_id);
+        match pending {
+            Some((_, _, stats, _)) => Ok(Some(stats.clone())),
+            None => self.inner.player_stats_at(player, game_id),
+        }
+    }
+
+    fn update_player_stats(
+        &mut self,
+        player: &Identity,
+        game_id: u32,
+        stats: &PlayerGameStats,
+        height: u32,
+    ) -> crate::Result<()> {
+        let player_stats = &mut self.pending.player_stats;
+        match player_stats
+            .iter_mut()
+            .find(|(identity, id, _, _)| identity == player && *id == game_id)
+        {
+            Some(entry) => {
+                entry.2 = stats.clone();
+                entry.3 = height;
+            }
+            None => player_stats.push((*player, game_id, stats.clone(), height)),
+        }
+        Ok(())
+    }
+
+    fn player_stats_in_games(
+        &self,
+        from_game: u32,
+        to_game: u32,
+    ) -> crate::Result<Vec<(Identity, u32, PlayerGameStats)>> {
+        let pending = self
+            .pending
+            .player_stats
+            .iter()
+            .filter(|(_, game_id, _, _)| (from_game..=to_game).contains(game_id));
+        let mut stats: Vec<_> = self
+            .inner
+            .player_stats_in_games(from_game, to_game)?
+            .into_iter()
+            .filter(|(player, game_id, _)| {
+                !self
+                    .pending
+                    .player_stats
+                    .iter()
+                    .any(|(identity, id, _, _)| identity == player && id == game_id)
+            })
+            .collect();
+        stats.extend(
+            pending.map(|(player, game_id, stats, _)| (*player, *game_id, stats.clone())),
+        );
+        stats.sort_by_key(|(_, game_id, _)| *game_id);
+        Ok(stats)
+    }
+
+    fn latest_working_state(&self) -> crate::Result<Option<(GameWorkingState, u32)>> {
+        match &self.pending.working_state {
+            Some(latest) => Ok(Some(latest.clone())),
+            None => self.inner.latest_working_state(),
+        }
+    }
+
+    fn update_working_state(
+        &mut self,
+        state: &GameWorkingState,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.pending.working_state = Some((state.clone(), height));
+        Ok(())
+    }
+
+    fn record_house_ledger_entry(
+        &mut self,
+        entry: &HouseLedgerEntry,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.pending.house_ledger.push((entry.clone(), height));
+        Ok(())
+    }
+
+    fn house_ledger(&self) -> crate::Result<Vec<(HouseLedgerEntry, u32)>> {
+        let mut ledger = self.inner.house_ledger()?;
+        ledger.extend(self.pending.house_ledger.iter().cloned());
+        Ok(ledger)
+    }
+
+    fn record_strap_supply_change(
+        &mut self,
+        change: &StrapSupplyChange,
+        height: u32,
+    ) -> crate::Result<()> {
+        self.pending.strap_supply.push((change.clone(), height));
+        Ok(())
+    }
+
+    fn strap_supply_changes(&self) -> crate::Result<Vec<(StrapSupplyChange, u32)>> {
+        let mut changes = self.inner.strap_supply_changes()?;
+        changes.extend(self.pending.strap_supply.iter().cloned());
+        Ok(changes)
+    }
+
+    fn record_block_timestamp(
+        &mut self,
+        height: u32,
+        timestamp: u64,
+    ) -> crate::Result<()> {
+        self.pending.block_timestamps.insert(height, timestamp);
+        Ok(())
+    }
+
+    fn block_timestamp_at_or_before(
+        &self,
+        height: u32,
+    ) -> crate::Result<Option<(u32, u64)>> {
+        let pending = self
+            .pending
+            .block_timestamps
+            .range(..=height)
+            .next_back()
+            .map(|(height, timestamp)| (*height, *timestamp));
+        let stored = self.inner.block_timestamp_at_or_before(height)?;
+        // Buffered timestamps win ties since they overwrite the stored ones on commit
+        Ok(match (pending, stored) {
+            (Some(pending), Some(stored)) if stored.0 > pending.0 => Some(stored),
+            (pending, stored) => pending.or(stored),
+        })
+    }
+
+    fn roll_back_snapshots(&mut self, to_height: u32) -> crate::Result<()> {
+        self.discard();
+        self.inner.roll_back_snapshots(to_height)
+    }
+
+    fn roll_back(&mut self, to_height: u32) -> crate::Result<()> {
+        self.discard();
+        self.inner.roll_back(to_height)
+    }
+
+    fn prune_from(&mut self, from_height: u32) -> crate::Result<()> {
+        self.discard();
+        self.inner.prune_from(from_height)
+    }
+
+    fn historical_snapshots(&self, game_id: u32) -> crate::Result<HistoricalSnapshot> {
+        let snapshot = match self.pending.historical.get(&game_id) {
+            Some(snapshot) => snapshot.clone(),
+            None => self.inner.historical_snapshots(game_id)?,
+        };
+        Ok(self.with_pending_accounts(snapshot))
+    }
+
+    fn write_historical_snapshot(
+        &mut self,
+        game_id: u32,
+        snapshot: &HistoricalSnapshot,
+    ) -> crate::Result<()> {
+        self.pending.historical.insert(game_id, snapshot.clone());
+        Ok(())
+    }
+
+    fn update_historical_account(
+        &mut self,
+        game_id: u32,
+        account: &Identity,
+        account_snapshot: &AccountSnapshot,
+        height: u32,
+    ) -> crate::Result<()> {
+        let updates = &mut self.pending.historical_accounts;
+        match updates.iter_mut().find(|(id, identity, _, update_height)| {
+            *id == game_id && identity == account && *update_height == height
+        }) {
+            Some(entry) => entry.2 = account_snapshot.clone(),
+            None => updates.push((game_id, *account, account_snapshot.clone(), height)),
+        }
+        Ok(())
+    }
+
+    fn historical_snapshot_range(
+        &self,
+        from: Option<u32>,
+        limit: usize,
+        order: HistoryOrder,
+    ) -> crate::Result<Vec<HistoricalSnapshot>> {
+        let mut snapshots: BTreeMap<u32, HistoricalSnapshot> = self
+            .inner
+            .historical_snapshot_range(from, limit, order)?
+            .into_iter()
+            .map(|snapshot| (snapshot.game_id, snapshot))
+            .collect();
+        let pending =
+            self.pending
+                .historical
+                .iter()
+                .filter(|(game_id, _)| match (order, from) {
+                    (_, None) => true,
+                    (HistoryOrder::Ascending, Some(from)) => **game_id >= from,
+                    (HistoryOrder::Descending, Some(from)) => **game_id <= from,
+                });
+        for (game_id, snapshot) in pending {
+            snapshots.insert(*game_id, snapshot.clone());
+        }
+        let snapshots = snapshots
+            .into_values()
+            .map(|snapshot| self.with_pending_accounts(snapshot));
+        Ok(match order {
+            HistoryOrder::Ascending => snapshots.take(limit).collect(),
+            HistoryOrder::Descending => snapshots.rev().take(limit).collect(),
+        })
+    }
+
+    fn remove_historical_snapshots_from(&mut self, game_id: u32) -> crate::Result<()> {
+        self.pending.historical.split_off(&game_id);
+        self.pending
+            .historical_accounts
+            .retain(|(id, ..)| *id < game_id);
+        self.inner.remove_historical_snapshots_from(game_id)
+    }
+
+    fn commit_block(&mut self, changes: &BlockChanges) -> crate::Result<()> {
+        self.commit()?;
+        self.inner.commit_block(changes)
+    }
+}
@@ -12,10 +12,17 @@ use serde::{
 
 #[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
-    BlockchainEvent,
+    BlockchainEvent(BlockMetadataEvent),
     ContractEvent(ContractEvent),
 }
 
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct BlockMetadataEvent {
+    pub height: u32,
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub enum ContractEvent {
     Initialized(InitializedEvent),
@@ -27,6 +34,8 @@ pub enum ContractEvent {
     ClaimRewards(ClaimRewardsEvent),
     FundPot(FundPotEvent),
     PurchaseModifier(PurchaseModifierEvent),
+    WithdrawHousePot(WithdrawHousePotEvent),
+    InsufficientHouseWithdrawal(InsufficientHouseWithdrawalEvent),
 }
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -44,7 +53,7 @@ pub enum Roll {
     Twelve,
 }
 
-#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum StrapKind {
     Shirt,
     Pants,
@@ -67,7 +76,7 @@ pub enum StrapKind {
     Belt,
 }
 
-#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum Modifier {
     Nothing,
     Burnt,
@@ -221,6 +230,19 @@ pub struct FundPotEvent {
     pub funder: Identity,
 }
 
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawHousePotEvent {
+    pub amount: u64,
+    pub to: Identity,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct InsufficientHouseWithdrawalEvent {
+    pub requested_amount: u64,
+    pub available_amount: u64,
+    pub to: Identity,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct PurchaseModifierEvent {
     pub expected_roll: Roll,
@@ -229,6 +251,32 @@ pub struct PurchaseModifierEvent {
 }
 
 impl Event {
+    /// Name of the event's variant, for logs and metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::BlockchainEvent(_) => "BlockMetadata",
+            Event::ContractEvent(event) => match event {
+                ContractEvent::Initialized(_) => "Initialized",
+                ContractEvent::Roll(_) => "Roll",
+                ContractEvent::NewGame(_) => "NewGame",
+                ContractEvent::ModifierTriggered(_) => "ModifierTriggered",
+                ContractEvent::PlaceChipBet(_) => "PlaceChipBet",
+                ContractEvent::PlaceStrapBet(_) => "PlaceStrapBet",
+                ContractEvent::ClaimRewards(_) => "ClaimRewards",
+                ContractEvent::FundPot(_) => "FundPot",
+                ContractEvent::PurchaseModifier(_) => "PurchaseModifier",
+                ContractEvent::WithdrawHousePot(_) => "WithdrawHousePot",
+                ContractEvent::InsufficientHouseWithdrawal(_) => {
+                    "InsufficientHouseWithdrawal"
+                }
+            },
+        }
+    }
+
+    pub fn block_event(height: u32, timestamp: u64) -> Self {
+        Event::BlockchainEvent(BlockMetadataEvent { height, timestamp })
+    }
+
     pub fn init_event(
         vrf_contract_id: ContractId,
         chip_asset_id: AssetId,
@@ -282,3 +330,33 @@ impl Event {
         Event::ContractEvent(ContractEvent::NewGame(inner))
     }
 }
+
+/// An event tagged with the contract that emitted it, so one receipts stream can feed
+/// several deployments
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct ScopedEvent {
+    /// Unset for chain-wide events like block metadata, which every deployment sees
+    pub contract_id: Option<ContractId>,
+    pub event: Event,
+}
+
+impl ScopedEvent {
+    pub fn contract(contract_id: ContractId, event: Event) -> Self {
+        Self {
+            contract_id: Some(contract_id),
+            event,
+        }
+    }
+
+    pub fn chain(event: Event) -> Self {
+        Self {
+            contract_id: None,
+            event,
+        }
+    }
+
+    /// Whether a deployment indexing `contract_id` should see this event
+    pub fn is_visible_to(&self, contract_id: &ContractId) -> bool {
+        self.contract_id.is_none_or(|id| id == *contract_id)
+    }
+}
@@ -5,9 +5,11 @@ use anyhow::{
 use clap::{
     ArgGroup,
     Parser,
+    Subcommand,
 };
 use deployments::{
     DeploymentEnv,
+    DeploymentRecord,
     DeploymentStore,
 };
 use fuel_core::{
@@ -18,18 +20,44 @@ use fuel_core::{
     types::fuel_types::BlockHeight,
 };
 use fuel_event_streams::service::Config;
-use fuels::types::ContractId;
+use fuels::{
+    prelude::Provider,
+    types::ContractId,
+};
 use indexer::app::{
     App,
     RunState,
     actix_query_api::ActixQueryApi,
+    event_archive::{
+        ArchivingEventSource,
+        EventArchive,
+        FileEventSource,
+    },
     fuel_indexer_event_source::{
         FuelIndexerEventSource,
-        parse_event_logs,
+        parse_scoped_event_logs,
+    },
+    health::{
+        DEFAULT_MAX_READY_LAG,
+        Readiness,
     },
     init_tracing,
+    query_api::{
+        DisabledQueryApi,
+        StorageReader,
+    },
+    shared_event_source::{
+        SharedEventSource,
+        SubscribedEventSource,
+    },
     sled_storage::SledSnapshotStorage,
-    snapshot_storage::SnapshotStorage,
+    snapshot_storage::{
+        DEFAULT_REORG_WINDOW,
+        PersistentSnapshotStorage,
+        RetentionPolicy,
+        SnapshotStorage,
+    },
+    sqlite_storage::SqliteSnapshotStorage,
 };
 use std::{
     convert::TryFrom,
@@ -37,29 +65,47 @@ use std::{
     fs,
     path::PathBuf,
     str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::watch,
+    task::LocalSet,
 };
 use url::Url;
 
+// How often the node's head height is polled for `/ready`
+const NODE_HEIGHT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+// How often overview snapshots outside the retention window are pruned
+const OVERVIEW_COMPACTION_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Parser, Debug)]
 #[command(
     version,
     about,
     long_about = None,
+    subcommand_negates_reqs = true,
     group(
         ArgGroup::new("network")
             .args(["local", "dev", "test"])
             .required(true)
+            .multiple(true)
     )
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Contract to index; repeat to index several from one receipts stream. Defaults to
+    /// the deployment record of each selected network.
     #[arg(short, long)]
-    contract_id: Option<String>,
+    contract_id: Vec<String>,
 
     #[arg(long = "start-height")]
     start_height: Option<u32>,
 
-    #[arg(short, long)]
-    graphql_url: Url,
+    #[arg(short, long, required = true)]
+    graphql_url: Option<Url>,
 
     #[arg(short, long)]
     port: Option<u16>,
@@ -81,6 +127,67 @@ struct Args {
 
     #[arg(long)]
     block_request_concurrency: Option<usize>,
+
+    /// Append decoded event batches here (defaults to the contract's data directory)
+    #[arg(long)]
+    archive_path: Option<PathBuf>,
+
+    /// Blocks the indexer may trail the node's head by before `/ready` fails
+    #[arg(long, default_value_t = DEFAULT_MAX_READY_LAG)]
+    max_ready_lag: u32,
+
+    /// Blocks of per-height overview snapshots to keep; older ones are pruned down to
+    /// the last of each game, so rollbacks deeper than this are refused. At least 1,
+    /// as a restart rolls back the latest block to index it again.
+    #[arg(
+        long,
+        default_value_t = DEFAULT_REORG_WINDOW,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    overview_retention_blocks: u32,
+
+    /// Backend to keep snapshots in
+    #[arg(long, value_enum, default_value_t = StorageKind::Sled)]
+    storage: StorageKind,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum StorageKind {
+    /// Sled trees
+    Sled,
+    /// SQLite database files, with tables that can be queried directly
+    Sqlite,
+}
+
+impl StorageKind {
+    /// Directory under a deployment's data directory the storage defaults to
+    fn dir_name(self) -> &'static str {
+        match self {
+            StorageKind::Sled => "snapshots",
+            StorageKind::Sqlite => "sqlite",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rebuild snapshots offline from an event archive
+    Replay(ReplayArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ReplayArgs {
+    /// Event archive written by a previous indexer run
+    #[arg(long)]
+    from: PathBuf,
+
+    /// Contract the archive was recorded for
+    #[arg(short, long)]
+    contract_id: String,
+
+    /// Empty directory to write the rebuilt snapshots to
+    #[arg(long)]
+    snapshot_dir: PathBuf,
 }
 
 async fn handle_interupt() {
@@ -95,6 +202,51 @@ async fn handle_interupt() {
     }
 }
 
+async fn poll_node_height(graphql_url: Url, readiness: Readiness) {
+    let provider = loop {
+        match Provider::connect(graphql_url.as_str()).await {
+            Ok(provider) => break provider,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to connect to {graphql_url} for head height: {e:?}"
+                );
+                tokio::time::sleep(NODE_HEIGHT_POLL_INTERVAL).await;
+            }
+        }
+    };
+    loop {
+        match provider.latest_block_height().await {
+            Ok(height) => readiness.record_node_height(height),
+            Err(e) => tracing::warn!("Failed to read node head height: {e:?}"),
+        }
+        tokio::time::sleep(NODE_HEIGHT_POLL_INTERVAL).await;
+    }
+}
+
+async fn compact_overviews<Storage: PersistentSnapshotStorage>(
+    storages: Vec<Storage>,
+    policy: RetentionPolicy,
+) {
+    let mut interval = tokio::time::interval(OVERVIEW_COMPACTION_INTERVAL);
+    loop {
+        interval.tick().await;
+        for snapshots in &storages {
+            let snapshots = snapshots.clone();
+            let compacted =
+                tokio::task::spawn_blocking(move || snapshots.compact_overviews(policy))
+                    .await;
+            match compacted {
+                Ok(Ok(0)) => {}
+                Ok(Ok(removed)) => {
+                    tracing::info!("Pruned {removed} overview snapshots")
+                }
+                Ok(Err(e)) => tracing::warn!("Failed to prune overview snapshots: {e:?}"),
+                Err(e) => tracing::warn!("Overview pruning task failed: {e:?}"),
+            }
+        }
+    }
+}
+
 fn parse_contract_id_str(raw: &str) -> anyhow::Result<ContractId> {
     let trimmed = raw.trim();
     let cleaned = trimmed.trim_start_matches("fuel");
@@ -102,196 +254,493 @@ fn parse_contract_id_str(raw: &str) -> anyhow::Result<ContractId> {
         .map_err(|e| anyhow!("Failed to parse contract id '{raw}': {e:?}"))
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
-    if args.tracing {
-        init_tracing();
+async fn replay(args: ReplayArgs) -> anyhow::Result<()> {
+    let contract_id =
+        parse_contract_id_str(&args.contract_id).context("parsing --contract-id")?;
+    fs::create_dir_all(&args.snapshot_dir)?;
+    let (snapshots, metadata) = SledSnapshotStorage::open(&args.snapshot_dir)?;
+    if snapshots.latest_snapshot().is_ok() {
+        return Err(anyhow!(
+            "Snapshot directory {} already contains indexed state; replay needs an empty directory",
+            args.snapshot_dir.display()
+        ));
     }
-    let (deployment_env, network_label) = if args.local {
-        (DeploymentEnv::Local, "local")
-    } else if args.dev {
-        (DeploymentEnv::Dev, "dev")
-    } else {
-        (DeploymentEnv::Test, "test")
-    };
-    let store =
-        DeploymentStore::new(deployment_env).context("opening deployments store")?;
-    let stored_record = store.load().context("loading deployment")?;
-    let user_contract_id = args
-        .contract_id
-        .as_ref()
-        .map(|raw| parse_contract_id_str(raw).context("parsing --contract-id"))
-        .transpose()?;
-    let matches_cli_contract = |record: &deployments::DeploymentRecord,
-                                cid: &ContractId| {
-        parse_contract_id_str(&record.contract_id)
-            .map(|parsed| parsed == *cid)
-            .unwrap_or(false)
-    };
-    let selected_record = match (&user_contract_id, stored_record.clone()) {
-        (Some(cid), Some(record)) if matches_cli_contract(&record, cid) => Some(record),
-        (None, record) => record,
-        _ => None,
-    };
-    let (contract_id, record_used) = match selected_record {
-        Some(record) => {
-            let contract_id =
-                parse_contract_id_str(&record.contract_id).with_context(|| {
-                    format!(
-                        "parsing contract id from deployment record {}",
-                        record.contract_id
-                    )
-                })?;
-            (contract_id, Some(record))
+    let events = FileEventSource::open(&args.from)?;
+    let finished = events.finished();
+    let mut app = App::new(events, DisabledQueryApi, snapshots, metadata, contract_id);
+
+    tracing::info!(
+        "Replaying {} into {}",
+        args.from.display(),
+        args.snapshot_dir.display()
+    );
+    loop {
+        let interrupt = async {
+            tokio::select! {
+                _ = finished.notified() => {}
+                _ = handle_interupt() => {}
+            }
+        };
+        match app.run(interrupt).await? {
+            RunState::Continue => continue,
+            RunState::Exit => {
+                tracing::info!("Replay finished");
+                return Ok(());
+            }
         }
-        None => {
-            let cid = user_contract_id
-                .ok_or_else(|| anyhow!(
-                    "No deployment record found for {network_label}; provide --contract-id"
-                ))?;
-            (cid, None)
+    }
+}
+
+/// A deployment to index, with the deployment record it was found in, if any
+struct Target {
+    contract_id: ContractId,
+    network_label: &'static str,
+    record: Option<DeploymentRecord>,
+}
+
+impl Target {
+    /// Name of this deployment's data directory and sled namespace
+    fn dir_name(&self) -> String {
+        self.record
+            .as_ref()
+            .map(|record| record.contract_id.clone())
+            .unwrap_or_else(|| self.contract_id.to_string())
+    }
+
+    /// Where indexing starts when nothing has been indexed yet
+    fn requested_start_height(
+        &self,
+        override_start_height: Option<u32>,
+    ) -> anyhow::Result<u32> {
+        if let Some(height) = override_start_height {
+            return Ok(height);
         }
-    };
-    let override_start_height = args.start_height;
-    let mut start_height = if let Some(ref record) = record_used {
+        let Some(record) = &self.record else {
+            return Err(anyhow!(
+                "No deployment metadata available for contract {}; supply --start-height",
+                self.contract_id
+            ));
+        };
         match record.deployment_block_height {
             Some(height) => {
-                u32::try_from(height).context("deployment block height exceeds u32")?
+                u32::try_from(height).context("deployment block height exceeds u32")
             }
             None => {
                 tracing::warn!(
                     "Deployment record {} missing deployment_block_height; defaulting to 0",
                     record.contract_id
                 );
-                0
+                Ok(0)
             }
         }
-    } else {
-        0
-    };
-    if record_used.is_none() && override_start_height.is_none() {
+    }
+}
+
+/// The deployments of every selected network, or those of `--contract-id` if given
+fn select_targets(args: &Args) -> anyhow::Result<Vec<Target>> {
+    let networks = [
+        (args.local, DeploymentEnv::Local, "local"),
+        (args.dev, DeploymentEnv::Dev, "dev"),
+        (args.test, DeploymentEnv::Test, "test"),
+    ];
+    let mut records = Vec::new();
+    for (_, env, label) in networks.into_iter().filter(|(selected, ..)| *selected) {
+        let store = DeploymentStore::new(env).context("opening deployments store")?;
+        let record = store.load().context("loading deployment")?;
+        records.push((label, record));
+    }
+
+    if args.contract_id.is_empty() {
+        return records
+            .into_iter()
+            .map(|(network_label, record)| {
+                let record = record.ok_or_else(|| {
+                    anyhow!(
+                        "No deployment record found for {network_label}; provide --contract-id"
+                    )
+                })?;
+                let contract_id =
+                    parse_contract_id_str(&record.contract_id).with_context(|| {
+                        format!(
+                            "parsing contract id from deployment record {}",
+                            record.contract_id
+                        )
+                    })?;
+                Ok(Target {
+                    contract_id,
+                    network_label,
+                    record: Some(record),
+                })
+            })
+            .collect();
+    }
+
+    let mut targets: Vec<Target> = Vec::new();
+    for raw in &args.contract_id {
+        let contract_id = parse_contract_id_str(raw).context("parsing --contract-id")?;
+        if targets
+            .iter()
+            .any(|target| target.contract_id == contract_id)
+        {
+            return Err(anyhow!("--contract-id {raw} was given more than once"));
+        }
+        let matching = records.iter().find_map(|(network_label, record)| {
+            record
+                .as_ref()
+                .filter(|record| {
+                    parse_contract_id_str(&record.contract_id)
+                        .is_ok_and(|parsed| parsed == contract_id)
+                })
+                .map(|record| (*network_label, record.clone()))
+        });
+        let target = match matching {
+            Some((network_label, record)) => Target {
+                contract_id,
+                network_label,
+                record: Some(record),
+            },
+            // Without a record to go by, the network has to be the only one selected
+            None => match records.as_slice() {
+                [(network_label, _)] => Target {
+                    contract_id,
+                    network_label: *network_label,
+                    record: None,
+                },
+                _ => {
+                    return Err(anyhow!(
+                        "--contract-id {raw} matches no deployment record, so its \
+                         network is ambiguous; select exactly one network"
+                    ));
+                }
+            },
+        };
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+type DeploymentApp<Storage> = App<
+    ArchivingEventSource<SubscribedEventSource>,
+    ActixQueryApi,
+    Storage,
+    <Storage as PersistentSnapshotStorage>::Metadata,
+>;
+
+/// Run one deployment's event loop until `shutdown` flips to `true`
+async fn index<Storage: PersistentSnapshotStorage>(
+    mut app: DeploymentApp<Storage>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    loop {
+        let interrupt = async {
+            let _ = shutdown.wait_for(|stop| *stop).await;
+        };
+        match app.run(interrupt).await? {
+            RunState::Continue => continue,
+            RunState::Exit => return Ok(()),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args = Args::parse();
+    if args.tracing {
+        init_tracing();
+    }
+    if let Some(Command::Replay(replay_args)) = args.command.take() {
+        return replay(replay_args).await;
+    }
+    let graphql_url = args
+        .graphql_url
+        .clone()
+        .context("--graphql-url is required unless replaying")?;
+    let targets = select_targets(&args)?;
+    let shared = targets.len() > 1;
+    if shared && args.archive_path.is_some() {
         return Err(anyhow!(
-            "No deployment metadata available for contract {}; supply --start-height",
-            contract_id
+            "--archive-path only applies when indexing a single contract"
         ));
     }
-    if let Some(custom_height) = override_start_height {
-        start_height = custom_height;
-    }
-    let requested_start_height = start_height;
-    if let Some(ref record) = record_used {
-        tracing::info!(
-            "Using deployment record {} (network {}) deployed at {} (block height {:?})",
-            record.contract_id,
-            record.network_url,
-            record.deployed_at,
-            record.deployment_block_height
-        );
-    } else {
-        tracing::info!(
-            "Using contract {} provided via CLI override with start height {}",
-            contract_id,
-            start_height
-        );
+    for target in &targets {
+        match &target.record {
+            Some(record) => tracing::info!(
+                "Using deployment record {} (network {}) deployed at {} (block height {:?})",
+                record.contract_id,
+                record.network_url,
+                record.deployed_at,
+                record.deployment_block_height
+            ),
+            None => tracing::info!(
+                "Using contract {} provided via CLI override",
+                target.contract_id
+            ),
+        }
     }
-    let contract_dir_name = record_used
-        .as_ref()
-        .map(|record| record.contract_id.clone())
-        .unwrap_or_else(|| contract_id.to_string());
+
+    // A single deployment keeps its own directory; several share one receipts store and
+    // one storage directory, namespaced per deployment
     let execution_dir = current_dir().context("determine process working directory")?;
-    let data_root = execution_dir
-        .join("strapped_indexer_db")
-        .join(network_label)
-        .join(&contract_dir_name);
+    let data_root = if shared {
+        let mut network_labels = Vec::new();
+        for target in &targets {
+            if !network_labels.contains(&target.network_label) {
+                network_labels.push(target.network_label);
+            }
+        }
+        let short_ids: Vec<_> = targets
+            .iter()
+            .map(|target| format!("{:x}", target.contract_id)[..8].to_string())
+            .collect();
+        execution_dir
+            .join("strapped_indexer_db")
+            .join(network_labels.join("+"))
+            .join(format!("shared_{}", short_ids.join("+")))
+    } else {
+        execution_dir
+            .join("strapped_indexer_db")
+            .join(targets[0].network_label)
+            .join(targets[0].dir_name())
+    };
     fs::create_dir_all(&data_root)?;
     let event_data_path = data_root.join("events");
     fs::create_dir_all(&event_data_path)?;
     tracing::info!(
-        "Using persistent event directory for {}: {}",
-        contract_dir_name,
+        "Using persistent event directory: {}",
         event_data_path.display()
     );
+    match args.storage {
+        StorageKind::Sled => {
+            index_deployments::<SledSnapshotStorage>(
+                args,
+                graphql_url,
+                targets,
+                data_root,
+                event_data_path,
+            )
+            .await
+        }
+        StorageKind::Sqlite => {
+            index_deployments::<SqliteSnapshotStorage>(
+                args,
+                graphql_url,
+                targets,
+                data_root,
+                event_data_path,
+            )
+            .await
+        }
+    }
+}
+
+/// Index every target into `Storage` until interrupted or one of them fails
+async fn index_deployments<Storage: PersistentSnapshotStorage>(
+    args: Args,
+    graphql_url: Url,
+    targets: Vec<Target>,
+    data_root: PathBuf,
+    event_data_path: PathBuf,
+) -> anyhow::Result<()> {
+    let shared = targets.len() > 1;
     let storage_path = match &args.snapshot_dir {
         Some(path) => path.clone(),
-        None => data_root.join("snapshots"),
+        None => data_root.join(args.storage.dir_name()),
     };
     fs::create_dir_all(&storage_path)?;
     tracing::info!(
-        "Using sled storage directory for {}: {}",
-        contract_dir_name,
+        "Using {:?} storage directory: {}",
+        args.storage,
         storage_path.display()
     );
+    let namespaces: Vec<_> = if shared {
+        targets.iter().map(Target::dir_name).collect()
+    } else {
+        vec![String::new()]
+    };
+    let storages = Storage::open_namespaces(&storage_path, &namespaces)?;
 
-    let (mut snapshots, metadata) = SledSnapshotStorage::open(&storage_path)?;
-    let last_indexed_height = snapshots.latest_snapshot().map(|(_, height)| height).ok();
-    if let Some(existing_height) = last_indexed_height {
-        if existing_height > start_height {
+    let override_start_height = args.start_height;
+    let mut deployments = Vec::with_capacity(targets.len());
+    for (target, (mut snapshots, metadata)) in targets.iter().zip(storages) {
+        let contract_dir_name = target.dir_name();
+        let requested_start_height =
+            target.requested_start_height(override_start_height)?;
+        let mut start_height = requested_start_height;
+        let last_indexed_height =
+            snapshots.latest_snapshot().map(|(_, height)| height).ok();
+        if let Some(existing_height) = last_indexed_height {
+            if existing_height > start_height {
+                tracing::info!(
+                    "Found indexed state for {} up to block height {}; overriding requested start {}",
+                    contract_dir_name,
+                    existing_height,
+                    requested_start_height
+                );
+            }
+            start_height = start_height.max(existing_height);
+        }
+        snapshots.prune_from(start_height)?;
+        if start_height != requested_start_height {
             tracing::info!(
-                "Found indexed state up to block height {}; overriding requested start {}",
-                existing_height,
+                "Indexing {} will resume from block height {} (requested {})",
+                contract_dir_name,
+                start_height,
                 requested_start_height
             );
+        } else {
+            tracing::info!(
+                "Indexing {} will start from block height {}",
+                contract_dir_name,
+                start_height
+            );
         }
-        start_height = start_height.max(existing_height);
-    }
-    snapshots.prune_from(start_height)?;
-    if start_height != requested_start_height {
-        tracing::info!(
-            "Indexer will resume from block height {} (requested {})",
-            start_height,
-            requested_start_height
-        );
-    } else {
-        tracing::info!("Indexer will start from block height {}", start_height);
-    }
-    let should_backfill_deployment_block = record_used.is_some()
-        && last_indexed_height.is_none()
-        && override_start_height.is_none();
-    let event_start_height = if should_backfill_deployment_block {
-        start_height.saturating_sub(1)
-    } else {
-        start_height
-    };
-    if should_backfill_deployment_block && event_start_height != start_height {
+        let should_backfill_deployment_block = target.record.is_some()
+            && last_indexed_height.is_none()
+            && override_start_height.is_none();
+        let event_start_height = if should_backfill_deployment_block {
+            start_height.saturating_sub(1)
+        } else {
+            start_height
+        };
+        if should_backfill_deployment_block && event_start_height != start_height {
+            tracing::info!(
+                "Fuel receipts stream will backfill from block height {} to include the deployment block {}",
+                event_start_height,
+                start_height,
+            );
+        }
+
+        let archive_path = match &args.archive_path {
+            Some(path) => path.clone(),
+            None if shared => data_root
+                .join("archives")
+                .join(format!("{contract_dir_name}.jsonl")),
+            None => data_root.join("event_archive.jsonl"),
+        };
+        if let Some(parent) = archive_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // The stream restarts at `event_start_height`, so anything archived from there
+        // on will be received again
+        let archive = EventArchive::open(&archive_path, event_start_height)?;
         tracing::info!(
-            "Fuel receipts stream will backfill from block height {} to include the deployment block {}",
-            event_start_height,
-            start_height,
+            "Archiving event batches for {} to {}",
+            contract_dir_name,
+            archive_path.display()
         );
+        deployments.push((snapshots, metadata, event_start_height, archive));
     }
-    let start_block_height: BlockHeight = event_start_height.into();
 
+    // The shared stream starts early enough for the deployment furthest behind; the
+    // others skip whatever they have already indexed
+    let stream_start_height = deployments
+        .iter()
+        .map(|(_, _, event_start_height, _)| *event_start_height)
+        .min()
+        .unwrap_or_default();
+    let start_block_height: BlockHeight = stream_start_height.into();
     let database_config = DatabaseConfig {
         cache_capacity: None,
         max_fds: 512,
         columns_policy: ColumnsPolicy::Lazy,
     };
-    let mut indexer_config = Config::new(start_block_height, false, args.graphql_url);
+    let mut indexer_config = Config::new(start_block_height, false, graphql_url.clone());
     if let Some(concurrency) = args.block_request_concurrency {
         indexer_config.blocks_request_concurrency = concurrency;
     }
-
+    // The receipt handler must be `Copy`, so events of contracts no deployment indexes
+    // are dropped by the shared event source instead
     let events = FuelIndexerEventSource::new(
-        parse_event_logs,
+        parse_scoped_event_logs,
         event_data_path.clone(),
         database_config,
         indexer_config,
         start_block_height,
     )
     .await?;
-    let api = ActixQueryApi::new(args.port).await?;
-    let mut app = App::new(events, api, snapshots, metadata, contract_id);
+    let mut shared_events = SharedEventSource::new(events);
+    let rejected_receipts = shared_events.rejected_receipts();
 
-    tracing::info!("Starting indexer service");
-    loop {
-        let interrupt = handle_interupt();
-        match app.run(interrupt).await? {
-            RunState::Continue => continue,
-            RunState::Exit => {
-                tracing::info!("Exiting indexer service");
-                return Ok(());
-            }
-        }
+    // Storage handles are cheap to clone and only see committed blocks, so the API can
+    // read them directly while the event loops keep writing
+    let readers = targets
+        .iter()
+        .zip(&deployments)
+        .map(|(target, (snapshots, metadata, ..))| {
+            let reader = StorageReader::new(snapshots.clone(), metadata.clone());
+            (target.contract_id, reader)
+        })
+        .collect();
+    let apis = ActixQueryApi::with_deployments(args.port, readers).await?;
+    let readiness = apis[0].readiness();
+    readiness.set_max_lag(args.max_ready_lag);
+    // Clones share the same storage, so pruning one prunes what its event loop writes
+    let compacted_storages: Vec<_> = deployments
+        .iter()
+        .map(|(snapshots, ..)| snapshots.clone())
+        .collect();
+    let retention = RetentionPolicy {
+        reorg_window: args.overview_retention_blocks,
+    };
+
+    let mut apps = Vec::with_capacity(targets.len());
+    for ((target, api), (snapshots, metadata, event_start_height, archive)) in
+        targets.iter().zip(apis).zip(deployments)
+    {
+        let events = ArchivingEventSource::new(
+            shared_events.subscribe(target.contract_id, event_start_height),
+            archive,
+        );
+        let metrics = api.metrics();
+        let app = App::new(events, api, snapshots, metadata, target.contract_id)
+            .with_metrics(metrics);
+        apps.push((target.contract_id, app));
     }
+
+    tracing::info!("Starting indexer service for {} deployment(s)", apps.len());
+    // Event loops share this thread, so none of their state needs to be `Send`
+    let local = LocalSet::new();
+    let result = local
+        .run_until(async move {
+            tokio::task::spawn_local(poll_node_height(graphql_url, readiness));
+            tokio::task::spawn_local(compact_overviews(compacted_storages, retention));
+            tokio::task::spawn_local(async move {
+                if let Err(e) = shared_events.run().await {
+                    tracing::error!("Shared receipts stream failed: {e:?}");
+                }
+            });
+            let (stop, shutdown) = watch::channel(false);
+            let stop = Arc::new(stop);
+            let interrupt_stop = stop.clone();
+            tokio::task::spawn_local(async move {
+                handle_interupt().await;
+                let _ = interrupt_stop.send(true);
+            });
+
+            let handles: Vec<_> = apps
+                .into_iter()
+                .map(|(contract_id, app)| {
+                    let stop = stop.clone();
+                    let shutdown = shutdown.clone();
+                    tokio::task::spawn_local(async move {
+                        let result = index(app, shutdown).await;
+                        // One deployment failing takes the others down with it
+                        let _ = stop.send(true);
+                        result.with_context(|| format!("indexing {contract_id:#x}"))
+                    })
+                })
+                .collect();
+            let mut result = Ok(());
+            for handle in handles {
+                let outcome = handle.await.context("indexer event loop panicked")?;
+                result = result.and(outcome);
+            }
+            result
+        })
+        .await;
+    tracing::info!(
+        "Exiting indexer service ({} receipts from other contracts rejected)",
+        rejected_receipts.count()
+    );
+    result
 }
@@ -17,6 +17,10 @@ pub struct OverviewSnapshot {
     pub(crate) chips_owed: u64,
     pub(crate) current_block_height: u32,
     pub(crate) next_roll_height: Option<u32>,
+    /// Unix timestamp (seconds) of `next_roll_height`, estimated while the block is
+    /// still in the future
+    #[serde(default)]
+    pub(crate) next_roll_time: Option<u64>,
     #[serde(default)]
     pub(crate) roll_frequency: Option<u32>,
     #[serde(default)]
@@ -108,6 +112,7 @@ impl Default for OverviewSnapshot {
             chips_owed: 0,
             current_block_height: 0,
             next_roll_height: None,
+            next_roll_time: None,
             roll_frequency: None,
             first_roll_height: None,
             rewards: Vec::new(),
@@ -212,6 +217,40 @@ pub struct HistoricalAccountSnapshot {
     pub snapshot: AccountSnapshot,
 }
 
+/// One player's results in one game, the building block for leaderboards
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerGameStats {
+    pub chips_wagered: u64,
+    /// Chips paid out by `ClaimRewardsEvent`s
+    pub chips_won: u64,
+    /// Whether the player's claim paid out any chips or straps
+    pub hit: bool,
+    /// Highest level strap won in the game, 0 if none
+    pub highest_strap_level: u8,
+}
+
+/// Compact view of a finished game, for listing game history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoricalGameSummary {
+    pub game_id: u32,
+    pub roll_count: u32,
+    pub last_roll: Option<Roll>,
+    pub strap_rewards: Vec<(Roll, Strap, u64)>,
+    pub player_count: u32,
+}
+
+impl From<&HistoricalSnapshot> for HistoricalGameSummary {
+    fn from(snapshot: &HistoricalSnapshot) -> Self {
+        Self {
+            game_id: snapshot.game_id,
+            roll_count: snapshot.rolls.len() as u32,
+            last_roll: snapshot.rolls.last().copied(),
+            strap_rewards: snapshot.strap_rewards.clone(),
+            player_count: snapshot.accounts.len() as u32,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ActiveModifier {
     pub roll_index: u32,
@@ -229,6 +268,53 @@ impl ActiveModifier {
     }
 }
 
+// Per-game progress tracked between `NewGameEvent`s, persisted so a restart mid-game
+// can still produce a complete historical snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameWorkingState {
+    pub historical_modifiers: Vec<ActiveModifier>,
+    pub modifier_triggered: Vec<Modifier>,
+    pub modifier_purchased: Vec<Modifier>,
+}
+
+// Movement of chips in or out of the house pot, used to reconcile `pot_size`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HouseLedgerEntry {
+    Fund {
+        amount: u64,
+        funder: Identity,
+    },
+    Withdrawal {
+        amount: u64,
+        to: Identity,
+    },
+    FailedWithdrawal {
+        requested_amount: u64,
+        available_amount: u64,
+        to: Identity,
+    },
+}
+
+// Straps of one kind, level and modifier moving into or out of players' hands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrapSupplyChange {
+    pub strap: Strap,
+    pub kind: StrapSupplyChangeKind,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StrapSupplyChangeKind {
+    /// Paid out by a `ClaimRewardsEvent`, upgrades included
+    Minted,
+    /// Bet with a `PlaceStrapBetEvent` and held by the contract
+    Escrowed,
+    /// A winning strap bet, given up for the next level
+    UpgradeConsumed,
+    /// The next level strap a winning strap bet pays out
+    UpgradeProduced,
+}
+
 impl HistoricalSnapshot {
     pub fn new(game_id: u32, rolls: Vec<Roll>, modifiers: Vec<ActiveModifier>) -> Self {
         Self {
@@ -239,6 +325,20 @@ impl HistoricalSnapshot {
             accounts: Vec::new(),
         }
     }
+
+    /// Replace `identity`'s entry in the results table, adding one if they had none
+    pub fn upsert_account(&mut self, identity: Identity, snapshot: AccountSnapshot) {
+        match self
+            .accounts
+            .iter_mut()
+            .find(|entry| entry.identity == identity)
+        {
+            Some(entry) => entry.snapshot = snapshot,
+            None => self
+                .accounts
+                .push(HistoricalAccountSnapshot { identity, snapshot }),
+        }
+    }
 }
 
 pub fn all_rolls() -> Vec<Roll> {